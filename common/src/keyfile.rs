@@ -0,0 +1,271 @@
+//! The line format of SRTP key stores and key bundles, one key per line:
+//! `<camera> <key id> <cipher> <activation unix time> <hex key>`.
+
+use crate::srtp::{SrtpCipher, parse_srtp_key};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One SRTP master key of a camera. `key_id` is unique per camera and is sent
+/// as the MKI so receivers can tell which key protected a packet.
+#[derive(Clone, Debug)]
+pub struct KeyEntry {
+    pub camera: usize,
+    pub key_id: u32,
+    pub cipher: SrtpCipher,
+    pub activates_at: u64,
+    pub key: String,
+}
+
+pub fn read_key_file(path: &Path) -> Result<Vec<KeyEntry>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let mut entries = Vec::new();
+    for (line_number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = || {
+            format!(
+                "{} line {}: invalid key entry",
+                path.display(),
+                line_number + 1
+            )
+        };
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(invalid());
+        }
+
+        let cipher = SrtpCipher::from_name(fields[2]).ok_or_else(invalid)?;
+        entries.push(KeyEntry {
+            camera: fields[0].parse().map_err(|_| invalid())?,
+            key_id: fields[1].parse().map_err(|_| invalid())?,
+            cipher,
+            activates_at: fields[3].parse().map_err(|_| invalid())?,
+            key: parse_srtp_key(cipher, fields[4])
+                .map_err(|e| format!("{} line {}: {}", path.display(), line_number + 1, e))?,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Replaces the file at `path` with `entries`. The keys are written to a
+/// temporary file only the current user can read, flushed to disk and then
+/// renamed over the old file, so a crash never leaves a truncated key file.
+pub fn write_key_file<'a>(
+    path: &Path,
+    description: &str,
+    entries: impl Iterator<Item = &'a KeyEntry>,
+) -> Result<(), String> {
+    let mut content = format!(
+        "# SRTP {}\n# camera key_id cipher activates_at key\n",
+        description
+    );
+    for entry in entries {
+        content.push_str(&format!(
+            "{} {} {} {} {}\n",
+            entry.camera,
+            entry.key_id,
+            entry.cipher.name(),
+            entry.activates_at,
+            entry.key
+        ));
+    }
+
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = PathBuf::from(temp_name);
+    let failed = |e: std::io::Error| format!("Failed to write {}: {}", path.display(), e);
+
+    // A leftover from an earlier crash may have other permissions.
+    let _ = fs::remove_file(&temp_path);
+    let written = create_private(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(content.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(failed(e));
+    }
+    Ok(())
+}
+
+/// Creates a new file that only its owner can read or write.
+#[cfg(unix)]
+fn create_private(path: &Path) -> std::io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+}
+
+/// Creates a new file that only its owner (and the system) can open, with a
+/// protected DACL so the directory's permissions are not inherited.
+#[cfg(windows)]
+fn create_private(path: &Path) -> std::io::Result<File> {
+    use std::os::windows::io::AsRawHandle;
+    use std::ptr;
+    use winapi::shared::sddl::{
+        ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+    };
+    use winapi::um::accctrl::SE_FILE_OBJECT;
+    use winapi::um::aclapi::SetSecurityInfo;
+    use winapi::um::securitybaseapi::GetSecurityDescriptorDacl;
+    use winapi::um::winbase::LocalFree;
+    use winapi::um::winnt::{
+        DACL_SECURITY_INFORMATION, PACL, PROTECTED_DACL_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR,
+    };
+
+    let file = OpenOptions::new().write(true).create_new(true).open(path)?;
+
+    // Full access for the file's owner and for SYSTEM, nobody else.
+    let sddl: Vec<u16> = "D:P(A;;FA;;;OW)(A;;FA;;;SY)\0".encode_utf16().collect();
+    let mut descriptor: PSECURITY_DESCRIPTOR = ptr::null_mut();
+    let converted = unsafe {
+        ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            sddl.as_ptr(),
+            SDDL_REVISION_1 as u32,
+            &mut descriptor,
+            ptr::null_mut(),
+        )
+    };
+    if converted == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let mut present = 0;
+    let mut defaulted = 0;
+    let mut dacl: PACL = ptr::null_mut();
+    let result = unsafe {
+        if GetSecurityDescriptorDacl(descriptor, &mut present, &mut dacl, &mut defaulted) == 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            match SetSecurityInfo(
+                file.as_raw_handle() as _,
+                SE_FILE_OBJECT,
+                DACL_SECURITY_INFORMATION | PROTECTED_DACL_SECURITY_INFORMATION,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                dacl,
+                ptr::null_mut(),
+            ) {
+                0 => Ok(()),
+                error => Err(std::io::Error::from_raw_os_error(error as i32)),
+            }
+        }
+    };
+    unsafe {
+        LocalFree(descriptor);
+    }
+    result.map(|_| file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const KEY: &str = "00112233445566778899AABBCCDDEEFF00112233445566778899AABBCCDD";
+
+    fn temp_path(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        std::env::temp_dir().join(format!("keyfile_{}_{}_{}", std::process::id(), nanos, name))
+    }
+
+    fn entry(camera: usize, key_id: u32) -> KeyEntry {
+        KeyEntry {
+            camera,
+            key_id,
+            cipher: SrtpCipher::Aes128Icm,
+            activates_at: 1_700_000_000 + key_id as u64,
+            key: KEY.to_string(),
+        }
+    }
+
+    #[test]
+    fn reads_back_written_keys() {
+        let path = temp_path("roundtrip.txt");
+        let entries = [entry(0, 1), entry(2, 7)];
+        write_key_file(&path, "test keys", entries.iter()).unwrap();
+
+        let read = read_key_file(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[1].camera, 2);
+        assert_eq!(read[1].key_id, 7);
+        assert_eq!(read[1].cipher, SrtpCipher::Aes128Icm);
+        assert_eq!(read[1].activates_at, 1_700_000_007);
+        assert_eq!(read[1].key, KEY);
+    }
+
+    #[test]
+    fn replaces_the_file_and_leaves_no_temporary() {
+        let path = temp_path("replace.txt");
+        fs::write(&path, "old content\n").unwrap();
+        write_key_file(&path, "test keys", [entry(1, 3)].iter()).unwrap();
+
+        let mut temp_name = path.as_os_str().to_owned();
+        temp_name.push(".tmp");
+        let read = read_key_file(&path);
+        let temp_exists = PathBuf::from(temp_name).exists();
+        let _ = fs::remove_file(&path);
+        assert_eq!(read.map(|entries| entries.len()), Ok(1));
+        assert!(!temp_exists);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_keys_private_to_the_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_path("private.txt");
+        write_key_file(&path, "test keys", [entry(0, 1)].iter()).unwrap();
+        let mode = fs::metadata(&path).ok().map(|m| m.permissions().mode());
+        let _ = fs::remove_file(&path);
+        assert_eq!(mode.map(|mode| mode & 0o777), Some(0o600));
+    }
+
+    #[test]
+    fn reports_the_line_of_a_bad_key() {
+        let path = temp_path("bad_key.txt");
+        fs::write(
+            &path,
+            format!(
+                "# keys\n0 1 aes-128-icm 0 {}\n0 2 aes-128-icm 0 {}\n",
+                KEY,
+                &KEY[2..]
+            ),
+        )
+        .unwrap();
+
+        let error = read_key_file(&path).err().unwrap_or_default();
+        let _ = fs::remove_file(&path);
+        assert!(
+            error.contains("line 3: SRTP key for aes-128-icm"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        let path = temp_path("malformed.txt");
+        fs::write(&path, format!("0 1 aes-128-icm {}\n", KEY)).unwrap();
+        let error = read_key_file(&path).err().unwrap_or_default();
+        let _ = fs::remove_file(&path);
+        assert!(error.contains("line 1: invalid key entry"), "{}", error);
+    }
+}
@@ -0,0 +1,46 @@
+//! Code the sender and receiver share: the stream settings both ends have to
+//! agree on, SRTP keys and their files, and helpers for the processes they
+//! launch.
+
+pub mod keyfile;
+pub mod process;
+pub mod srtp;
+pub mod stream;
+
+use std::net::IpAddr;
+
+/// Parses an IPv4 or IPv6 address as typed by the user. IPv6 addresses may be
+/// written with or without surrounding brackets (`[::1]` or `::1`).
+pub fn parse_ip_address(text: &str) -> Result<IpAddr, String> {
+    let trimmed = text.trim();
+    let unbracketed = trimmed
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .unwrap_or(trimmed);
+
+    unbracketed
+        .parse::<IpAddr>()
+        .map_err(|_| format!("'{}' is not a valid IPv4 or IPv6 address", trimmed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn parses_ip_addresses() {
+        assert_eq!(
+            parse_ip_address(" 192.168.0.20 "),
+            Ok(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 20)))
+        );
+        assert_eq!(parse_ip_address("::1"), Ok(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+        assert_eq!(
+            parse_ip_address("[::1]"),
+            Ok(IpAddr::V6(Ipv6Addr::LOCALHOST))
+        );
+        assert!(parse_ip_address("[::1").is_err());
+        assert!(parse_ip_address("192.168.0").is_err());
+        assert!(parse_ip_address("localhost").is_err());
+    }
+}
@@ -0,0 +1,31 @@
+//! Helpers for the `gst-launch-1.0` windows the sender and receiver open as
+//! processes of their own.
+
+use std::process::Command;
+
+/// Runs a command line through the platform's shell: `cmd /C` on Windows,
+/// `sh -c` elsewhere, where `exec` hands the shell's PID to the command so
+/// killing it stops the command itself.
+pub fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", &format!("exec {}", command)]);
+        shell
+    }
+}
+
+/// Kills a process started with `shell`, along with the command `cmd`
+/// started for it on Windows.
+pub fn kill_tree(pid: u32) {
+    if cfg!(windows) {
+        let _ = Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &pid.to_string()])
+            .output();
+    } else {
+        let _ = Command::new("kill").arg(pid.to_string()).output();
+    }
+}
@@ -0,0 +1,113 @@
+//! SRTP ciphers and the master keys both ends are given.
+
+/// SRTP protection profile shared by the media and FEC flows of a camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SrtpCipher {
+    Aes128Icm,
+    Aes256Icm,
+    Aes128Gcm,
+    Aes256Gcm,
+}
+
+impl SrtpCipher {
+    pub const ALL: [SrtpCipher; 4] = [
+        SrtpCipher::Aes128Icm,
+        SrtpCipher::Aes256Icm,
+        SrtpCipher::Aes128Gcm,
+        SrtpCipher::Aes256Gcm,
+    ];
+
+    pub fn from_name(name: &str) -> Option<SrtpCipher> {
+        SrtpCipher::ALL.iter().copied().find(|c| c.name() == name)
+    }
+
+    /// Cipher name as understood by `srtpenc`/`srtpdec`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SrtpCipher::Aes128Icm => "aes-128-icm",
+            SrtpCipher::Aes256Icm => "aes-256-icm",
+            SrtpCipher::Aes128Gcm => "aes-128-gcm",
+            SrtpCipher::Aes256Gcm => "aes-256-gcm",
+        }
+    }
+
+    /// GCM authenticates on its own; ICM is paired with HMAC-SHA1-80.
+    pub fn auth(&self) -> &'static str {
+        match self {
+            SrtpCipher::Aes128Icm | SrtpCipher::Aes256Icm => "hmac-sha1-80",
+            SrtpCipher::Aes128Gcm | SrtpCipher::Aes256Gcm => "null",
+        }
+    }
+
+    /// Master key plus salt length in bytes.
+    pub fn key_len(&self) -> usize {
+        match self {
+            SrtpCipher::Aes128Icm => 30,
+            SrtpCipher::Aes256Icm => 46,
+            SrtpCipher::Aes128Gcm => 28,
+            SrtpCipher::Aes256Gcm => 44,
+        }
+    }
+}
+
+/// Validates a hex key typed by the user against the cipher's key length and
+/// returns it normalised to uppercase hex without separators.
+pub fn parse_srtp_key(cipher: SrtpCipher, text: &str) -> Result<String, String> {
+    let hex: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ':' && *c != '-')
+        .collect();
+
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("SRTP key must be hexadecimal".to_string());
+    }
+
+    if hex.len() != cipher.key_len() * 2 {
+        return Err(format!(
+            "SRTP key for {} must be {} bytes ({} hex digits), got {} hex digits",
+            cipher.name(),
+            cipher.key_len(),
+            cipher.key_len() * 2,
+            hex.len()
+        ));
+    }
+
+    Ok(hex.to_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_ciphers_by_name() {
+        for cipher in SrtpCipher::ALL {
+            assert_eq!(SrtpCipher::from_name(cipher.name()), Some(cipher));
+        }
+        assert_eq!(SrtpCipher::from_name("aes-192-icm"), None);
+    }
+
+    #[test]
+    fn normalises_srtp_keys() {
+        let key = "00112233445566778899aabbccddeeff00112233445566778899aabbccdd";
+        assert_eq!(
+            parse_srtp_key(SrtpCipher::Aes128Icm, key),
+            Ok(key.to_uppercase())
+        );
+        assert_eq!(
+            parse_srtp_key(
+                SrtpCipher::Aes128Icm,
+                &format!(" {}:{}-{} ", &key[..20], &key[20..40], &key[40..])
+            ),
+            Ok(key.to_uppercase())
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_srtp_keys() {
+        let key = "00112233445566778899aabbccddeeff00112233445566778899aabbccdd";
+        assert!(parse_srtp_key(SrtpCipher::Aes256Icm, key).is_err());
+        assert!(parse_srtp_key(SrtpCipher::Aes128Icm, &key[2..]).is_err());
+        assert!(parse_srtp_key(SrtpCipher::Aes128Icm, &key.replace('a', "g")).is_err());
+    }
+}
@@ -0,0 +1,553 @@
+//! Stream settings both ends have to agree on: payload formats, forward
+//! error correction, transports and the RTP payload types they use.
+
+use std::time::Duration;
+
+/// RTP payload type of retransmitted packets.
+pub const RTX_PAYLOAD_TYPE: u8 = 97;
+
+/// RTP payload types of ULPFEC packets and of the RED packets wrapping the
+/// stream.
+pub const ULPFEC_PAYLOAD_TYPE: u8 = 122;
+pub const RED_PAYLOAD_TYPE: u8 = 123;
+
+/// Default ULPFEC protection, in percent of packets, for all packets and for
+/// the important (key frame) ones.
+pub const DEFAULT_ULPFEC_PERCENTAGE: u32 = 20;
+pub const DEFAULT_ULPFEC_PERCENTAGE_IMPORTANT: u32 = 40;
+
+/// Forward error correction of a stream. RaptorQ sends its repair packets
+/// to the FEC port; ULPFEC packets travel inside the RTP stream, wrapped in
+/// RED, which peers without the gst-plugins-rs RaptorQ elements can decode.
+/// The ULPFEC percentages only matter to the sender.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FecScheme {
+    RaptorQ,
+    Ulpfec {
+        percentage: u32,
+        percentage_important: u32,
+    },
+}
+
+impl FecScheme {
+    pub const ALL: [FecScheme; 2] = [
+        FecScheme::RaptorQ,
+        FecScheme::Ulpfec {
+            percentage: DEFAULT_ULPFEC_PERCENTAGE,
+            percentage_important: DEFAULT_ULPFEC_PERCENTAGE_IMPORTANT,
+        },
+    ];
+
+    /// Parses FEC written as `raptorq`, or `ulpfec` optionally followed by
+    /// `:<percentage>[,<percentage for key frames>]`.
+    pub fn parse(text: &str) -> Result<FecScheme, String> {
+        let text = text.trim();
+        if text.eq_ignore_ascii_case("raptorq") {
+            return Ok(FecScheme::RaptorQ);
+        }
+
+        let settings = match text.split_once(':') {
+            Some((scheme, settings)) if scheme.eq_ignore_ascii_case("ulpfec") => settings,
+            None if text.eq_ignore_ascii_case("ulpfec") => "",
+            _ => {
+                return Err(format!(
+                    "Unknown FEC scheme '{}'; use raptorq or ulpfec",
+                    text
+                ));
+            }
+        };
+        FecScheme::ulpfec(settings)
+    }
+
+    /// ULPFEC with protection written as `<percentage>[,<percentage for key
+    /// frames>]`; empty text gives the defaults.
+    pub fn ulpfec(settings: &str) -> Result<FecScheme, String> {
+        let percent = |value: &str| match value.trim().parse::<u32>() {
+            Ok(percent) if percent <= 100 => Ok(percent),
+            _ => Err(format!(
+                "'{}' is not a percentage between 0 and 100",
+                value.trim()
+            )),
+        };
+
+        let (percentage, percentage_important) = match settings.trim() {
+            "" => (
+                DEFAULT_ULPFEC_PERCENTAGE,
+                DEFAULT_ULPFEC_PERCENTAGE_IMPORTANT,
+            ),
+            settings => match settings.split_once(',') {
+                Some((all, important)) => (percent(all)?, percent(important)?),
+                None => {
+                    let percentage = percent(settings)?;
+                    (percentage, percentage)
+                }
+            },
+        };
+        Ok(FecScheme::Ulpfec {
+            percentage,
+            percentage_important,
+        })
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FecScheme::RaptorQ => "RaptorQ",
+            FecScheme::Ulpfec { .. } => "ULPFEC + RED",
+        }
+    }
+
+    /// Whether repair packets go to the FEC port as a flow of their own.
+    pub fn has_repair_flow(&self) -> bool {
+        matches!(self, FecScheme::RaptorQ)
+    }
+
+    /// Element description the sender's `rtpbin` creates the encoder of
+    /// session 0 from.
+    pub fn encoder(&self) -> String {
+        match self {
+            FecScheme::RaptorQ => "raptorqenc mtu=1356 symbol-size=1344 protected-packets=10 \
+repair-packets=1000 repair-window=200"
+                .to_string(),
+            FecScheme::Ulpfec {
+                percentage,
+                percentage_important,
+            } => format!(
+                "rtpulpfecenc pt={} percentage={} percentage-important={} multipacket=true ! \
+rtpredenc pt={} allow-no-red-blocks=true",
+                ULPFEC_PAYLOAD_TYPE, percentage, percentage_important, RED_PAYLOAD_TYPE
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for FecScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FecScheme::RaptorQ => write!(f, "RaptorQ"),
+            FecScheme::Ulpfec {
+                percentage,
+                percentage_important,
+            } => write!(
+                f,
+                "ULPFEC+RED {}% ({}% key frames)",
+                percentage, percentage_important
+            ),
+        }
+    }
+}
+
+/// How a stream travels from the sender to the receiver.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Transport {
+    /// RTP over UDP, protected by the stream's FEC.
+    Udp,
+    /// RTP over UDP, with the same RTP and FEC packets also served over TCP
+    /// by the sender. The receiver falls back to connecting once no UDP
+    /// packet has arrived for this long.
+    UdpWithTcpFallback(Duration),
+    /// RTP and FEC over TCP, framed as in RFC 4571, one connection each.
+    Tcp(TcpMode),
+    /// The MPEG-TS over SRT, which retransmits lost packets itself.
+    Srt(SrtConfig),
+}
+
+/// Time without UDP packets after which a receiver falls back to TCP unless
+/// another is given.
+pub const DEFAULT_TCP_FALLBACK: Duration = Duration::from_secs(5);
+
+impl Transport {
+    /// Parses a transport written as `udp`, `udp+tcp[:<seconds>]`,
+    /// `tcp:connect`, `tcp:listen`, or `srt:` followed by SRT settings as
+    /// `SrtConfig::parse` takes them.
+    pub fn parse(text: &str) -> Result<Transport, String> {
+        let (transport, settings) = text.trim().split_once(':').unwrap_or((text.trim(), ""));
+        match transport.to_ascii_lowercase().as_str() {
+            "udp" if settings.is_empty() => Ok(Transport::Udp),
+            "udp+tcp" => Transport::tcp_fallback(settings),
+            "tcp" => TcpMode::from_name(settings)
+                .map(Transport::Tcp)
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown TCP mode '{}'; use connect or listen",
+                        settings.trim()
+                    )
+                }),
+            "srt" => Ok(Transport::Srt(SrtConfig::parse(settings)?)),
+            _ => Err(format!(
+                "Unknown transport '{}'; use udp, udp+tcp[:<seconds>], tcp:<connect|listen> \
+or srt:<mode>[:<latency>[:<passphrase>]]",
+                text.trim()
+            )),
+        }
+    }
+
+    /// UDP falling back to TCP after the seconds in `settings`; empty text
+    /// gives the default.
+    pub fn tcp_fallback(settings: &str) -> Result<Transport, String> {
+        let after = match settings.trim() {
+            "" => DEFAULT_TCP_FALLBACK,
+            seconds => match seconds.parse::<u64>() {
+                Ok(seconds) if seconds > 0 => Duration::from_secs(seconds),
+                _ => {
+                    return Err(format!(
+                        "'{}' is not a number of seconds for the TCP fallback",
+                        seconds
+                    ));
+                }
+            },
+        };
+        Ok(Transport::UdpWithTcpFallback(after))
+    }
+
+    /// Whether the stream travels as RTP, over UDP or TCP.
+    pub fn is_rtp(&self) -> bool {
+        !matches!(self, Transport::Srt(_))
+    }
+}
+
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transport::Udp => write!(f, "UDP"),
+            Transport::UdpWithTcpFallback(_) => write!(f, "UDP with TCP fallback"),
+            Transport::Tcp(mode) => write!(f, "TCP ({})", mode.name()),
+            Transport::Srt(srt) => write!(f, "SRT {} ({} ms)", srt.mode.name(), srt.latency_ms),
+        }
+    }
+}
+
+/// Which end of a TCP transport opens the connections: one end connects to
+/// the other, which listens. Each end picks its own mode, so a sender that
+/// listens pairs with a receiver that connects, which also works through a
+/// firewall on the receiver's side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TcpMode {
+    Connect,
+    Listen,
+}
+
+impl TcpMode {
+    pub const ALL: [TcpMode; 2] = [TcpMode::Connect, TcpMode::Listen];
+
+    pub fn from_name(name: &str) -> Option<TcpMode> {
+        TcpMode::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TcpMode::Connect => "connect",
+            TcpMode::Listen => "listen",
+        }
+    }
+}
+
+/// Which side of an SRT connection sets it up: the caller connects to a
+/// listener, and two rendezvous peers connect to each other.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SrtMode {
+    Caller,
+    Listener,
+    Rendezvous,
+}
+
+impl SrtMode {
+    pub const ALL: [SrtMode; 3] = [SrtMode::Caller, SrtMode::Listener, SrtMode::Rendezvous];
+
+    pub fn from_name(name: &str) -> Option<SrtMode> {
+        SrtMode::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Mode name as understood by `srtsink`/`srtsrc`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SrtMode::Caller => "caller",
+            SrtMode::Listener => "listener",
+            SrtMode::Rendezvous => "rendezvous",
+        }
+    }
+}
+
+/// Latency SRT uses for retransmissions unless one is given.
+pub const DEFAULT_SRT_LATENCY_MS: u32 = 125;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SrtConfig {
+    pub mode: SrtMode,
+    /// Time packets are buffered for retransmissions to arrive in; the
+    /// larger of the two ends' values is used.
+    pub latency_ms: u32,
+    /// Encrypts the stream when set; both ends need the same one.
+    pub passphrase: String,
+}
+
+impl SrtConfig {
+    /// Parses SRT settings written as `<mode>[:<latency ms>[:<passphrase>]]`.
+    pub fn parse(text: &str) -> Result<SrtConfig, String> {
+        let (mode_name, settings) = text.split_once(':').unwrap_or((text, ""));
+        let mode = SrtMode::from_name(mode_name).ok_or_else(|| {
+            format!(
+                "Unknown SRT mode '{}'; use caller, listener or rendezvous",
+                mode_name.trim()
+            )
+        })?;
+        SrtConfig::with_settings(mode, settings)
+    }
+
+    /// SRT in `mode` with settings written as `<latency ms>[:<passphrase>]`;
+    /// empty text gives the default latency and no encryption.
+    pub fn with_settings(mode: SrtMode, settings: &str) -> Result<SrtConfig, String> {
+        let (latency, passphrase) = settings.split_once(':').unwrap_or((settings, ""));
+        let latency_ms = match latency.trim() {
+            "" => DEFAULT_SRT_LATENCY_MS,
+            latency => latency
+                .parse()
+                .map_err(|_| format!("'{}' is not an SRT latency in milliseconds", latency))?,
+        };
+
+        let passphrase = passphrase.trim().to_string();
+        // Kept to characters that pass through the command line unquoted.
+        let allowed = |c: char| c.is_ascii_alphanumeric() || "-_.+/=".contains(c);
+        if !passphrase.is_empty() && !(10..=79).contains(&passphrase.len()) {
+            return Err("An SRT passphrase needs 10 to 79 characters".to_string());
+        }
+        if !passphrase.chars().all(allowed) {
+            return Err(
+                "An SRT passphrase may only contain letters, digits and - _ . + / =".to_string(),
+            );
+        }
+
+        Ok(SrtConfig {
+            mode,
+            latency_ms,
+            passphrase,
+        })
+    }
+
+    /// `srtsink`/`srtsrc` properties for the connection on `port`. `remote`
+    /// is the peer a caller or rendezvous end connects to, `local` the
+    /// address a listener waits on; either may be empty for any.
+    pub fn properties(&self, remote: &str, local: &str, port: u16) -> String {
+        let host = match self.mode {
+            SrtMode::Listener => local,
+            SrtMode::Caller | SrtMode::Rendezvous => remote,
+        };
+        let host = if host.contains(':') {
+            format!("[{}]", host)
+        } else {
+            host.to_string()
+        };
+
+        let mut properties = format!(
+            "uri=\"srt://{}:{}\" mode={} latency={}",
+            host,
+            port,
+            self.mode.name(),
+            self.latency_ms
+        );
+        if self.mode == SrtMode::Rendezvous {
+            properties.push_str(&format!(" localport={}", port));
+        }
+        if !self.passphrase.is_empty() {
+            properties.push_str(&format!(" passphrase={}", self.passphrase));
+        }
+        properties
+    }
+}
+
+/// Video encoding and RTP packetization of a stream: H.264 in MPEG-TS, or
+/// the codec payloaded directly, which saves the TS headers and padding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PayloadFormat {
+    MpegTs,
+    H264,
+    H265,
+    Vp8,
+}
+
+impl PayloadFormat {
+    pub const ALL: [PayloadFormat; 4] = [
+        PayloadFormat::MpegTs,
+        PayloadFormat::H264,
+        PayloadFormat::H265,
+        PayloadFormat::Vp8,
+    ];
+
+    pub fn from_name(name: &str) -> Option<PayloadFormat> {
+        PayloadFormat::ALL
+            .iter()
+            .copied()
+            .find(|p| p.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PayloadFormat::MpegTs => "ts",
+            PayloadFormat::H264 => "h264",
+            PayloadFormat::H265 => "h265",
+            PayloadFormat::Vp8 => "vp8",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PayloadFormat::MpegTs => "MPEG-TS",
+            PayloadFormat::H264 => "H.264",
+            PayloadFormat::H265 => "H.265",
+            PayloadFormat::Vp8 => "VP8",
+        }
+    }
+
+    pub fn is_h264(&self) -> bool {
+        matches!(self, PayloadFormat::MpegTs | PayloadFormat::H264)
+    }
+
+    /// RTP payload type the payloader sends.
+    pub fn payload_type(&self) -> u8 {
+        match self {
+            PayloadFormat::MpegTs => 33,
+            PayloadFormat::H264 | PayloadFormat::H265 | PayloadFormat::Vp8 => 96,
+        }
+    }
+
+    pub fn encoding_name(&self) -> &'static str {
+        match self {
+            PayloadFormat::MpegTs => "mp2t",
+            PayloadFormat::H264 => "H264",
+            PayloadFormat::H265 => "H265",
+            PayloadFormat::Vp8 => "VP8",
+        }
+    }
+
+    /// RTP caps fields the sender's payloader produces.
+    pub fn rtp_caps(&self) -> String {
+        format!(
+            "encoding-name={}, payload={}",
+            self.encoding_name(),
+            self.payload_type()
+        )
+    }
+
+    /// Encoder for raw video.
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            PayloadFormat::MpegTs | PayloadFormat::H264 => {
+                "x264enc key-int-max=45 tune=zerolatency speed-preset=veryfast bitrate=2000"
+            }
+            PayloadFormat::H265 => {
+                "x265enc key-int-max=45 tune=zerolatency speed-preset=veryfast bitrate=2000"
+            }
+            PayloadFormat::Vp8 => {
+                "vp8enc deadline=1 keyframe-max-dist=45 target-bitrate=2000000 error-resilient=partitions"
+            }
+        }
+    }
+
+    /// Muxer and payloader producing the RTP packets. Native payloaders keep
+    /// packets to the size `rtpmp2tpay` makes (7 TS packets plus header), so
+    /// the FEC settings fit all formats.
+    pub fn payloader(&self) -> &'static str {
+        match self {
+            PayloadFormat::MpegTs => "mpegtsmux name=mux ! rtpmp2tpay",
+            PayloadFormat::H264 => "rtph264pay config-interval=-1 pt=96 mtu=1328",
+            PayloadFormat::H265 => "rtph265pay config-interval=-1 pt=96 mtu=1328",
+            PayloadFormat::Vp8 => "rtpvp8pay pt=96 mtu=1328",
+        }
+    }
+
+    pub fn depayloader(&self) -> &'static str {
+        match self {
+            PayloadFormat::MpegTs => "rtpmp2tdepay",
+            PayloadFormat::H264 => "rtph264depay",
+            PayloadFormat::H265 => "rtph265depay",
+            PayloadFormat::Vp8 => "rtpvp8depay",
+        }
+    }
+
+    /// Parser of the elementary video stream; VP8 needs none.
+    pub fn parser(&self) -> Option<&'static str> {
+        match self {
+            PayloadFormat::MpegTs | PayloadFormat::H264 => Some("h264parse"),
+            PayloadFormat::H265 => Some("h265parse"),
+            PayloadFormat::Vp8 => None,
+        }
+    }
+
+    pub fn decoder(&self) -> &'static str {
+        match self {
+            PayloadFormat::MpegTs | PayloadFormat::H264 => "avdec_h264 max-threads=4",
+            PayloadFormat::H265 => "avdec_h265 max-threads=4",
+            PayloadFormat::Vp8 => "vp8dec",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_transports() {
+        assert_eq!(Transport::parse(" UDP "), Ok(Transport::Udp));
+        assert_eq!(
+            Transport::parse("udp+tcp"),
+            Ok(Transport::UdpWithTcpFallback(DEFAULT_TCP_FALLBACK))
+        );
+        assert_eq!(
+            Transport::parse("udp+tcp:10"),
+            Ok(Transport::UdpWithTcpFallback(Duration::from_secs(10)))
+        );
+        assert_eq!(
+            Transport::parse("tcp:listen"),
+            Ok(Transport::Tcp(TcpMode::Listen))
+        );
+        assert_eq!(
+            Transport::parse("srt:caller"),
+            Ok(Transport::Srt(SrtConfig {
+                mode: SrtMode::Caller,
+                latency_ms: DEFAULT_SRT_LATENCY_MS,
+                passphrase: String::new(),
+            }))
+        );
+        assert_eq!(
+            Transport::parse("srt:rendezvous:200:0123456789abc"),
+            Ok(Transport::Srt(SrtConfig {
+                mode: SrtMode::Rendezvous,
+                latency_ms: 200,
+                passphrase: "0123456789abc".to_string(),
+            }))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_transports() {
+        assert!(Transport::parse("udp:5").is_err());
+        assert!(Transport::parse("udp+tcp:0").is_err());
+        assert!(Transport::parse("tcp").is_err());
+        assert!(Transport::parse("tcp:accept").is_err());
+        assert!(Transport::parse("srt:server").is_err());
+        assert!(Transport::parse("srt:caller:soon").is_err());
+        assert!(Transport::parse("srt:caller:200:short").is_err());
+        assert!(Transport::parse("quic").is_err());
+    }
+
+    #[test]
+    fn parses_fec_schemes() {
+        assert_eq!(FecScheme::parse("RaptorQ"), Ok(FecScheme::RaptorQ));
+        assert_eq!(FecScheme::parse("ulpfec"), Ok(FecScheme::ALL[1]));
+        assert_eq!(
+            FecScheme::parse("ulpfec:10,30"),
+            Ok(FecScheme::Ulpfec {
+                percentage: 10,
+                percentage_important: 30
+            })
+        );
+        assert!(FecScheme::parse("ulpfec:101").is_err());
+        assert!(FecScheme::parse("reed-solomon").is_err());
+    }
+}
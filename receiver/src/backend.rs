@@ -1,41 +1,89 @@
+use crate::dvr::{self, ReplayPlayer};
+use crate::frames::FrameOutput;
 use crate::gstreamer::GStreamerPipeline;
+use crate::keystore::KeyStore;
+use crate::mosaic::{self, MosaicLayout, MosaicView};
+use crate::overlay::{self, OverlaySender};
+use crate::snapshot::{self, SnapshotConfig};
+use crate::retention::{RetentionManager, RetentionPolicy};
 use crate::stats_collector::{StatsCollector, StreamStats};
-use crate::types::{CameraState, CameraConfig};
+use crate::types::{
+    CameraConfig, CameraState, ClipConfig, DvrConfig, FecScheme, MAX_DVR_MINUTES, PayloadFormat,
+    RecordingConfig, RecordingFormat, SrtMode, SrtpCipher, SrtpConfig, TcpMode, Transport,
+    parse_ip_address, parse_srtp_key, unix_now,
+};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const KEY_STORE_PATH: &str = "receiver_keys.txt";
 
 pub struct CameraBackend {
     cameras: Vec<CameraState>,
     pipelines: Vec<Option<GStreamerPipeline>>,
     stats_collectors: Vec<Option<Arc<std::sync::Mutex<StatsCollector>>>>,
+    key_store: Option<KeyStore>,
+    recordings: Vec<Option<RecordingConfig>>,
+    /// Recordings suspended by the retention manager for lack of disk space.
+    paused_recordings: Vec<Option<RecordingConfig>>,
+    retention: RetentionManager,
+    dvr: DvrConfig,
+    replays: Vec<Option<ReplayPlayer>>,
+    clip: ClipConfig,
+    clip_counts: Vec<u32>,
+    mosaic: Option<MosaicView>,
+    overlays: Vec<bool>,
+    audio_mutes: Vec<bool>,
+    overlay_sender: OverlaySender,
+    snapshot: SnapshotConfig,
+    frame_outputs: Vec<Option<FrameOutput>>,
 }
 
 impl CameraBackend {
     pub fn new() -> Self {
         let cameras = vec![
-            CameraState::new("192.168.0.105", "5000", "5002"),
-            CameraState::new("192.168.0.105", "5004", "5006"),
-            CameraState::new("192.168.0.105", "5008", "5010"),
-            CameraState::new("192.168.0.105", "5012", "5014"),
+            CameraState::new("192.168.0.105", "0.0.0.0", "5000", "5002"),
+            CameraState::new("192.168.0.105", "0.0.0.0", "5004", "5006"),
+            CameraState::new("192.168.0.105", "0.0.0.0", "5008", "5010"),
+            CameraState::new("192.168.0.105", "0.0.0.0", "5012", "5014"),
         ];
 
+        let key_store = match KeyStore::load(Path::new(KEY_STORE_PATH)) {
+            Ok(key_store) => Some(key_store),
+            Err(e) => {
+                println!("Warning: SRTP key store unavailable: {}", e);
+                None
+            }
+        };
+
+        let mut retention = RetentionManager::new(RetentionPolicy::default());
+        retention.start();
+
         Self {
             cameras,
             pipelines: vec![None, None, None, None],
             stats_collectors: vec![None, None, None, None],
+            key_store,
+            recordings: vec![None, None, None, None],
+            paused_recordings: vec![None, None, None, None],
+            retention,
+            dvr: DvrConfig::default(),
+            replays: vec![None, None, None, None],
+            clip: ClipConfig::default(),
+            clip_counts: vec![0; 4],
+            mosaic: None,
+            overlays: vec![false; 4],
+            audio_mutes: vec![false; 4],
+            overlay_sender: OverlaySender::new(),
+            snapshot: SnapshotConfig::default(),
+            frame_outputs: vec![None, None, None, None],
         }
     }
 
-    pub fn update_camera_config(
-        &mut self,
-        camera_index: usize,
-        sender_ip: &str,
-        rtp_port: &str,
-        fec_port: &str,
-    ) {
+    pub fn update_camera_config(&mut self, camera_index: usize, config: CameraConfig) {
         if camera_index < self.cameras.len() {
-            self.cameras[camera_index].config.sender_ip = sender_ip.to_string();
-            self.cameras[camera_index].config.rtp_port = rtp_port.to_string();
-            self.cameras[camera_index].config.fec_port = fec_port.to_string();
+            self.cameras[camera_index].config = config;
         }
     }
 
@@ -49,6 +97,135 @@ impl CameraBackend {
         }
 
         let camera = &self.cameras[camera_index];
+
+        let bind_address = parse_ip_address(&camera.config.bind_address)?;
+        let sender_ip = parse_ip_address(&camera.config.sender_ip);
+        if let Ok(sender_ip) = sender_ip {
+            if sender_ip.is_ipv4() != bind_address.is_ipv4() {
+                return Err(format!(
+                    "Bind address {} and sender {} use different IP versions",
+                    bind_address, sender_ip
+                ));
+            }
+        }
+
+        if camera.config.audio && camera.config.payload != PayloadFormat::MpegTs {
+            return Err(format!(
+                "Audio is carried in the MPEG-TS; it cannot be played with {} payloading",
+                camera.config.payload.label()
+            ));
+        }
+
+        if camera.config.source_specific {
+            if !bind_address.is_multicast() {
+                return Err("Source-specific multicast needs a multicast group as bind address".to_string());
+            }
+            if let Err(e) = sender_ip {
+                return Err(format!("Source-specific multicast needs the sender IP: {}", e));
+            }
+        }
+
+        if camera.config.retransmission {
+            if camera.config.fec != FecScheme::RaptorQ {
+                return Err("Retransmission is only combined with RaptorQ FEC".to_string());
+            }
+            if camera.config.srtp.is_some() {
+                return Err(
+                    "Retransmission feedback is sent as plain RTCP and cannot be used with SRTP"
+                        .to_string(),
+                );
+            }
+            if let Err(e) = sender_ip {
+                return Err(format!("Retransmission needs the sender IP to send NACKs to: {}", e));
+            }
+            camera.config.rtcp_ports()?;
+        }
+
+        if let Transport::Srt(srt) = &camera.config.transport {
+            if camera.config.payload != PayloadFormat::MpegTs {
+                return Err("SRT carries the MPEG-TS; choose MPEG-TS payloading".to_string());
+            }
+            if camera.config.srtp.is_some() {
+                return Err("SRT decrypts with its passphrase; turn SRTP off".to_string());
+            }
+            if camera.config.retransmission {
+                return Err("SRT retransmits lost packets itself".to_string());
+            }
+            if bind_address.is_multicast() {
+                return Err(
+                    "SRT is point to point and cannot receive from a multicast group".to_string(),
+                );
+            }
+            if srt.mode != SrtMode::Listener {
+                if let Err(e) = sender_ip {
+                    return Err(format!("SRT {} mode needs the sender IP: {}", srt.mode.name(), e));
+                }
+            }
+            camera
+                .config
+                .rtp_port
+                .trim()
+                .parse::<u16>()
+                .map_err(|_| format!("'{}' is not a valid SRT port", camera.config.rtp_port))?;
+        }
+
+        match camera.config.transport {
+            Transport::Tcp(mode) => {
+                if camera.config.retransmission {
+                    return Err("TCP delivers every packet; turn retransmission off".to_string());
+                }
+                if mode == TcpMode::Connect {
+                    if let Err(e) = sender_ip {
+                        return Err(format!("TCP connect mode needs the sender IP: {}", e));
+                    }
+                } else if bind_address.is_multicast() {
+                    return Err(
+                        "TCP is point to point and cannot listen on a multicast group".to_string(),
+                    );
+                }
+            }
+            Transport::UdpWithTcpFallback(_) => {
+                if let Err(e) = sender_ip {
+                    return Err(format!("The TCP fallback needs the sender IP: {}", e));
+                }
+            }
+            _ => {}
+        }
+
+        let mut config = camera.config.clone();
+        config.bind_address = bind_address.to_string();
+        if let Ok(sender_ip) = sender_ip {
+            config.sender_ip = sender_ip.to_string();
+        }
+        config.recording = self.recordings[camera_index].clone();
+        if let Some(recording) = &config.recording {
+            check_recording_format(config.payload, recording)?;
+        }
+        let is_vp8 = config.payload == PayloadFormat::Vp8;
+        config.frames = self.frame_outputs[camera_index].clone();
+        config.audio_muted = self.audio_mutes[camera_index];
+        let displayed = config.frames.as_ref().is_none_or(|output| output.display);
+        if self.mosaic.is_some() && displayed {
+            config.mosaic_port = Some(mosaic::mosaic_port(camera_index));
+        }
+        if self.dvr.minutes > 0 && is_vp8 {
+            println!("Camera {} - DVR buffer unavailable for VP8 video", camera_index + 1);
+        } else if self.dvr.minutes > 0 {
+            dvr::prepare(&self.dvr, camera_index)?;
+            config.dvr = Some(self.dvr.clone());
+        }
+        if let Some(srtp) = &camera.config.srtp {
+            config.srtp = if srtp.key.trim().is_empty() {
+                Some(self.key_store_srtp_config(camera_index, srtp.cipher)?)
+            } else {
+                Some(SrtpConfig {
+                    cipher: srtp.cipher,
+                    key: parse_srtp_key(srtp.cipher, &srtp.key)?,
+                    mki: None,
+                    additional_keys: Vec::new(),
+                })
+            };
+        }
         
         let rtp_port = camera.config.rtp_port.parse::<u16>().unwrap_or(5000);
         let fec_port = camera.config.fec_port.parse::<u16>().unwrap_or(5002);
@@ -63,7 +240,7 @@ impl CameraBackend {
         
         let mut pipeline = GStreamerPipeline::new(
             camera_index,
-            camera.config.clone(),
+            config,
             Arc::clone(&camera.receiving),
             Some(stats_collector_arc),
         );
@@ -73,6 +250,42 @@ impl CameraBackend {
         Ok(())
     }
 
+    fn key_store_srtp_config(&self, camera_index: usize, cipher: SrtpCipher) -> Result<SrtpConfig, String> {
+        let key_store = self
+            .key_store
+            .as_ref()
+            .ok_or("SRTP key store could not be loaded; see console output")?;
+
+        let srtp = key_store
+            .srtp_config(camera_index + 1, unix_now())
+            .ok_or_else(|| {
+                format!(
+                    "No SRTP keys for camera {} in the key store; import a key bundle from the sender",
+                    camera_index + 1
+                )
+            })?;
+
+        if srtp.cipher != cipher {
+            return Err(format!(
+                "Key store has {} keys for camera {}, but {} is selected",
+                srtp.cipher.name(),
+                camera_index + 1,
+                cipher.name()
+            ));
+        }
+
+        Ok(srtp)
+    }
+
+    /// Imports a sender-generated key bundle. Cameras already running keep
+    /// their keys until restarted.
+    pub fn import_key_bundle(&mut self, path: &Path) -> Result<usize, String> {
+        self.key_store
+            .as_mut()
+            .ok_or("SRTP key store could not be loaded; see console output")?
+            .import_bundle(path)
+    }
+
     pub fn stop_camera(&mut self, camera_index: usize) -> Result<(), String> {
         if camera_index >= self.cameras.len() {
             return Err("Invalid camera index".to_string());
@@ -99,6 +312,363 @@ impl CameraBackend {
         }
     }
 
+    /// Starts recording a camera to segmented files. A running camera keeps
+    /// playing while its pipeline gets the recording branch.
+    pub fn start_recording(&mut self, camera_index: usize, recording: RecordingConfig) -> Result<(), String> {
+        if camera_index >= self.cameras.len() {
+            return Err("Invalid camera index".to_string());
+        }
+
+        check_recording_format(self.cameras[camera_index].config.payload, &recording)?;
+        recording.segment_limits()?;
+        std::fs::create_dir_all(&recording.directory).map_err(|e| {
+            format!("Failed to create recording directory {}: {}", recording.directory, e)
+        })?;
+
+        self.retention.watch_directory(&recording.directory);
+
+        if self.retention.out_of_space() {
+            self.paused_recordings[camera_index] = Some(recording);
+            return Err("Not enough free disk space; recording will start once space is available".to_string());
+        }
+
+        self.recordings[camera_index] = Some(recording.clone());
+        self.set_pipeline_recording(camera_index, Some(recording))
+    }
+
+    pub fn stop_recording(&mut self, camera_index: usize) -> Result<(), String> {
+        if camera_index >= self.cameras.len() {
+            return Err("Invalid camera index".to_string());
+        }
+
+        self.paused_recordings[camera_index] = None;
+        if self.recordings[camera_index].take().is_some() {
+            self.set_pipeline_recording(camera_index, None)?;
+        }
+        Ok(())
+    }
+
+    /// Adds or removes the recording branch of a running camera.
+    fn set_pipeline_recording(&mut self, camera_index: usize, recording: Option<RecordingConfig>) -> Result<(), String> {
+        match &mut self.pipelines[camera_index] {
+            Some(pipeline) => pipeline.set_recording(recording),
+            None => Ok(()),
+        }
+    }
+
+    /// True while a camera is recording or waiting for disk space to record.
+    pub fn is_recording(&self, camera_index: usize) -> bool {
+        camera_index < self.recordings.len()
+            && (self.recordings[camera_index].is_some() || self.paused_recordings[camera_index].is_some())
+    }
+
+    pub fn set_retention_policy(&mut self, policy: RetentionPolicy) {
+        self.retention.set_policy(policy);
+    }
+
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        self.retention.policy()
+    }
+
+    /// Pauses recordings while the retention manager reports the disk as full
+    /// and resumes them once space is back. Returns the current retention
+    /// warning, if any.
+    pub fn check_retention(&mut self) -> Option<String> {
+        let out_of_space = self.retention.out_of_space();
+
+        for i in 0..self.cameras.len() {
+            let (from, to) = if out_of_space {
+                (&mut self.recordings, &mut self.paused_recordings)
+            } else {
+                (&mut self.paused_recordings, &mut self.recordings)
+            };
+
+            if let Some(recording) = from[i].take() {
+                to[i] = Some(recording.clone());
+                println!(
+                    "Camera {}: recording {}",
+                    i + 1,
+                    if out_of_space { "paused, disk is full" } else { "resumed" }
+                );
+                let resumed = if out_of_space { None } else { Some(recording) };
+                if let Err(e) = self.set_pipeline_recording(i, resumed) {
+                    println!("Failed to switch recording of camera {}: {}", i + 1, e);
+                }
+            }
+        }
+
+        self.retention.warning()
+    }
+
+    fn restart_if_running(&mut self, camera_index: usize) -> Result<(), String> {
+        if self.is_camera_running(camera_index) {
+            self.stop_camera(camera_index)?;
+            self.start_camera(camera_index)?;
+        }
+        Ok(())
+    }
+
+    /// Changes the DVR buffer length. Running cameras keep their current
+    /// buffer until restarted.
+    pub fn set_dvr_minutes(&mut self, minutes: u64) -> Result<(), String> {
+        if minutes > MAX_DVR_MINUTES {
+            return Err(format!("The DVR buffer holds at most {} minutes", MAX_DVR_MINUTES));
+        }
+        self.dvr.minutes = minutes;
+        Ok(())
+    }
+
+    pub fn dvr_minutes(&self) -> u64 {
+        self.dvr.minutes
+    }
+
+    /// Opens a replay window showing the camera from `seconds_back` seconds
+    /// ago. Any replay already open for the camera is closed first.
+    pub fn start_replay(&mut self, camera_index: usize, seconds_back: u64) -> Result<(), String> {
+        if camera_index >= self.cameras.len() {
+            return Err("Invalid camera index".to_string());
+        }
+        if self.dvr.minutes == 0 {
+            return Err("The DVR buffer is disabled".to_string());
+        }
+        if seconds_back > self.dvr.window_seconds() {
+            return Err(format!(
+                "Only the last {} minute(s) are buffered",
+                self.dvr.minutes
+            ));
+        }
+
+        self.stop_replay(camera_index);
+        self.replays[camera_index] = Some(ReplayPlayer::start(&self.dvr, camera_index, seconds_back)?);
+        Ok(())
+    }
+
+    pub fn stop_replay(&mut self, camera_index: usize) {
+        if let Some(mut replay) = self.replays[camera_index].take() {
+            replay.stop();
+        }
+    }
+
+    pub fn is_replaying(&mut self, camera_index: usize) -> bool {
+        match &mut self.replays[camera_index] {
+            Some(replay) => replay.is_running(),
+            None => false,
+        }
+    }
+
+    pub fn set_clip_config(&mut self, clip: ClipConfig) {
+        self.clip = clip;
+    }
+
+    pub fn clip_config(&self) -> ClipConfig {
+        self.clip.clone()
+    }
+
+    /// Clips saved for a camera since the receiver started.
+    pub fn clip_count(&self, camera_index: usize) -> u32 {
+        self.clip_counts.get(camera_index).copied().unwrap_or(0)
+    }
+
+    /// Checks every running camera for a burst of unrecovered loss and saves
+    /// a clip around it from the DVR buffer. The clip is written on a
+    /// background thread once the seconds after the burst have been buffered.
+    /// Returns a message for each burst found.
+    pub fn capture_loss_clips(&mut self) -> Vec<String> {
+        let mut messages = Vec::new();
+        if self.clip.loss_threshold == 0 {
+            return messages;
+        }
+
+        let cooldown = Duration::from_secs(self.clip.seconds_before + self.clip.seconds_after);
+
+        for i in 0..self.cameras.len() {
+            let burst = match &self.stats_collectors[i] {
+                Some(collector) => collector
+                    .lock()
+                    .ok()
+                    .and_then(|mut collector| collector.take_loss_burst(self.clip.loss_threshold, cooldown)),
+                None => None,
+            };
+            let burst = match burst {
+                Some(burst) => burst,
+                None => continue,
+            };
+
+            // Clips come from the DVR buffer, which is off unless turned on.
+            if self.dvr.minutes == 0 {
+                continue;
+            }
+
+            self.clip_counts[i] += 1;
+            messages.push(format!(
+                "Camera {}: {} packets lost unrecovered, saving clip",
+                i + 1,
+                burst.unrecovered
+            ));
+
+            let dvr = self.dvr.clone();
+            let clip = self.clip.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_secs(clip.seconds_after + dvr::DVR_SEGMENT_SECONDS));
+                match dvr::save_clip(&dvr, &clip, i, &burst) {
+                    Ok(path) => println!("Camera {} - Saved loss clip {}", i + 1, path.display()),
+                    Err(e) => println!("Camera {} - Failed to save loss clip: {}", i + 1, e),
+                }
+            });
+        }
+
+        messages
+    }
+
+    /// Shows all cameras in one mosaic window, or switches the layout of the
+    /// open one. Opening the mosaic restarts running cameras so they send
+    /// their video to it instead of their own windows; a layout switch only
+    /// restarts the mosaic.
+    pub fn start_mosaic(&mut self, layout: MosaicLayout) -> Result<(), String> {
+        let was_open = match self.mosaic.take() {
+            Some(mut mosaic) => {
+                mosaic.stop();
+                true
+            }
+            None => false,
+        };
+
+        match MosaicView::start(layout, self.cameras.len()) {
+            Ok(view) => {
+                self.mosaic = Some(view);
+                if !was_open {
+                    self.restart_all_running();
+                }
+                Ok(())
+            }
+            Err(e) => {
+                if was_open {
+                    self.restart_all_running();
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Closes the mosaic and gives running cameras their own windows back.
+    pub fn stop_mosaic(&mut self) {
+        if let Some(mut mosaic) = self.mosaic.take() {
+            mosaic.stop();
+            self.restart_all_running();
+        }
+    }
+
+    pub fn mosaic_layout(&self) -> Option<MosaicLayout> {
+        self.mosaic.as_ref().map(|mosaic| mosaic.layout())
+    }
+
+    /// True while mosaic mode is on but its window has been closed.
+    pub fn mosaic_closed(&mut self) -> bool {
+        match &mut self.mosaic {
+            Some(mosaic) => !mosaic.is_running(),
+            None => false,
+        }
+    }
+
+    fn restart_all_running(&mut self) {
+        for i in 0..self.cameras.len() {
+            if let Err(e) = self.restart_if_running(i) {
+                println!("Failed to restart camera {}: {}", i + 1, e);
+            }
+        }
+    }
+
+    pub fn set_snapshot_config(&mut self, snapshot: SnapshotConfig) {
+        self.snapshot = snapshot;
+    }
+
+    pub fn snapshot_config(&self) -> SnapshotConfig {
+        self.snapshot.clone()
+    }
+
+    /// Saves the camera's most recent decoded frame and returns its path.
+    pub fn take_snapshot(&self, camera_index: usize) -> Result<PathBuf, String> {
+        if camera_index >= self.cameras.len() {
+            return Err("Invalid camera index".to_string());
+        }
+        if !self.is_camera_running(camera_index) {
+            return Err("Camera is not running".to_string());
+        }
+        let frame = self.pipelines[camera_index]
+            .as_ref()
+            .and_then(|pipeline| pipeline.latest_frame())
+            .ok_or_else(|| format!("No video from camera {} yet", camera_index + 1))?;
+        snapshot::save_snapshot(&self.snapshot, camera_index, &frame)
+    }
+
+    /// Delivers a camera's decoded frames to `output`, or stops delivering
+    /// them with `None`. A running camera keeps playing while its pipeline
+    /// gets or loses the frame sink, unless this opens or closes its window,
+    /// in which case it is restarted.
+    pub fn set_frame_output(&mut self, camera_index: usize, output: Option<FrameOutput>) -> Result<(), String> {
+        if camera_index >= self.cameras.len() {
+            return Err("Invalid camera index".to_string());
+        }
+
+        let displayed = |output: &Option<FrameOutput>| output.as_ref().is_none_or(|output| output.display);
+        let window_changed = displayed(&self.frame_outputs[camera_index]) != displayed(&output);
+        self.frame_outputs[camera_index] = output.clone();
+        if window_changed {
+            return self.restart_if_running(camera_index);
+        }
+        match &mut self.pipelines[camera_index] {
+            Some(pipeline) => pipeline.set_frame_output(output),
+            None => Ok(()),
+        }
+    }
+
+    pub fn set_overlay(&mut self, camera_index: usize, enabled: bool) {
+        if camera_index < self.overlays.len() {
+            self.overlays[camera_index] = enabled;
+        }
+        self.update_overlays();
+    }
+
+    pub fn is_overlay_enabled(&self, camera_index: usize) -> bool {
+        self.overlays.get(camera_index).copied().unwrap_or(false)
+    }
+
+    /// Sends each running camera its current overlay text; call about once
+    /// a second. Cameras without the overlay get a blank, or just their name
+    /// while the mosaic needs it as a tile label.
+    pub fn update_overlays(&mut self) {
+        for i in 0..self.cameras.len() {
+            if !self.is_camera_running(i) {
+                continue;
+            }
+
+            let text = if self.overlays[i] {
+                overlay::overlay_text(i, self.get_camera_stats(i).as_ref())
+            } else if self.mosaic.is_some() {
+                overlay::overlay_text(i, None)
+            } else {
+                String::new()
+            };
+            self.overlay_sender.send(i, &text);
+        }
+    }
+
+    /// Mutes or unmutes a camera's audio, straight away if it is running.
+    pub fn set_audio_muted(&mut self, camera_index: usize, muted: bool) {
+        if camera_index >= self.cameras.len() {
+            return;
+        }
+
+        self.audio_mutes[camera_index] = muted;
+        if let Some(pipeline) = &mut self.pipelines[camera_index] {
+            pipeline.set_audio_muted(muted);
+        }
+    }
+
+    pub fn is_audio_muted(&self, camera_index: usize) -> bool {
+        self.audio_mutes.get(camera_index).copied().unwrap_or(false)
+    }
+
     pub fn is_camera_running(&self, camera_index: usize) -> bool {
         if camera_index < self.cameras.len() {
             *self.cameras[camera_index].receiving.lock().unwrap()
@@ -142,8 +712,28 @@ impl CameraBackend {
     }
 
     pub fn shutdown(&mut self) {
+        // Let every recording pipeline finalize at the same time instead of
+        // waiting for them one after another.
+        for pipeline in self.pipelines.iter_mut().flatten() {
+            pipeline.request_finalize();
+        }
+
+        if let Some(mut mosaic) = self.mosaic.take() {
+            mosaic.stop();
+        }
+
         for i in 0..self.cameras.len() {
+            self.stop_replay(i);
             let _ = self.stop_camera(i);
         }
+        self.retention.stop();
+    }
+}
+
+/// MP4 and MPEG-TS have no VP8 mapping.
+fn check_recording_format(payload: PayloadFormat, recording: &RecordingConfig) -> Result<(), String> {
+    if payload == PayloadFormat::Vp8 && recording.format != RecordingFormat::Mkv {
+        return Err("VP8 video can only be recorded as mkv".to_string());
     }
+    Ok(())
 }
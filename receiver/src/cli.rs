@@ -0,0 +1,508 @@
+use receiver::backend::CameraBackend;
+use receiver::mosaic::MosaicLayout;
+use receiver::snapshot::{SnapshotConfig, SnapshotFormat};
+use receiver::retention::RetentionPolicy;
+use receiver::types::{
+    CameraConfig, ClipConfig, FecScheme, MAX_DVR_MINUTES, PayloadFormat, RecordingConfig,
+    RecordingFormat, Transport,
+};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Usage: receiver [--headless] [options]
+
+Without options the receiver opens its window. With --headless it runs from
+the console and reads commands from standard input, and from TCP clients when
+--control is given.
+
+Options:
+  --start <cams>            Cameras to start, e.g. 1,3
+  --record <cams>           Cameras to record from the start
+  --audio <cams>            Cameras whose audio is played
+  --payload <cam>=<fmt>     RTP payload a camera's sender uses: ts
+                            (default), h264, h265 or vp8
+  --fec <cam>=<scheme>      FEC a camera's sender adds: raptorq (default)
+                            or ulpfec
+  --retransmit <cams>       Cameras that ask their sender to resend lost
+                            packets (RaptorQ FEC only)
+  --transport <cam>=<t>     How a camera's stream arrives: udp (default),
+                            udp+tcp[:<seconds>] (UDP, connecting to the sender
+                            over TCP after that long without packets),
+                            tcp:<connect|listen> or
+                            srt:<caller|listener|rendezvous>[:<latency ms>
+                            [:<passphrase>]], e.g. 2=srt:listener:200
+  --record-format <fmt>     mp4, mkv or ts
+  --record-dir <dir>        Directory for recorded segments
+  --segment-seconds <n>     Segment length in seconds (0 = no limit)
+  --segment-mb <n>          Segment size in megabytes (0 = no limit)
+  --dvr-minutes <n>         Minutes of video buffered for replay (0 = off)
+  --clip-threshold <n>      Unrecovered packets in 5 s that save a clip (0 = off)
+  --clip-seconds <b>,<a>    Seconds of video kept before and after a loss burst
+  --clip-dir <dir>          Directory for loss clips
+  --snapshot-dir <dir>      Directory for snapshots
+  --max-age-hours <n>       Delete recordings older than this (0 = keep)
+  --max-camera-mb <n>       Recorded megabytes kept per camera (0 = no limit)
+  --min-free-mb <n>         Free space to keep on the recording disk (0 = off)
+  --control <addr:port>     Also take commands over TCP, one per line, e.g.
+                            127.0.0.1:5800; each reply ends with an empty line
+
+Commands:
+  start <cam> | stop <cam> | record <cam> | stop-record <cam>
+  replay <cam> [seconds ago] | stop-replay <cam>
+  mosaic [2x2|3x3|1+5|<cam>] | stop-mosaic | overlay <cam> on|off
+  mute <cam> on|off
+  snapshot <cam> [jpg|png]
+  stats | help | quit";
+
+const CAMERA_COUNT: usize = 4;
+
+pub struct CliOptions {
+    pub start: Vec<usize>,
+    pub record: Vec<usize>,
+    pub audio: Vec<usize>,
+    pub payloads: Vec<(usize, PayloadFormat)>,
+    pub fec: Vec<(usize, FecScheme)>,
+    pub retransmission: Vec<usize>,
+    pub transports: Vec<(usize, Transport)>,
+    pub recording: RecordingConfig,
+    pub retention: RetentionPolicy,
+    pub dvr_minutes: Option<u64>,
+    pub clip: ClipConfig,
+    pub snapshot: SnapshotConfig,
+    pub control: Option<String>,
+}
+
+/// Parses the command line. Returns `None` when the receiver should open its
+/// window as usual.
+pub fn parse_args(args: &[String]) -> Result<Option<CliOptions>, String> {
+    if args.is_empty() {
+        return Ok(None);
+    }
+
+    let mut headless = false;
+    let mut options = CliOptions {
+        start: Vec::new(),
+        record: Vec::new(),
+        audio: Vec::new(),
+        payloads: Vec::new(),
+        fec: Vec::new(),
+        retransmission: Vec::new(),
+        transports: Vec::new(),
+        recording: RecordingConfig::default(),
+        retention: RetentionPolicy::default(),
+        dvr_minutes: None,
+        clip: ClipConfig::default(),
+        snapshot: SnapshotConfig::default(),
+        control: None,
+    };
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", arg))
+        };
+
+        match arg.as_str() {
+            "--headless" => headless = true,
+            "--start" => options.start = parse_camera_list(&value()?)?,
+            "--record" => options.record = parse_camera_list(&value()?)?,
+            "--audio" => options.audio = parse_camera_list(&value()?)?,
+            "--payload" => {
+                let text = value()?;
+                let (camera, payload) = text
+                    .split_once('=')
+                    .ok_or_else(|| format!("{} needs <cam>=<format>", arg))?;
+                let camera_index = parse_camera(camera)?;
+                let payload = PayloadFormat::from_name(payload)
+                    .ok_or_else(|| format!("Unknown payload format '{}'", payload))?;
+                options.payloads.push((camera_index, payload));
+            }
+            "--fec" => {
+                let text = value()?;
+                let (camera, fec) = text
+                    .split_once('=')
+                    .ok_or_else(|| format!("{} needs <cam>=<scheme>", arg))?;
+                let camera_index = parse_camera(camera)?;
+                options.fec.push((camera_index, FecScheme::parse(fec)?));
+            }
+            "--retransmit" => options.retransmission = parse_camera_list(&value()?)?,
+            "--transport" => {
+                let text = value()?;
+                let (camera, transport) = text
+                    .split_once('=')
+                    .ok_or_else(|| format!("{} needs <cam>=<transport>", arg))?;
+                options.transports.push((parse_camera(camera)?, Transport::parse(transport)?));
+            }
+            "--record-format" => {
+                let format = value()?;
+                options.recording.format = RecordingFormat::from_extension(&format)
+                    .ok_or_else(|| format!("Unknown recording format '{}'", format))?;
+            }
+            "--record-dir" => options.recording.directory = value()?,
+            "--segment-seconds" => {
+                options.recording.segment_seconds = parse_number(arg, &value()?)?;
+            }
+            "--segment-mb" => {
+                options.recording.segment_megabytes = parse_number(arg, &value()?)?;
+            }
+            "--dvr-minutes" => {
+                let minutes = parse_number(arg, &value()?)?;
+                if minutes > MAX_DVR_MINUTES {
+                    return Err(format!("{} can be at most {}", arg, MAX_DVR_MINUTES));
+                }
+                options.dvr_minutes = Some(minutes);
+            }
+            "--clip-threshold" => {
+                let threshold = parse_number(arg, &value()?)?;
+                options.clip.loss_threshold =
+                    u32::try_from(threshold).map_err(|_| format!("{} is too large", arg))?;
+            }
+            "--clip-seconds" => {
+                let text = value()?;
+                let (before, after) = text
+                    .split_once(',')
+                    .ok_or_else(|| format!("{} needs <before>,<after>", arg))?;
+                options.clip.seconds_before = parse_number(arg, before)?;
+                options.clip.seconds_after = parse_number(arg, after)?;
+            }
+            "--clip-dir" => options.clip.directory = value()?,
+            "--snapshot-dir" => options.snapshot.directory = value()?,
+            "--control" => options.control = Some(value()?),
+            "--max-age-hours" => {
+                options.retention.max_age_hours = parse_number(arg, &value()?)?;
+            }
+            "--max-camera-mb" => {
+                options.retention.max_megabytes_per_camera = parse_number(arg, &value()?)?;
+            }
+            "--min-free-mb" => {
+                options.retention.min_free_megabytes = parse_number(arg, &value()?)?;
+            }
+            "--help" | "-h" => return Err(String::new()),
+            other => return Err(format!("Unknown option '{}'", other)),
+        }
+    }
+
+    if !headless {
+        return Err("Options are only supported together with --headless".to_string());
+    }
+    options.recording.segment_limits()?;
+
+    Ok(Some(options))
+}
+
+/// Runs the receiver from the console until `quit` or end of input.
+pub fn run(backend: &mut CameraBackend, options: CliOptions) {
+    backend.set_retention_policy(options.retention.clone());
+    if let Some(minutes) = options.dvr_minutes {
+        if let Err(e) = backend.set_dvr_minutes(minutes) {
+            println!("{}", e);
+        }
+    }
+    backend.set_clip_config(options.clip.clone());
+    backend.set_snapshot_config(options.snapshot.clone());
+
+    for &camera_index in &options.audio {
+        update_config(backend, camera_index, |config| config.audio = true);
+    }
+    for &(camera_index, payload) in &options.payloads {
+        update_config(backend, camera_index, |config| config.payload = payload);
+    }
+    for &(camera_index, fec) in &options.fec {
+        update_config(backend, camera_index, |config| config.fec = fec);
+    }
+    for &camera_index in &options.retransmission {
+        update_config(backend, camera_index, |config| config.retransmission = true);
+    }
+    for (camera_index, transport) in &options.transports {
+        update_config(backend, *camera_index, |config| config.transport = transport.clone());
+    }
+    for &camera_index in &options.record {
+        let result = backend.start_recording(camera_index, options.recording.clone());
+        println!("{}", report(camera_index, "record", result));
+    }
+    for &camera_index in &options.start {
+        println!("{}", report(camera_index, "start", backend.start_camera(camera_index)));
+    }
+
+    println!("Receiver running headless. Type 'help' for commands.");
+
+    // Stdin and control connections are read on their own threads so
+    // retention and loss bursts can be checked while waiting for commands.
+    // Control commands come with a channel for their reply.
+    let (line_sender, lines) = mpsc::channel::<(String, Option<Sender<String>>)>();
+    if let Some(address) = &options.control {
+        match TcpListener::bind(address) {
+            Ok(listener) => {
+                println!("Control API listening on {}", address);
+                let line_sender = line_sender.clone();
+                thread::spawn(move || serve_control(listener, line_sender));
+            }
+            Err(e) => println!("Control API unavailable on {}: {}", address, e),
+        }
+    }
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            if line_sender.send((line, None)).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut last_warning = None;
+    loop {
+        let (line, reply) = match lines.recv_timeout(Duration::from_secs(1)) {
+            Ok(received) => received,
+            Err(RecvTimeoutError::Timeout) => {
+                backend.update_overlays();
+                for message in backend.capture_loss_clips() {
+                    println!("{}", message);
+                }
+                let warning = backend.check_retention();
+                if warning != last_warning {
+                    if let Some(warning) = &warning {
+                        println!("Warning: {}", warning);
+                    }
+                    last_warning = warning;
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        let output = handle_command(backend, &options, &line);
+        match reply {
+            Some(reply) => {
+                let _ = reply.send(output.unwrap_or_default());
+            }
+            None => match output {
+                Some(output) if output.is_empty() => {}
+                Some(output) => println!("{}", output),
+                None => break,
+            },
+        }
+        let _ = io::stdout().flush();
+    }
+
+    backend.shutdown();
+}
+
+/// Accepts control connections, one thread each. Every line a client sends
+/// is run as a command and answered with the command's output followed by
+/// an empty line; `quit` closes the connection.
+fn serve_control(listener: TcpListener, line_sender: Sender<(String, Option<Sender<String>>)>) {
+    for stream in listener.incoming().map_while(Result::ok) {
+        let line_sender = line_sender.clone();
+        thread::spawn(move || {
+            let mut writer = match stream.try_clone() {
+                Ok(writer) => writer,
+                Err(_) => return,
+            };
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                if matches!(line.trim(), "quit" | "exit") {
+                    break;
+                }
+                let (reply_sender, reply) = mpsc::channel();
+                if line_sender.send((line, Some(reply_sender))).is_err() {
+                    break;
+                }
+                let output = match reply.recv() {
+                    Ok(output) => output,
+                    Err(_) => break,
+                };
+                if write!(writer, "{}\n\n", output).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+/// Runs one command line and returns its output, or `None` to quit.
+fn handle_command(backend: &mut CameraBackend, options: &CliOptions, line: &str) -> Option<String> {
+    let mut words = line.split_whitespace();
+    let command = match words.next() {
+        Some(command) => command,
+        None => return Some(String::new()),
+    };
+    let camera = words.next().map(parse_camera);
+
+    let output = match (command, camera) {
+        ("quit" | "exit", _) => return None,
+        ("help", _) => USAGE.to_string(),
+        ("stats", _) => stats_report(backend),
+        ("stop-mosaic", _) => {
+            backend.stop_mosaic();
+            "Mosaic closed".to_string()
+        }
+        ("mosaic", _) => {
+            let name = line.split_whitespace().nth(1).unwrap_or("2x2");
+            match MosaicLayout::from_name(name, CAMERA_COUNT) {
+                Some(layout) => match backend.start_mosaic(layout) {
+                    Ok(()) => format!("Mosaic showing {}", layout.name()),
+                    Err(e) => format!("Mosaic failed: {}", e),
+                },
+                None => format!("Unknown mosaic layout '{}'", name),
+            }
+        }
+        (_, Some(Err(e))) => e,
+        ("start", Some(Ok(camera_index))) => {
+            report(camera_index, command, backend.start_camera(camera_index))
+        }
+        ("stop", Some(Ok(camera_index))) => {
+            report(camera_index, command, backend.stop_camera(camera_index))
+        }
+        ("record", Some(Ok(camera_index))) => report(
+            camera_index,
+            command,
+            backend.start_recording(camera_index, options.recording.clone()),
+        ),
+        ("stop-record", Some(Ok(camera_index))) => {
+            report(camera_index, command, backend.stop_recording(camera_index))
+        }
+        ("replay", Some(Ok(camera_index))) => {
+            match words.next().map_or(Ok(30), |word| parse_number("replay", word)) {
+                Ok(seconds) => report(
+                    camera_index,
+                    command,
+                    backend.start_replay(camera_index, seconds),
+                ),
+                Err(e) => e,
+            }
+        }
+        ("snapshot", Some(Ok(camera_index))) => {
+            // A format given here is kept for later snapshots.
+            if let Some(extension) = words.next() {
+                match SnapshotFormat::from_extension(extension) {
+                    Some(format) => backend.set_snapshot_config(SnapshotConfig {
+                        format,
+                        ..backend.snapshot_config()
+                    }),
+                    None => return Some(format!("Unknown snapshot format '{}'", extension)),
+                }
+            }
+            match backend.take_snapshot(camera_index) {
+                Ok(path) => format!("Camera {}: saved {}", camera_index + 1, path.display()),
+                Err(e) => format!("Camera {}: snapshot failed: {}", camera_index + 1, e),
+            }
+        }
+        ("overlay", Some(Ok(camera_index))) => match words.next() {
+            Some(state @ ("on" | "off")) => {
+                backend.set_overlay(camera_index, state == "on");
+                report(camera_index, command, Ok(()))
+            }
+            _ => "Usage: overlay <cam> on|off".to_string(),
+        },
+        ("mute", Some(Ok(camera_index))) => match words.next() {
+            Some(state @ ("on" | "off")) => {
+                backend.set_audio_muted(camera_index, state == "on");
+                report(camera_index, command, Ok(()))
+            }
+            _ => "Usage: mute <cam> on|off".to_string(),
+        },
+        ("stop-replay", Some(Ok(camera_index))) => {
+            backend.stop_replay(camera_index);
+            report(camera_index, command, Ok(()))
+        }
+        _ => format!("Unknown command '{}'. Type 'help' for commands.", line.trim()),
+    };
+    Some(output)
+}
+
+fn stats_report(backend: &mut CameraBackend) -> String {
+    let mut lines = Vec::new();
+    for camera_index in 0..CAMERA_COUNT {
+        let state = if backend.is_camera_running(camera_index) {
+            "running"
+        } else {
+            "stopped"
+        };
+        let recording = if backend.is_recording(camera_index) {
+            ", recording"
+        } else {
+            ""
+        };
+
+        let config = backend.get_camera_config(camera_index);
+        let srt = match config.map(|config| &config.transport) {
+            Some(Transport::Srt(srt)) => Some(srt.mode.name()),
+            _ => None,
+        };
+        match backend.get_camera_stats(camera_index) {
+            Some(stats) => {
+                let srt_stats = srt
+                    .map(|mode| {
+                        format!(
+                            " SRT {} RTT:{:.1}ms Retrans:{} Dropped:{}",
+                            mode, stats.srt_rtt, stats.srt_retransmitted, stats.srt_dropped
+                        )
+                    })
+                    .unwrap_or_default();
+                lines.push(format!(
+                    "Camera {} ({}{}): Received:{} (FEC:{} RTX:{}) Lost:{} Repair:{:.1}% \
+                    Bitrate:{:.1}kbps Jitter:{:.1}ms Overhead:{:.1}% ({}){}{}",
+                    camera_index + 1,
+                    state,
+                    recording,
+                    stats.packets_received,
+                    stats.fec_recovered,
+                    stats.rtx_recovered,
+                    stats.packets_lost,
+                    stats.repair_rate,
+                    stats.bitrate,
+                    stats.jitter,
+                    stats.overhead,
+                    config.map_or("", |config| config.payload.label()),
+                    srt_stats,
+                    if stats.tcp_fallback { " over TCP fallback" } else { "" }
+                ))
+            }
+            None => lines.push(format!("Camera {} ({}{})", camera_index + 1, state, recording)),
+        }
+    }
+
+    if let Some(warning) = backend.check_retention() {
+        lines.push(format!("Warning: {}", warning));
+    }
+    lines.join("\n")
+}
+
+fn report(camera_index: usize, action: &str, result: Result<(), String>) -> String {
+    match result {
+        Ok(()) => format!("Camera {}: {} ok", camera_index + 1, action),
+        Err(e) => format!("Camera {}: {} failed: {}", camera_index + 1, action, e),
+    }
+}
+
+fn update_config(backend: &mut CameraBackend, camera_index: usize, change: impl FnOnce(&mut CameraConfig)) {
+    if let Some(config) = backend.get_camera_config(camera_index) {
+        let mut config = config.clone();
+        change(&mut config);
+        backend.update_camera_config(camera_index, config);
+    }
+}
+
+fn parse_camera(text: &str) -> Result<usize, String> {
+    match text.trim().parse::<usize>() {
+        Ok(number) if (1..=CAMERA_COUNT).contains(&number) => Ok(number - 1),
+        _ => Err(format!(
+            "'{}' is not a camera number between 1 and {}",
+            text, CAMERA_COUNT
+        )),
+    }
+}
+
+fn parse_camera_list(text: &str) -> Result<Vec<usize>, String> {
+    text.split(',').map(parse_camera).collect()
+}
+
+fn parse_number(option: &str, text: &str) -> Result<u64, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("{} needs a whole number, got '{}'", option, text))
+}
@@ -0,0 +1,192 @@
+use crate::stats_collector::{LOSS_WINDOW, LossBurst};
+use crate::types::{ClipConfig, DvrConfig, format_timestamp};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use common::process::{kill_tree, shell};
+use std::process::{Child, Stdio};
+use std::time::{Duration, SystemTime};
+
+/// Length of each DVR segment. Replay offsets are accurate to about this.
+pub const DVR_SEGMENT_SECONDS: u64 = 2;
+
+/// Directory holding one camera's DVR segments.
+pub fn segment_directory(dvr: &DvrConfig, camera_index: usize) -> PathBuf {
+    Path::new(&dvr.directory).join(format!("camera{}", camera_index + 1))
+}
+
+/// Number of segment files that cover the configured buffer length.
+pub fn max_segments(dvr: &DvrConfig) -> u64 {
+    dvr.window_seconds().div_ceil(DVR_SEGMENT_SECONDS).max(1)
+}
+
+/// Creates the camera's segment directory and removes segments left over from
+/// an earlier run that fall outside the buffer.
+pub fn prepare(dvr: &DvrConfig, camera_index: usize) -> Result<(), String> {
+    let directory = segment_directory(dvr, camera_index);
+    fs::create_dir_all(&directory)
+        .map_err(|e| format!("Failed to create DVR directory {}: {}", directory.display(), e))?;
+
+    let window = Duration::from_secs(dvr.window_seconds());
+    for (path, modified) in list_segments(&directory) {
+        if modified.elapsed().map(|age| age > window).unwrap_or(false) {
+            let _ = fs::remove_file(path);
+        }
+    }
+    Ok(())
+}
+
+/// Segment files sorted oldest first. splitmuxsink reuses file names once the
+/// ring wraps, so the modification time gives the order, not the name.
+fn list_segments(directory: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let mut segments: Vec<(PathBuf, SystemTime)> = match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "ts"))
+            .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?.modified().ok()?)))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    segments.sort_by_key(|(_, modified)| *modified);
+    segments
+}
+
+/// Joins the buffered segments covering `from` up to `to` into one MPEG-TS
+/// file. TS can be concatenated byte for byte. Returns the number of segments
+/// written.
+pub fn export_range(
+    dvr: &DvrConfig,
+    camera_index: usize,
+    from: SystemTime,
+    to: SystemTime,
+    destination: &Path,
+) -> Result<usize, String> {
+    // A segment's modification time is when it was last written, i.e. its end.
+    let segment_length = Duration::from_secs(DVR_SEGMENT_SECONDS);
+    let segments: Vec<PathBuf> = list_segments(&segment_directory(dvr, camera_index))
+        .into_iter()
+        .filter(|(_, end)| *end >= from && *end <= to + segment_length)
+        .map(|(path, _)| path)
+        .collect();
+
+    if segments.is_empty() {
+        return Err(format!(
+            "No buffered video for camera {} in that time range",
+            camera_index + 1
+        ));
+    }
+
+    let mut output = File::create(destination)
+        .map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+    for segment in &segments {
+        // The newest segment is still being written; copy what is there.
+        let mut input = File::open(segment)
+            .map_err(|e| format!("Failed to read {}: {}", segment.display(), e))?;
+        io::copy(&mut input, &mut output)
+            .map_err(|e| format!("Failed to write {}: {}", destination.display(), e))?;
+    }
+
+    Ok(segments.len())
+}
+
+/// Saves the buffered video around a loss burst to
+/// `camera<N>_<time>_loss.ts` in the clip directory, with the stats at the
+/// time of the burst in a `.txt` file next to it. Call once the DVR buffer
+/// holds `seconds_after` seconds past the burst. Returns the clip path.
+pub fn save_clip(
+    dvr: &DvrConfig,
+    clip: &ClipConfig,
+    camera_index: usize,
+    burst: &LossBurst,
+) -> Result<PathBuf, String> {
+    fs::create_dir_all(&clip.directory)
+        .map_err(|e| format!("Failed to create clip directory {}: {}", clip.directory, e))?;
+
+    let timestamp = format_timestamp(
+        burst
+            .at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    );
+    let base = Path::new(&clip.directory).join(format!("camera{}_{}_loss", camera_index + 1, timestamp));
+    let clip_path = base.with_extension("ts");
+
+    export_range(
+        dvr,
+        camera_index,
+        burst.at - Duration::from_secs(clip.seconds_before),
+        burst.at + Duration::from_secs(clip.seconds_after),
+        &clip_path,
+    )?;
+
+    let stats = &burst.stats;
+    let sidecar = base.with_extension("txt");
+    let mut file = File::create(&sidecar)
+        .map_err(|e| format!("Failed to create {}: {}", sidecar.display(), e))?;
+    write!(
+        file,
+        "camera: {}\ntime: {} UTC\nunrecovered_packets: {} in {} s\n\
+        seconds_before: {}\nseconds_after: {}\n\
+        received: {}\nlost: {}\nlate: {}\nrepair_rate: {:.1}%\n\
+        bitrate: {:.1} kbps\njitter: {:.1} ms\nauth_failures: {}\n",
+        camera_index + 1,
+        timestamp,
+        burst.unrecovered,
+        LOSS_WINDOW.as_secs(),
+        clip.seconds_before,
+        clip.seconds_after,
+        stats.packets_received,
+        stats.packets_lost,
+        stats.packets_late,
+        stats.repair_rate,
+        stats.bitrate,
+        stats.jitter,
+        stats.auth_failures
+    )
+    .map_err(|e| format!("Failed to write {}: {}", sidecar.display(), e))?;
+
+    Ok(clip_path)
+}
+
+/// Plays a camera's buffered video from some seconds ago in its own window,
+/// independent of the live pipeline.
+pub struct ReplayPlayer {
+    child: Child,
+}
+
+impl ReplayPlayer {
+    pub fn start(dvr: &DvrConfig, camera_index: usize, seconds_back: u64) -> Result<Self, String> {
+        let now = SystemTime::now();
+        let from = now - Duration::from_secs(seconds_back);
+        let file = Path::new(&dvr.directory).join(format!("replay_camera{}.ts", camera_index + 1));
+        export_range(dvr, camera_index, from, now, &file)?;
+
+        let cmd = format!(
+            "gst-launch-1.0 filesrc location=\"{}\" ! tsdemux ! decodebin ! \
+            videoconvert ! autovideosink",
+            file.display().to_string().replace('\\', "/")
+        );
+
+        println!("Camera {} - Replaying the last {} seconds", camera_index + 1, seconds_back);
+
+        let child = shell(&cmd)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start replay: {}", e))?;
+
+        Ok(Self { child })
+    }
+
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    pub fn stop(&mut self) {
+        if self.is_running() {
+            kill_tree(self.child.id());
+        }
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,187 @@
+use gstreamer as gst;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Name of the `appsink` decoded frames are pulled from.
+pub const FRAME_SINK_NAME: &str = "frames";
+
+/// Name of the depayloader whose input carries the sender's RTP timestamps.
+pub const DEPAYLOADER_NAME: &str = "depay";
+
+/// RTP clock rate of the MPEG-TS video flow.
+const RTP_CLOCK_RATE: u64 = 90_000;
+
+/// Seconds between the NTP epoch (1900) and the Unix epoch.
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// One decoded video frame of a camera, as packed 8-bit RGB.
+#[derive(Clone)]
+pub struct DecodedFrame {
+    pub camera_index: usize,
+    pub width: u32,
+    pub height: u32,
+    /// Bytes per row of `data`; rows can be padded past `width * 3`.
+    pub stride: usize,
+    pub data: Vec<u8>,
+    /// Presentation time in the pipeline's running time.
+    pub pts: Option<Duration>,
+    /// The sender's 90 kHz RTP time for the frame, carried over from the RTP
+    /// packets by pipeline time.
+    pub rtp_timestamp: Option<u32>,
+    /// The sender's wall-clock time for the frame. Only known once RTCP
+    /// sender reports have tied its RTP time to NTP time.
+    pub ntp_time: Option<SystemTime>,
+}
+
+/// Where a camera's decoded frames are delivered.
+#[derive(Clone)]
+pub enum FrameSink {
+    /// Called on the pipeline's streaming thread for every frame, so it
+    /// should return quickly.
+    Callback(Arc<dyn Fn(DecodedFrame) + Send + Sync>),
+    /// Frames are dropped instead of queued while the channel is full.
+    Channel(SyncSender<DecodedFrame>),
+}
+
+impl FrameSink {
+    pub fn callback<F>(callback: F) -> Self
+    where
+        F: Fn(DecodedFrame) + Send + Sync + 'static,
+    {
+        FrameSink::Callback(Arc::new(callback))
+    }
+
+    /// A channel sink holding at most `capacity` frames, and its receiver.
+    pub fn channel(capacity: usize) -> (Self, Receiver<DecodedFrame>) {
+        let (sender, receiver) = mpsc::sync_channel(capacity.max(1));
+        (FrameSink::Channel(sender), receiver)
+    }
+
+    fn deliver(&self, frame: DecodedFrame) {
+        match self {
+            FrameSink::Callback(callback) => callback(frame),
+            // A full or closed channel just misses this frame.
+            FrameSink::Channel(sender) => {
+                let _ = sender.try_send(frame);
+            }
+        }
+    }
+}
+
+/// Decoded-frame delivery for one camera.
+#[derive(Clone)]
+pub struct FrameOutput {
+    pub sink: FrameSink,
+    /// Keep showing the camera in its own window, or the mosaic, as well.
+    pub display: bool,
+}
+
+/// Pipeline tail for the `decoded` tee feeding the frame `appsink`. Frames
+/// are dropped rather than holding up the display when the consumer is slow.
+pub fn frame_sink_branch() -> String {
+    format!(
+        "queue leaky=downstream max-size-buffers=2 ! videoconvert ! video/x-raw,format=RGB ! \
+        appsink name={} emit-signals=true sync=false max-buffers=2 drop=true",
+        FRAME_SINK_NAME
+    )
+}
+
+/// RTP and NTP time of the newest packet entering the depayloader.
+#[derive(Clone, Copy)]
+pub struct RtpReference {
+    pts: Duration,
+    rtp_timestamp: u32,
+    ntp_time: Option<SystemTime>,
+}
+
+impl RtpReference {
+    pub fn from_packet(buffer: &gst::BufferRef) -> Option<Self> {
+        let pts = Duration::from_nanos(buffer.pts()?.nseconds());
+        let map = buffer.map_readable().ok()?;
+        let header = map.as_slice().get(4..8)?;
+        let rtp_timestamp = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+
+        let ntp_time = buffer
+            .iter_meta::<gst::ReferenceTimestampMeta>()
+            .find(|meta| {
+                meta.reference()
+                    .structure(0)
+                    .is_some_and(|s| s.name() == "timestamp/x-ntp")
+            })
+            .and_then(|meta| {
+                Duration::from_nanos(meta.timestamp().nseconds())
+                    .checked_sub(Duration::from_secs(NTP_UNIX_OFFSET))
+            })
+            .map(|since_unix| UNIX_EPOCH + since_unix);
+
+        Some(Self {
+            pts,
+            rtp_timestamp,
+            ntp_time,
+        })
+    }
+
+    /// RTP and NTP time at `pts`, moving on from this packet at the RTP
+    /// clock rate.
+    fn at(&self, pts: Duration) -> (u32, Option<SystemTime>) {
+        let (ticks, ntp_time) = if pts >= self.pts {
+            let delta = pts - self.pts;
+            let ticks = (delta.as_nanos() as u64 * RTP_CLOCK_RATE / 1_000_000_000) as u32;
+            (ticks, self.ntp_time.map(|ntp| ntp + delta))
+        } else {
+            let delta = self.pts - pts;
+            let ticks = (delta.as_nanos() as u64 * RTP_CLOCK_RATE / 1_000_000_000) as u32;
+            (ticks.wrapping_neg(), self.ntp_time.and_then(|ntp| ntp.checked_sub(delta)))
+        };
+        (self.rtp_timestamp.wrapping_add(ticks), ntp_time)
+    }
+}
+
+/// Hands a sample pulled from the frame `appsink` to the camera's sink.
+pub fn deliver_sample(
+    sink: &FrameSink,
+    camera_index: usize,
+    sample: &gst::Sample,
+    reference: Option<RtpReference>,
+) {
+    if let Some(frame) = decoded_frame(camera_index, sample, reference) {
+        sink.deliver(frame);
+    }
+}
+
+fn decoded_frame(
+    camera_index: usize,
+    sample: &gst::Sample,
+    reference: Option<RtpReference>,
+) -> Option<DecodedFrame> {
+    let structure = sample.caps()?.structure(0)?;
+    let width = structure.get::<i32>("width").ok()? as u32;
+    let height = structure.get::<i32>("height").ok()? as u32;
+    if height == 0 {
+        return None;
+    }
+
+    let buffer = sample.buffer()?;
+    let data = buffer.map_readable().ok()?.as_slice().to_vec();
+    let pts = buffer.pts().map(|pts| Duration::from_nanos(pts.nseconds()));
+
+    let (rtp_timestamp, ntp_time) = match (reference, pts) {
+        (Some(reference), Some(pts)) => {
+            let (rtp_timestamp, ntp_time) = reference.at(pts);
+            (Some(rtp_timestamp), ntp_time)
+        }
+        _ => (None, None),
+    };
+
+    Some(DecodedFrame {
+        camera_index,
+        width,
+        height,
+        stride: data.len() / height as usize,
+        data,
+        pts,
+        rtp_timestamp,
+        ntp_time,
+    })
+}
@@ -1,9 +1,48 @@
-use crate::types::CameraConfig;
-use crate::stats_collector::StatsCollector;
-use std::io::{BufRead, BufReader};
-use std::process::{Command, Stdio};
-use std::sync::{Arc, Mutex};
+use crate::dvr::{self, DVR_SEGMENT_SECONDS};
+use crate::frames::{self, FrameOutput, RtpReference};
+use crate::overlay;
+use crate::snapshot;
+use crate::types::{
+    CameraConfig, DvrConfig, FecScheme, PayloadFormat, RED_PAYLOAD_TYPE, RTX_PAYLOAD_TYPE,
+    RecordingConfig, SrtConfig, TcpMode, Transport, ULPFEC_PAYLOAD_TYPE, format_timestamp,
+    parse_ip_address, unix_now,
+};
+use crate::stats_collector::{RTP_BYTES_NAME, SrtStats, StatsCollector, VIDEO_BYTES_NAME};
+use ::gstreamer as gst;
+use ::gstreamer::prelude::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a recording pipeline gets to flush and finalize its files after
+/// EOS before it is stopped.
+const FINALIZE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Debug output the stats are parsed from, and the elements producing it.
+const STATS_DEBUG: &str = "raptorqdec:5,rtpjitterbuffer:4,srtpdec:4";
+const STATS_ELEMENT_FACTORIES: [&str; 3] = ["raptorqdec", "rtpjitterbuffer", "srtpdec"];
+
+/// How long rtpbin keeps packets for ULPFEC to recover lost ones from.
+const ULPFEC_STORAGE_TIME: Duration = Duration::from_millis(250);
+const ULPFEC_DECODER_NAME: &str = "ulpfec";
+
+/// The element rtpbin restores retransmitted packets with.
+const RTX_RECEIVER_NAME: &str = "rtx_receive";
+
+/// The `srtsrc` of a camera receiving over SRT, read for its statistics.
+const SRT_SOURCE_NAME: &str = "srt_source";
+
+/// The `volume` element of a pipeline playing audio, used to mute it.
+const AUDIO_VOLUME_NAME: &str = "audio_volume";
+
+/// How long a snapshot of a camera the pipeline does not decode waits for a
+/// keyframe to decode.
+const KEYFRAME_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Elements of running pipelines whose debug output feeds a camera's stats
+/// collector, by element name.
+static STATS_ELEMENTS: Mutex<Vec<(String, Arc<Mutex<StatsCollector>>)>> = Mutex::new(Vec::new());
 
 pub struct GStreamerPipeline {
     camera_index: usize,
@@ -11,6 +50,30 @@ pub struct GStreamerPipeline {
     receiving: Arc<Mutex<bool>>,
     stats_collector: Option<Arc<Mutex<StatsCollector>>>,
     thread_handle: Option<thread::JoinHandle<()>>,
+    /// The pipeline while it runs.
+    pipeline: Arc<Mutex<Option<gst::Element>>>,
+    branches: Arc<Mutex<LiveBranches>>,
+    finalize_requested: bool,
+}
+
+/// The recording and frame output of a camera, which can be switched while
+/// its pipeline runs, and the branches of the running pipeline carrying
+/// them. Locked after `GStreamerPipeline::pipeline` where both are needed.
+#[derive(Default)]
+struct LiveBranches {
+    recording: Option<RecordingConfig>,
+    frames: Option<FrameOutput>,
+    recording_branch: Option<Branch>,
+    frame_branch: Option<Branch>,
+}
+
+/// A bin fed from a request pad of one of the pipeline's tees.
+struct Branch {
+    bin: gst::Bin,
+    tee_pad: gst::Pad,
+    /// A probe elsewhere in the pipeline the branch depends on, removed
+    /// along with it.
+    probe: Option<(gst::Pad, gst::PadProbeId)>,
 }
 
 impl GStreamerPipeline {
@@ -20,12 +83,20 @@ impl GStreamerPipeline {
         receiving: Arc<Mutex<bool>>,
         stats_collector: Option<Arc<Mutex<StatsCollector>>>,
     ) -> Self {
+        let branches = LiveBranches {
+            recording: config.recording.clone(),
+            frames: config.frames.clone(),
+            ..LiveBranches::default()
+        };
         Self {
             camera_index,
             config,
             receiving,
             stats_collector,
             thread_handle: None,
+            pipeline: Arc::new(Mutex::new(None)),
+            branches: Arc::new(Mutex::new(branches)),
+            finalize_requested: false,
         }
     }
 
@@ -36,101 +107,1180 @@ impl GStreamerPipeline {
         let config = self.config.clone();
         let receiving = Arc::clone(&self.receiving);
         let stats_collector = self.stats_collector.clone();
+        let pipeline = Arc::clone(&self.pipeline);
+        let branches = Arc::clone(&self.branches);
 
         self.thread_handle = Some(thread::spawn(move || {
-            run_pipeline(camera_index, config, receiving, stats_collector);
+            let mut config = config;
+            loop {
+                let fell_back = run_pipeline(
+                    camera_index,
+                    config.clone(),
+                    Arc::clone(&receiving),
+                    stats_collector.clone(),
+                    Arc::clone(&pipeline),
+                    Arc::clone(&branches),
+                );
+                if !fell_back {
+                    break;
+                }
+
+                if let Transport::UdpWithTcpFallback(after) = config.transport {
+                    println!(
+                        "Camera {} - No UDP packets for {} s; falling back to TCP",
+                        camera_index + 1,
+                        after.as_secs()
+                    );
+                }
+                config.transport = Transport::Tcp(TcpMode::Connect);
+                if let Some(collector) = &stats_collector {
+                    collector.lock().unwrap().set_tcp_fallback();
+                }
+            }
         }));
     }
 
+    /// Mutes or unmutes the audio of a running pipeline. Returns false if
+    /// the pipeline plays no audio.
+    pub fn set_audio_muted(&mut self, muted: bool) -> bool {
+        self.config.audio_muted = muted;
+
+        let volume = self
+            .pipeline
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|pipeline| pipeline.downcast_ref::<gst::Bin>()?.by_name(AUDIO_VOLUME_NAME));
+        match volume {
+            Some(volume) => {
+                volume.set_property("mute", muted);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Starts or stops recording by adding the recording branch to the
+    /// running pipeline or removing it, so the video keeps playing. A
+    /// recording in progress is finalized first; `Some` while recording
+    /// starts new segment files.
+    pub fn set_recording(&mut self, recording: Option<RecordingConfig>) -> Result<(), String> {
+        let pipeline = self.pipeline.lock().unwrap();
+        let mut branches = self.branches.lock().unwrap();
+
+        if let Some(branch) = branches.recording_branch.take() {
+            detach_branch(branch, true);
+        }
+        branches.recording = recording;
+        if let (Some(pipeline), Some(recording)) = (&*pipeline, &branches.recording) {
+            let branch = attach_recording(self.camera_index, pipeline, &self.config, recording)?;
+            branches.recording_branch = Some(branch);
+        }
+        Ok(())
+    }
+
+    /// Starts or stops delivering decoded frames by adding the frame sink to
+    /// the running pipeline or removing it.
+    pub fn set_frame_output(&mut self, output: Option<FrameOutput>) -> Result<(), String> {
+        let pipeline = self.pipeline.lock().unwrap();
+        let mut branches = self.branches.lock().unwrap();
+
+        if let Some(branch) = branches.frame_branch.take() {
+            detach_branch(branch, false);
+        }
+        branches.frames = output;
+        if let (Some(pipeline), Some(output)) = (&*pipeline, &branches.frames) {
+            let branch =
+                attach_frame_sink(self.camera_index, pipeline, &self.config, output.clone())?;
+            branches.frame_branch = Some(branch);
+        }
+        Ok(())
+    }
+
+    /// Asks a recording pipeline to send EOS so its muxers write their
+    /// indexes. Other pipelines have nothing to finalize and are stopped by
+    /// `stop` directly. Returns true if the request was sent.
+    pub fn request_finalize(&mut self) -> bool {
+        let recording = self.branches.lock().unwrap().recording.is_some();
+        if !recording || self.finalize_requested {
+            return self.finalize_requested;
+        }
+
+        if let Some(pipeline) = &*self.pipeline.lock().unwrap() {
+            self.finalize_requested = pipeline.send_event(gst::event::Eos::new());
+        }
+        self.finalize_requested
+    }
+
     pub fn stop(&mut self) {
-        *self.receiving.lock().unwrap() = false;
+        // Give recording pipelines the chance to finalize before anything is
+        // stopped; `run_pipeline` clears the pipeline once it has finished.
+        if self.request_finalize() {
+            let started = Instant::now();
+            while self.is_pipeline_running() && started.elapsed() < FINALIZE_TIMEOUT {
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
 
-        let _ = Command::new("taskkill")
-            .args(["/F", "/IM", "gst-launch-1.0.exe"])
-            .output();
+        *self.receiving.lock().unwrap() = false;
 
         if let Some(handle) = self.thread_handle.take() {
             std::thread::sleep(std::time::Duration::from_millis(500));
             let _ = handle.join();
         }
     }
+
+    /// The camera's most recent decoded frame, if one has arrived. An H.264
+    /// camera in mosaic mode is not decoded here, so a decoder is added for
+    /// the one frame, which waits up to `KEYFRAME_TIMEOUT` for a keyframe.
+    pub fn latest_frame(&self) -> Option<gst::Sample> {
+        let pipeline = self.pipeline.lock().unwrap().clone()?;
+        let sink = pipeline
+            .downcast_ref::<gst::Bin>()?
+            .by_name(snapshot::SNAPSHOT_SINK_NAME);
+        if let Some(sink) = sink {
+            return sink.property::<Option<gst::Sample>>("last-sample");
+        }
+
+        let (bin, tee) = decoded_branch(&pipeline, &self.config, &snapshot::snapshot_sink()).ok()?;
+        let sink = bin.by_name(snapshot::SNAPSHOT_SINK_NAME)?;
+        let branch = attach_branch(&pipeline, tee, bin).ok()?;
+        let started = Instant::now();
+        let mut frame = None;
+        while frame.is_none() && started.elapsed() < KEYFRAME_TIMEOUT {
+            thread::sleep(Duration::from_millis(50));
+            frame = sink.property::<Option<gst::Sample>>("last-sample");
+        }
+        detach_branch(branch, false);
+        frame
+    }
+
+    fn is_pipeline_running(&self) -> bool {
+        self.pipeline.lock().unwrap().is_some()
+    }
+}
+
+/// Whether a camera that falls back to TCP has gone without UDP packets for
+/// its fallback time since `last_packet`, or since the start before the
+/// first one.
+fn udp_silent(config: &CameraConfig, last_packet: Instant) -> bool {
+    match config.transport {
+        Transport::UdpWithTcpFallback(after) => last_packet.elapsed() >= after,
+        _ => false,
+    }
 }
 
+/// Runs the pipeline with `parse_launch` until the camera stops, so its
+/// decoded frames can be pulled from the `appsink` and handed to the frame
+/// output, its recording switched and its audio muted while it plays, its
+/// ULPFEC decoder and RTX receiver set up and its statistics read. Returns
+/// true if it was stopped to fall back to TCP.
 fn run_pipeline(
     camera_index: usize,
     config: CameraConfig,
     receiving: Arc<Mutex<bool>>,
     stats_collector: Option<Arc<Mutex<StatsCollector>>>,
-) {
-    let cmd = format!(
-        "gst-launch-1.0 --gst-debug=raptorqdec:5,rtpjitterbuffer:4 \
-        rtpbin latency=200 \
-        fec-decoders=\"fec,0=\\\"raptorqdec\\ name=raptor_{}\\ \
-        repair-window-tolerance\\=200\\\";\" name=rtp \
-        udpsrc port={} address=0.0.0.0 \
-        caps=\"application/x-rtp, payload=96, raptor-scheme-id=(string)6, \
-        repair-window=(string)200000, t=(string)1344\" ! \
-        queue max-size-buffers=0 max-size-time=0 max-size-bytes=0 ! rtp.recv_fec_sink_0_0 \
-        udpsrc port={} address=0.0.0.0 \
-        caps=\"application/x-rtp, media=video, clock-rate=90000, \
-        encoding-name=mp2t, payload=33\" ! \
-        queue max-size-buffers=0 max-size-time=0 max-size-bytes=0 ! \
-        netsim drop-probability=0.5 duplicate-probability=0.1 delay-distribution=normal ! \
-        rtp.recv_rtp_sink_0 \
-        rtp. ! rtpjitterbuffer latency=600 do-lost=true ! rtpmp2tdepay ! \
-        tsdemux ! h264parse ! avdec_h264 max-threads=4 ! videoconvert ! videorate ! \
-        video/x-raw,framerate=15/1 ! autovideosink sync=false",
-        camera_index,
-        config.fec_port,
-        config.rtp_port
-    );
+    running: Arc<Mutex<Option<gst::Element>>>,
+    branches: Arc<Mutex<LiveBranches>>,
+) -> bool {
+    println!("Camera {} - Starting GStreamer", camera_index + 1);
 
-    println!("Camera {} - Starting GStreamer with debug output capture", camera_index + 1);
-
-    if let Ok(mut child) = Command::new("cmd")
-        .args(["/C", &cmd])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-    {
-        if let Some(stderr) = child.stderr.take() {
-            let receiving_clone = Arc::clone(&receiving);
-            let stats_collector_clone = stats_collector.clone();
-            thread::spawn(move || {
-                let reader = BufReader::new(stderr);
-                for line in reader.lines() {
-                    if !*receiving_clone.lock().unwrap() {
-                        break;
-                    }
-                    
-                    if let Ok(line_content) = line {
-                        if line_content.contains("Successfully recovered packet") || 
-                           line_content.contains("Add Lost timer for #") {
-                            
-                            if let Some(ref stats_collector_arc) = stats_collector_clone {
-                                if let Ok(mut stats_collector) = stats_collector_arc.lock() {
-                                    stats_collector.parse_debug_line(&line_content);
-                                }
-                            }
+    // Both stay locked until the pipeline is published, so a recording or
+    // frame output switched meanwhile is either launched with it or
+    // attached to it afterwards.
+    let launched = {
+        let mut running = running.lock().unwrap();
+        let mut branches = branches.lock().unwrap();
+        let launched =
+            launch_pipeline(camera_index, &config, stats_collector.as_ref(), &mut branches);
+        match &launched {
+            Ok((pipeline, _)) => *running = Some(pipeline.clone()),
+            Err(_) => {
+                branches.recording_branch = None;
+                branches.frame_branch = None;
+            }
+        }
+        launched
+    };
+    let (pipeline, counts) = match launched {
+        Ok(launched) => launched,
+        Err(e) => {
+            println!("Camera {} - Failed to start GStreamer pipeline: {}", camera_index + 1, e);
+            return false;
+        }
+    };
+    let mut udp_packets = 0;
+    let mut last_udp_packet = Instant::now();
+    let mut fell_back = false;
+
+    let ulpfec_recovered = recovered_counter(&pipeline, ULPFEC_DECODER_NAME, "recovered");
+    let rtx_recovered = recovered_counter(&pipeline, RTX_RECEIVER_NAME, "num-rtx-assoc-packets");
+    let srt_stats = srt_stats_reader(&pipeline);
+    let jitter = jitter_reader(&pipeline);
+
+    if let Some(bus) = pipeline.bus() {
+        while *receiving.lock().unwrap() {
+            if let Some(collector) = &stats_collector {
+                let mut collector = collector.lock().unwrap();
+                collector.set_byte_counts(
+                    counts.rtp_bytes.load(Ordering::Relaxed),
+                    counts.video_bytes.load(Ordering::Relaxed),
+                );
+                if let Some(count) = ulpfec_recovered() {
+                    collector.set_ulpfec_recovered(count);
+                }
+                if let Some(count) = rtx_recovered() {
+                    collector.set_rtx_recovered(count);
+                }
+                if let Some(stats) = srt_stats() {
+                    collector.set_srt_stats(stats);
+                }
+                if let Some(jitter) = jitter() {
+                    collector.set_jitter(jitter);
+                }
+            }
+            let received = counts.udp_packets.load(Ordering::Relaxed);
+            if received > udp_packets {
+                udp_packets = received;
+                last_udp_packet = Instant::now();
+            }
+            if udp_silent(&config, last_udp_packet) {
+                fell_back = true;
+                break;
+            }
+
+            let message = match bus.timed_pop(gst::ClockTime::from_mseconds(100)) {
+                Some(message) => message,
+                None => continue,
+            };
+            match message.view() {
+                gst::MessageView::Eos(_) => break,
+                gst::MessageView::Error(err) => {
+                    println!("Camera {} - GStreamer error: {}", camera_index + 1, err.error());
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let _ = pipeline.set_state(gst::State::Null);
+    if let Some(collector) = &stats_collector {
+        STATS_ELEMENTS
+            .lock()
+            .unwrap()
+            .retain(|(_, watched)| !Arc::ptr_eq(watched, collector));
+    }
+    let mut running = running.lock().unwrap();
+    let mut branches = branches.lock().unwrap();
+    branches.recording_branch = None;
+    branches.frame_branch = None;
+    *running = None;
+    fell_back
+}
+
+fn launch_pipeline(
+    camera_index: usize,
+    config: &CameraConfig,
+    stats_collector: Option<&Arc<Mutex<StatsCollector>>>,
+    branches: &mut LiveBranches,
+) -> Result<(gst::Element, Arc<PacketCounts>), String> {
+    init_gstreamer()?;
+
+    let pipeline = gst::parse_launch(&build_pipeline_description(camera_index, config))
+        .map_err(|e| e.to_string())?;
+    let bin = pipeline
+        .clone()
+        .downcast::<gst::Bin>()
+        .map_err(|_| "Pipeline description did not produce a bin".to_string())?;
+
+    if let Some(collector) = stats_collector {
+        watch_stats_elements(&bin, collector);
+    }
+    let counts = count_packets(&bin);
+
+    // Once RTCP sender reports arrive, rtpbin tags the packets with the
+    // sender's NTP time. Older GStreamer versions lack the property.
+    if let Some(rtpbin) = bin.by_name("rtp") {
+        if rtpbin.find_property("add-reference-timestamp-meta").is_some() {
+            rtpbin.set_property("add-reference-timestamp-meta", true);
+        }
+    }
+
+    if let Some(recording) = &branches.recording {
+        let branch = attach_recording(camera_index, &pipeline, config, recording)?;
+        branches.recording_branch = Some(branch);
+    }
+    if let Some(output) = &branches.frames {
+        let branch = attach_frame_sink(camera_index, &pipeline, config, output.clone())?;
+        branches.frame_branch = Some(branch);
+    }
+    if let FecScheme::Ulpfec { .. } = config.fec {
+        attach_ulpfec_decoder(&bin)?;
+    }
+    if config.retransmission {
+        attach_rtx_receiver(&bin, config)?;
+    }
+    if matches!(config.fec, FecScheme::Ulpfec { .. }) || config.retransmission {
+        connect_payload_type_map(&bin, config)?;
+    }
+
+    if let Err(e) = pipeline.set_state(gst::State::Playing) {
+        let _ = pipeline.set_state(gst::State::Null);
+        return Err(e.to_string());
+    }
+
+    Ok((pipeline, counts))
+}
+
+/// Gives rtpbin a RED and ULPFEC decoder for each incoming stream, along
+/// with the packet storage ULPFEC recovers from.
+fn attach_ulpfec_decoder(bin: &gst::Bin) -> Result<(), String> {
+    let rtpbin = bin.by_name("rtp").ok_or("Pipeline has no rtpbin")?;
+
+    let storage = rtpbin
+        .emit_by_name::<Option<gst::Element>>("get-storage", &[&0u32])
+        .ok_or("rtpbin has no packet storage")?;
+    storage.set_property("size-time", ULPFEC_STORAGE_TIME.as_nanos() as u64);
+
+    rtpbin.connect("request-fec-decoder", false, |values| {
+        let decoder = values[0]
+            .get::<gst::Element>()
+            .ok()
+            .zip(values[1].get::<u32>().ok())
+            .and_then(|(rtpbin, session)| ulpfec_decoder(&rtpbin, session));
+        Some(decoder.to_value())
+    });
+
+    Ok(())
+}
+
+/// Answers rtpbin's questions for the caps of payload types the RTP port's
+/// caps do not describe: the one RED packets unwrap to, and the RTX one.
+fn connect_payload_type_map(bin: &gst::Bin, config: &CameraConfig) -> Result<(), String> {
+    let rtpbin = bin.by_name("rtp").ok_or("Pipeline has no rtpbin")?;
+
+    let payload_type = config.payload.payload_type();
+    let media_caps = format!(
+        "application/x-rtp, media=video, clock-rate=90000, {}",
+        config.payload.rtp_caps()
+    )
+    .parse::<gst::Caps>()
+    .map_err(|e| e.to_string())?;
+    let rtx_caps = format!(
+        "application/x-rtp, media=video, clock-rate=90000, encoding-name=RTX, payload={}",
+        RTX_PAYLOAD_TYPE
+    )
+    .parse::<gst::Caps>()
+    .map_err(|e| e.to_string())?;
+    rtpbin.connect("request-pt-map", false, move |values| {
+        let caps = match values[2].get::<u32>() {
+            Ok(pt) if pt == u32::from(payload_type) => Some(media_caps.clone()),
+            Ok(pt) if pt == u32::from(RTX_PAYLOAD_TYPE) => Some(rtx_caps.clone()),
+            _ => None,
+        };
+        Some(caps.to_value())
+    });
+
+    Ok(())
+}
+
+/// Gives rtpbin an RTX receiver for each session, which turns the packets
+/// the sender retransmits back into the ones its jitterbuffer asked for.
+fn attach_rtx_receiver(bin: &gst::Bin, config: &CameraConfig) -> Result<(), String> {
+    let rtpbin = bin.by_name("rtp").ok_or("Pipeline has no rtpbin")?;
+
+    let payload_type = config.payload.payload_type();
+    rtpbin.connect("request-aux-receiver", false, move |values| {
+        let receiver = values[1]
+            .get::<u32>()
+            .ok()
+            .and_then(|session| rtx_receiver(session, payload_type));
+        Some(receiver.to_value())
+    });
+
+    Ok(())
+}
+
+/// rtpbin links an aux receiver through pads named after the session.
+fn rtx_receiver(session: u32, payload_type: u8) -> Option<gst::Element> {
+    let receiver = gst::parse_bin_from_description(
+        &format!(
+            "rtprtxreceive name={} payload-type-map=\"application/x-rtp-pt-map, {}=(uint){}\"",
+            RTX_RECEIVER_NAME, payload_type, RTX_PAYLOAD_TYPE
+        ),
+        false,
+    )
+    .ok()?;
+    let element = receiver.by_name(RTX_RECEIVER_NAME)?;
+    for direction in ["sink", "src"] {
+        let pad = gst::GhostPad::with_target(
+            Some(&format!("{}_{}", direction, session)),
+            &element.static_pad(direction)?,
+        )
+        .ok()?;
+        receiver.add_pad(&pad).ok()?;
+    }
+    Some(receiver.upcast())
+}
+
+fn ulpfec_decoder(rtpbin: &gst::Element, session: u32) -> Option<gst::Element> {
+    let storage = rtpbin.emit_by_name::<Option<gst::glib::Object>>("get-internal-storage", &[&session])?;
+    let decoder = gst::parse_bin_from_description(
+        &format!(
+            "rtpreddec pt={} ! rtpulpfecdec name={} pt={}",
+            RED_PAYLOAD_TYPE, ULPFEC_DECODER_NAME, ULPFEC_PAYLOAD_TYPE
+        ),
+        true,
+    )
+    .ok()?;
+    decoder.by_name(ULPFEC_DECODER_NAME)?.set_property("storage", &storage);
+    Some(decoder.upcast())
+}
+
+/// Reads how many packets the pipeline's ULPFEC decoder or RTX receiver,
+/// by element name, has recovered; `None` without one.
+fn recovered_counter(
+    pipeline: &gst::Element,
+    name: &'static str,
+    property: &'static str,
+) -> impl Fn() -> Option<u32> {
+    let bin = pipeline.downcast_ref::<gst::Bin>().cloned();
+    move || {
+        let element = bin.as_ref()?.by_name(name)?;
+        Some(element.property::<u32>(property))
+    }
+}
+
+/// Reads the average jitter rtpbin's jitterbuffers measure, the highest
+/// where there are several sessions; `None` before packets have created
+/// them, for SRT cameras, and on GStreamer versions not reporting it.
+fn jitter_reader(pipeline: &gst::Element) -> impl Fn() -> Option<Duration> {
+    let rtpbin = pipeline
+        .downcast_ref::<gst::Bin>()
+        .and_then(|bin| bin.by_name("rtp"))
+        .and_then(|rtpbin| rtpbin.downcast::<gst::Bin>().ok());
+    move || {
+        rtpbin
+            .as_ref()?
+            .iterate_recurse()
+            .into_iter()
+            .flatten()
+            .filter(|element| {
+                element.factory().is_some_and(|factory| factory.name() == "rtpjitterbuffer")
+            })
+            .filter_map(|jitterbuffer| {
+                jitterbuffer
+                    .property::<gst::Structure>("stats")
+                    .get::<u64>("avg-jitter")
+                    .ok()
+            })
+            .max()
+            .map(Duration::from_nanos)
+    }
+}
+
+/// Reads the statistics of the pipeline's `srtsrc`; `None` without one.
+/// Fields older GStreamer versions do not report read as zero.
+fn srt_stats_reader(pipeline: &gst::Element) -> impl Fn() -> Option<SrtStats> {
+    let bin = pipeline.downcast_ref::<gst::Bin>().cloned();
+    move || {
+        let stats = bin
+            .as_ref()?
+            .by_name(SRT_SOURCE_NAME)?
+            .property::<gst::Structure>("stats");
+        // The fields are a mix of integer and floating point types.
+        let number = |field: &str| -> f64 {
+            stats
+                .value(field)
+                .ok()
+                .and_then(|value| {
+                    value
+                        .get::<f64>()
+                        .ok()
+                        .or_else(|| value.get::<i64>().ok().map(|n| n as f64))
+                        .or_else(|| value.get::<u64>().ok().map(|n| n as f64))
+                        .or_else(|| value.get::<i32>().ok().map(f64::from))
+                })
+                .unwrap_or_default()
+        };
+        Some(SrtStats {
+            rtt_ms: number("rtt-ms") as f32,
+            packets_lost: number("packets-received-lost") as u32,
+            packets_retransmitted: number("packets-received-retransmitted") as u32,
+            packets_dropped: number("packets-received-dropped") as u32,
+            receive_rate_mbps: number("receive-rate-mbps") as f32,
+        })
+    }
+}
+
+/// Adds the frame `appsink` to the decoded video and hands its samples to
+/// `output`, tagged with the RTP packet they were depayloaded from.
+fn attach_frame_sink(
+    camera_index: usize,
+    pipeline: &gst::Element,
+    config: &CameraConfig,
+    output: FrameOutput,
+) -> Result<Branch, String> {
+    let (bin, tee) = decoded_branch(pipeline, config, &frames::frame_sink_branch())?;
+    let reference: Arc<Mutex<Option<RtpReference>>> = Arc::new(Mutex::new(None));
+
+    let appsink = bin
+        .by_name(frames::FRAME_SINK_NAME)
+        .ok_or("Frame branch has no sink")?;
+    let sample_reference = Arc::clone(&reference);
+    appsink.connect("new-sample", false, move |values| {
+        let sample = values[0]
+            .get::<gst::Element>()
+            .ok()
+            .and_then(|sink| sink.emit_by_name::<Option<gst::Sample>>("pull-sample", &[]));
+        if let Some(sample) = sample {
+            let reference = *sample_reference.lock().unwrap();
+            frames::deliver_sample(&output.sink, camera_index, &sample, reference);
+        }
+        Some(gst::FlowReturn::Ok.to_value())
+    });
+
+    let mut branch = attach_branch(pipeline, tee, bin)?;
+    let depayloader_pad = pipeline
+        .downcast_ref::<gst::Bin>()
+        .and_then(|pipeline| pipeline.by_name(frames::DEPAYLOADER_NAME))
+        .and_then(|depayloader| depayloader.static_pad("sink"));
+    if let Some(pad) = depayloader_pad {
+        let probe = pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            if let Some(gst::PadProbeData::Buffer(buffer)) = &info.data {
+                if let Some(packet) = RtpReference::from_packet(buffer) {
+                    *reference.lock().unwrap() = Some(packet);
+                }
+            }
+            gst::PadProbeReturn::Ok
+        });
+        branch.probe = probe.map(|probe| (pad, probe));
+    }
+
+    Ok(branch)
+}
+
+/// Adds the recording branch to the `video` tee. Video up to the next
+/// keyframe is dropped so the first segment starts playable.
+fn attach_recording(
+    camera_index: usize,
+    pipeline: &gst::Element,
+    config: &CameraConfig,
+    recording: &RecordingConfig,
+) -> Result<Branch, String> {
+    let bin = parse_branch(&recording_branch(camera_index, config, recording))?;
+    drop_until_keyframe(&bin)?;
+    attach_branch(pipeline, "video", bin)
+}
+
+/// Parses a branch taking decoded video and names the tee to feed it from:
+/// `decoded`, or, where the pipeline decodes nothing itself (an H.264 camera
+/// in mosaic mode), `video` through a decoder of the branch's own that
+/// starts at the next keyframe.
+fn decoded_branch(
+    pipeline: &gst::Element,
+    config: &CameraConfig,
+    description: &str,
+) -> Result<(gst::Bin, &'static str), String> {
+    let decodes = pipeline
+        .downcast_ref::<gst::Bin>()
+        .and_then(|pipeline| pipeline.by_name("decoded"))
+        .is_some();
+    if decodes {
+        return Ok((parse_branch(description)?, "decoded"));
+    }
+
+    let bin = parse_branch(&format!(
+        "queue ! {}{} ! videoconvert ! {}",
+        reparse(config),
+        config.payload.decoder(),
+        description
+    ))?;
+    drop_until_keyframe(&bin)?;
+    Ok((bin, "video"))
+}
+
+/// Drops the video entering a branch up to the next keyframe, so what the
+/// branch decodes or writes starts clean.
+fn drop_until_keyframe(bin: &gst::Bin) -> Result<(), String> {
+    // Parsed branches always have their ghost sink pad.
+    let sink_pad = bin.static_pad("sink").ok_or("Branch has no input")?;
+    sink_pad.add_probe(gst::PadProbeType::BUFFER, |_, info| match &info.data {
+        Some(gst::PadProbeData::Buffer(buffer))
+            if buffer.flags().contains(gst::BufferFlags::DELTA_UNIT) =>
+        {
+            gst::PadProbeReturn::Drop
+        }
+        _ => gst::PadProbeReturn::Remove,
+    });
+    Ok(())
+}
+
+fn parse_branch(description: &str) -> Result<gst::Bin, String> {
+    let bin = gst::parse_bin_from_description(description, true).map_err(|e| e.to_string())?;
+    // Sinks prerolling in the branch must not take the playing pipeline
+    // back to paused.
+    bin.set_property("async-handling", true);
+    Ok(bin)
+}
+
+/// Adds `bin` to the pipeline, brings it to the pipeline's state and feeds
+/// it from a new pad of the tee named `tee`.
+fn attach_branch(pipeline: &gst::Element, tee: &str, bin: gst::Bin) -> Result<Branch, String> {
+    let pipeline = pipeline
+        .downcast_ref::<gst::Bin>()
+        .ok_or("Pipeline is not a bin")?;
+    let tee = pipeline
+        .by_name(tee)
+        .ok_or_else(|| format!("Pipeline has no {} tee", tee))?;
+    let sink_pad = bin.static_pad("sink").ok_or("Branch has no input")?;
+
+    pipeline.add(&bin).map_err(|e| e.to_string())?;
+    let tee_pad = match tee.request_pad_simple("src_%u") {
+        Some(pad) => pad,
+        None => {
+            let _ = pipeline.remove(&bin);
+            return Err("Tee has no pad for the branch".to_string());
+        }
+    };
+    let linked = bin
+        .sync_state_with_parent()
+        .map_err(|e| e.to_string())
+        .and_then(|_| tee_pad.link(&sink_pad).map(|_| ()).map_err(|e| e.to_string()));
+    if let Err(e) = linked {
+        let _ = bin.set_state(gst::State::Null);
+        let _ = pipeline.remove(&bin);
+        tee.release_request_pad(&tee_pad);
+        return Err(e);
+    }
+
+    Ok(Branch { bin, tee_pad, probe: None })
+}
+
+/// Unlinks a branch from its tee once no buffer is passing and shuts it
+/// down. With `finalize` the branch gets EOS first and is only shut down
+/// once that has reached its file sink, so the muxer can write its index.
+fn detach_branch(branch: Branch, finalize: bool) {
+    let Branch { bin, tee_pad, probe } = branch;
+    if let Some((pad, probe)) = probe {
+        pad.remove_probe(probe);
+    }
+
+    let file_sink_pad = bin
+        .iterate_recurse()
+        .into_iter()
+        .flatten()
+        .find(|element| element.factory().is_some_and(|factory| factory.name() == "filesink"))
+        .and_then(|sink| sink.static_pad("sink"));
+
+    tee_pad.clone().add_probe(gst::PadProbeType::IDLE, move |tee_pad, _| {
+        let sink_pad = bin.static_pad("sink");
+        if let Some(sink_pad) = &sink_pad {
+            let _ = tee_pad.unlink(sink_pad);
+        }
+        match (sink_pad, file_sink_pad.as_ref().filter(|_| finalize)) {
+            (Some(sink_pad), Some(file_sink_pad)) => {
+                let (bin, tee_pad) = (bin.clone(), tee_pad.clone());
+                file_sink_pad.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+                    let is_eos = match &info.data {
+                        Some(gst::PadProbeData::Event(event)) => {
+                            event.type_() == gst::EventType::Eos
                         }
+                        _ => false,
+                    };
+                    if !is_eos {
+                        return gst::PadProbeReturn::Ok;
                     }
-                }
-            });
+                    remove_branch(bin.clone(), tee_pad.clone());
+                    gst::PadProbeReturn::Remove
+                });
+                sink_pad.send_event(gst::event::Eos::new());
+            }
+            _ => remove_branch(bin.clone(), tee_pad.clone()),
         }
+        gst::PadProbeReturn::Remove
+    });
+}
 
-        loop {
-            if !*receiving.lock().unwrap() {
-                let _ = child.kill();
-                let _ = child.wait();
-                break;
+/// Shuts down a detached branch from its own thread, as neither the
+/// streaming threads nor the pad probes running on them may change its
+/// state.
+fn remove_branch(bin: gst::Bin, tee_pad: gst::Pad) {
+    thread::spawn(move || {
+        let _ = bin.set_state(gst::State::Null);
+        if let Some(pipeline) = bin.parent().and_then(|parent| parent.downcast::<gst::Bin>().ok()) {
+            let _ = pipeline.remove(&bin);
+        }
+        if let Some(tee) = tee_pad.parent_element() {
+            tee.release_request_pad(&tee_pad);
+        }
+    });
+}
+
+/// Initializes GStreamer, once. The debug output the stats are parsed from
+/// is routed to the cameras' stats collectors.
+fn init_gstreamer() -> Result<(), String> {
+    static INIT: OnceLock<Result<(), String>> = OnceLock::new();
+
+    INIT.get_or_init(|| {
+        gst::init().map_err(|e| format!("Failed to initialize GStreamer: {}", e))?;
+        gst::debug_set_active(true);
+        gst::debug_set_threshold_from_string(STATS_DEBUG, false);
+        // Unless asked for with GST_DEBUG, keep that output off the console.
+        if std::env::var_os("GST_DEBUG").is_none() {
+            gst::debug_remove_default_log_function();
+        }
+
+        gst::debug_add_log_function(|_, _, _, _, _, object, message| {
+            let (Some(object), Some(text)) = (object, message.get()) else {
+                return;
+            };
+            if !is_stats_line(&text) {
+                return;
             }
-            match child.try_wait() {
-                Ok(Some(_)) => break,
-                Ok(None) => std::thread::sleep(std::time::Duration::from_millis(100)),
-                Err(_) => break,
+            // Pads are logged as `element:pad`.
+            let object = object.to_string();
+            let element = object.split(':').next().unwrap_or_default();
+            let watched = STATS_ELEMENTS.lock().unwrap();
+            if let Some((_, collector)) = watched.iter().find(|(name, _)| name == element) {
+                if let Ok(mut collector) = collector.lock() {
+                    collector.parse_debug_line(&text);
+                }
             }
+        });
+        Ok(())
+    })
+    .clone()
+}
+
+/// Ties the stats elements of a pipeline to its collector,
+/// including the ones rtpbin creates once packets arrive.
+fn watch_stats_elements(bin: &gst::Bin, collector: &Arc<Mutex<StatsCollector>>) {
+    let collector = Arc::clone(collector);
+    let watch = move |element: &gst::Element| {
+        let is_stats_element = element
+            .factory()
+            .is_some_and(|factory| STATS_ELEMENT_FACTORIES.contains(&factory.name().as_str()));
+        if is_stats_element {
+            STATS_ELEMENTS
+                .lock()
+                .unwrap()
+                .push((element.name().to_string(), Arc::clone(&collector)));
         }
+    };
+
+    for element in bin.iterate_recurse().into_iter().flatten() {
+        watch(&element);
+    }
+    bin.connect_deep_element_added(move |_, _, element| watch(element));
+}
+
+/// Bytes seen by the byte-counting identities of a running pipeline, and
+/// the packets its `udpsrc`s have received. The pad probes only add to
+/// these; `run_pipeline` reads them along with the rest of the polled stats.
+#[derive(Default)]
+struct PacketCounts {
+    rtp_bytes: AtomicU64,
+    video_bytes: AtomicU64,
+    udp_packets: AtomicU64,
+}
+
+/// Counts the bytes passing the byte-counting identities and the packets
+/// leaving the `udpsrc`s with pad probes. The latter are counted as they
+/// arrive, before SRTP or rtpbin may drop them.
+fn count_packets(bin: &gst::Bin) -> Arc<PacketCounts> {
+    let counts = Arc::new(PacketCounts::default());
+    for name in [RTP_BYTES_NAME, VIDEO_BYTES_NAME] {
+        let Some(pad) = bin.by_name(name).and_then(|identity| identity.static_pad("sink")) else {
+            continue;
+        };
+
+        let counts = Arc::clone(&counts);
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            if let Some(gst::PadProbeData::Buffer(buffer)) = &info.data {
+                let counter = if name == RTP_BYTES_NAME {
+                    &counts.rtp_bytes
+                } else {
+                    &counts.video_bytes
+                };
+                counter.fetch_add(buffer.size() as u64, Ordering::Relaxed);
+            }
+            gst::PadProbeReturn::Ok
+        });
+    }
+
+    let udp_sources = bin
+        .iterate_recurse()
+        .into_iter()
+        .flatten()
+        .filter(|element| element.factory().is_some_and(|factory| factory.name() == "udpsrc"));
+    for udpsrc in udp_sources {
+        let Some(pad) = udpsrc.static_pad("src") else {
+            continue;
+        };
+        let counts = Arc::clone(&counts);
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, _| {
+            counts.udp_packets.fetch_add(1, Ordering::Relaxed);
+            gst::PadProbeReturn::Ok
+        });
+    }
+    counts
+}
+
+fn is_stats_line(line: &str) -> bool {
+    line.contains("Successfully recovered packet")
+        || line.contains("Add Lost timer for #")
+        || line.contains("Unable to unprotect buffer")
+}
+
+fn build_pipeline_description(camera_index: usize, config: &CameraConfig) -> String {
+    let branches = encoded_video_branches(camera_index, config);
+    let parser = config
+        .payload
+        .parser()
+        .map(|parser| format!("{} config-interval=-1 ! ", parser))
+        .unwrap_or_default();
+    let input = match &config.transport {
+        Transport::Srt(srt) => srt_input(config, srt),
+        _ => rtp_input(camera_index, config),
+    };
+
+    // The two identities count the bytes received and the video bytes they
+    // carry, for the payload overhead in the stats.
+    format!(
+        "{} ! identity name={} ! {}tee name=video ! queue ! {}{}{}",
+        input,
+        VIDEO_BYTES_NAME,
+        parser,
+        display_output(camera_index, config),
+        branches.concat(),
+        audio_branch(config)
+    )
+}
+
+/// rtpbin with its FEC and RTCP flows, up to the depayloaded (and, for
+/// MPEG-TS, demuxed) video.
+fn rtp_input(camera_index: usize, config: &CameraConfig) -> String {
+    let udpsrc_properties = udpsrc_properties(config);
+    let (media_type, srtp_caps, srtp_decoder) = srtp_elements(config);
+    let demux = if config.payload == PayloadFormat::MpegTs {
+        " ! tsdemux name=demux"
     } else {
-        println!("Camera {} - Failed to start GStreamer pipeline", camera_index + 1);
+        ""
+    };
+
+    // ULPFEC arrives inside the RTP stream, all of it wrapped in RED. Its
+    // decoder needs rtpbin's packet storage, so `launch_pipeline` hands it
+    // to rtpbin, which also has to report lost packets to it.
+    let (fec_decoders, fec_input, rtp_caps) = match config.fec {
+        FecScheme::RaptorQ => (
+            format!(
+                " fec-decoders=\"fec,0=\\\"raptorqdec\\ name=raptor_{}\\ \
+                repair-window-tolerance\\=200\\\";\"",
+                camera_index
+            ),
+            format!(
+                " {}{} ! \
+                queue max-size-buffers=0 max-size-time=0 max-size-bytes=0 ! rtp.recv_fec_sink_0_0",
+                flow_source(
+                    config,
+                    &config.fec_port,
+                    media_type,
+                    &format!(
+                        "payload=96, raptor-scheme-id=(string)6, repair-window=(string)200000, \
+                        t=(string)1344{}",
+                        srtp_caps
+                    )
+                ),
+                srtp_decoder
+            ),
+            config.payload.rtp_caps(),
+        ),
+        FecScheme::Ulpfec { .. } => (
+            " do-lost=true".to_string(),
+            String::new(),
+            format!("encoding-name=RED, payload={}", RED_PAYLOAD_TYPE),
+        ),
+    };
+
+    // rtpbin's jitterbuffer asks for lost packets with NACKs in its receiver
+    // reports, and `launch_pipeline` gives it the RTX receiver the
+    // retransmissions go through. Sender reports come back the other way.
+    let (retransmission, rtcp) = match config.rtcp_ports() {
+        Ok((rtcp_port, feedback_port)) if config.retransmission => (
+            " rtp-profile=avpf do-retransmission=true",
+            format!(
+                " udpsrc port={} {} ! rtp.recv_rtcp_sink_0 \
+                rtp.send_rtcp_src_0 ! udpsink host=\"{}\" port={} async=false sync=false",
+                rtcp_port, udpsrc_properties, config.sender_ip, feedback_port
+            ),
+        ),
+        _ => ("", String::new()),
+    };
+
+    // The simulated loss stands in for a lossy UDP network; a TCP connection
+    // delivers every packet, so its stream is left as it arrives.
+    let netsim = match config.transport {
+        Transport::Tcp(_) => "",
+        _ => "netsim drop-probability=0.5 duplicate-probability=0.1 delay-distribution=normal ! ",
+    };
+
+    format!(
+        "rtpbin latency=200{}{} name=rtp{}{} \
+        {}{} ! \
+        queue max-size-buffers=0 max-size-time=0 max-size-bytes=0 ! \
+        {}rtp.recv_rtp_sink_0 \
+        rtp. ! rtpjitterbuffer latency=600 do-lost=true ! \
+        identity name={} ! {} name={}{}",
+        retransmission,
+        fec_decoders,
+        fec_input,
+        rtcp,
+        flow_source(
+            config,
+            &config.rtp_port,
+            media_type,
+            &format!("media=video, clock-rate=90000, {}{}", rtp_caps, srtp_caps)
+        ),
+        srtp_decoder,
+        netsim,
+        RTP_BYTES_NAME,
+        config.payload.depayloader(),
+        frames::DEPAYLOADER_NAME,
+        demux
+    )
+}
+
+/// Source of the RTP or FEC flow on `port`, ending in `media_type` packets
+/// with the caps `fields`: a `udpsrc`, or a TCP connection with the RFC 4571
+/// length framing taken off.
+fn flow_source(config: &CameraConfig, port: &str, media_type: &str, fields: &str) -> String {
+    let tcp_source = match config.transport {
+        Transport::Tcp(TcpMode::Listen) => {
+            format!("tcpserversrc host=\"{}\" port={}", config.bind_address, port)
+        }
+        Transport::Tcp(TcpMode::Connect) => {
+            format!("tcpclientsrc host=\"{}\" port={}", config.sender_ip, port)
+        }
+        _ => {
+            return format!(
+                "udpsrc port={} {} caps=\"{}, {}\"",
+                port,
+                udpsrc_properties(config),
+                media_type,
+                fields
+            );
+        }
+    };
+    format!(
+        "{} ! capsfilter caps=\"{}-stream, {}\" ! rtpstreamdepay",
+        tcp_source, media_type, fields
+    )
+}
+
+/// `srtsrc` on the RTP port, up to the demuxed video. The byte-counting
+/// identity sees the MPEG-TS as it arrives.
+fn srt_input(config: &CameraConfig, srt: &SrtConfig) -> String {
+    let port = config.rtp_port.trim().parse().unwrap_or_default();
+    format!(
+        "srtsrc name={} {} ! \
+        queue max-size-buffers=0 max-size-time=0 max-size-bytes=0 ! \
+        identity name={} ! tsdemux name=demux",
+        SRT_SOURCE_NAME,
+        srt.properties(&config.sender_ip, &config.bind_address, port),
+        RTP_BYTES_NAME
+    )
+}
+
+/// Decodes the audio stream of the MPEG-TS, Opus or AAC, into the default
+/// output. It plays against the pipeline clock like the video, which keeps
+/// the two in sync. Other payloads carry no audio and have no `demux`.
+fn audio_branch(config: &CameraConfig) -> String {
+    if !config.audio || config.payload != PayloadFormat::MpegTs {
+        return String::new();
     }
+
+    format!(
+        " demux. ! capsfilter caps=\"audio/x-opus;audio/mpeg\" ! queue ! decodebin ! \
+        audioconvert ! audioresample ! volume name={} mute={} ! autoaudiosink sync=true",
+        AUDIO_VOLUME_NAME, config.audio_muted
+    )
+}
+
+/// End of the main path: the camera's own window with the stats overlay, or,
+/// in mosaic mode, the still encoded video sent over loopback to the mosaic
+/// process, which draws the overlay itself. Cameras whose decoded frames go
+/// to library code only may have no window at all.
+fn display_output(camera_index: usize, config: &CameraConfig) -> String {
+    let decoded = decoded_video_branch();
+    if let Some(port) = config.mosaic_port {
+        let payloader = format!(
+            "rtph264pay config-interval=1 pt=96 ! udpsink host=127.0.0.1 port={} sync=false",
+            port
+        );
+        // The mosaic takes H.264. Other codecs are decoded once, for the
+        // snapshots and frames as well, and re-encoded for it.
+        if config.payload.is_h264() {
+            return payloader;
+        }
+        return format!(
+            "{} ! videoconvert ! tee name=decoded ! queue ! x264enc tune=zerolatency \
+            speed-preset=ultrafast bitrate=1000 key-int-max=15 ! {}{}",
+            config.payload.decoder(),
+            payloader,
+            decoded
+        );
+    }
+
+    if config.frames.as_ref().is_some_and(|output| !output.display) {
+        return format!(
+            "{} ! videoconvert ! tee name=decoded{}",
+            config.payload.decoder(),
+            decoded
+        );
+    }
+
+    let (overlay, text_source) =
+        overlay::overlay_element(camera_index, "stats_overlay", "Monospace 12");
+    // Shown as soon as it arrives unless it has to wait for its audio.
+    let sync = config.audio;
+    // Snapshots and frames take the decoded video before the overlay is drawn.
+    format!(
+        "{} ! videoconvert ! tee name=decoded ! queue ! {} ! videorate ! \
+        video/x-raw,framerate=15/1 ! autovideosink sync={}{}{}",
+        config.payload.decoder(),
+        overlay, sync, text_source, decoded
+    )
+}
+
+/// Branch fed from the decoded video through the `decoded` tee. The frame
+/// sink is added to the tee while the pipeline runs.
+fn decoded_video_branch() -> String {
+    format!(" decoded. ! {}", snapshot::snapshot_sink())
+}
+
+/// Branches fed from the parsed, still encoded video through the `video` tee.
+/// Each one re-parses so it can negotiate the stream format it needs. The
+/// recording branch, and the decoder of an H.264 camera in mosaic mode that
+/// frames or a snapshot are wanted from, are added to the tee while the
+/// pipeline runs.
+fn encoded_video_branches(camera_index: usize, config: &CameraConfig) -> Vec<String> {
+    let mut branches = Vec::new();
+
+    if let Some(dvr) = &config.dvr {
+        branches.push(dvr_branch(camera_index, config, dvr));
+    }
+
+    branches
+}
+
+/// Keeps the last minutes of video as a ring of short MPEG-TS segments;
+/// splitmuxsink overwrites the oldest file once `max-files` is reached.
+fn dvr_branch(camera_index: usize, config: &CameraConfig, dvr: &DvrConfig) -> String {
+    let location = dvr::segment_directory(dvr, camera_index)
+        .join("dvr_%05d.ts")
+        .display()
+        .to_string()
+        .replace('\\', "/");
+
+    format!(
+        " video. ! queue max-size-buffers=0 max-size-time=0 max-size-bytes=0 ! {}\
+        splitmuxsink location=\"{}\" muxer-factory=mpegtsmux max-size-time={} max-files={}",
+        reparse(config),
+        location,
+        DVR_SEGMENT_SECONDS * 1_000_000_000,
+        dvr::max_segments(dvr)
+    )
+}
+
+/// Branch remuxing the stream into segment files named
+/// `camera<N>_<start time>_<segment number>.<ext>`. Segment limits that do
+/// not fit are rejected by `CameraBackend::start_recording`.
+fn recording_branch(camera_index: usize, config: &CameraConfig, recording: &RecordingConfig) -> String {
+    let (max_size_time, max_size_bytes) = recording.segment_limits().unwrap_or_default();
+    let location = format!(
+        "{}/camera{}_{}_%05d.{}",
+        recording.directory.replace('\\', "/").trim_end_matches('/'),
+        camera_index + 1,
+        format_timestamp(unix_now()),
+        recording.format.extension()
+    );
+    let muxer_properties = recording
+        .format
+        .muxer_properties()
+        .map(|properties| format!(" muxer-properties=\"{}\"", properties))
+        .unwrap_or_default();
+
+    format!(
+        "queue max-size-buffers=0 max-size-time=0 max-size-bytes=0 ! {}\
+        splitmuxsink location=\"{}\" muxer-factory={}{} max-size-time={} max-size-bytes={}",
+        reparse(config),
+        location,
+        recording.format.muxer(),
+        muxer_properties,
+        max_size_time,
+        max_size_bytes
+    )
+}
+
+/// Parser stage at the start of a `video` tee branch, if the codec has one.
+fn reparse(config: &CameraConfig) -> String {
+    config
+        .payload
+        .parser()
+        .map(|parser| format!("{} ! ", parser))
+        .unwrap_or_default()
+}
+
+/// Caps media type, extra SRTP caps fields and the `srtpdec` stage used on
+/// each incoming flow. `srtpdec` takes its key from the `udpsrc` caps; keys
+/// listed in `keys` are picked by the MKI carried in each packet.
+fn srtp_elements(config: &CameraConfig) -> (&'static str, String, &'static str) {
+    match &config.srtp {
+        Some(srtp) => {
+            let mut caps = format!(
+                ", srtp-key=(buffer){key}, srtp-cipher=(string){cipher}, \
+                srtp-auth=(string){auth}, srtcp-cipher=(string){cipher}, srtcp-auth=(string){auth}",
+                key = srtp.key,
+                cipher = srtp.cipher.name(),
+                auth = srtp.cipher.auth()
+            );
+            if let Some(mki) = srtp.mki {
+                caps.push_str(&format!(", mki=(buffer){:08X}", mki));
+            }
+            if !srtp.additional_keys.is_empty() {
+                let keys: Vec<String> = srtp
+                    .additional_keys
+                    .iter()
+                    .map(|k| format!("[key, srtp-key=(buffer){}, mki=(buffer){:08X}]", k.key, k.mki))
+                    .collect();
+                caps.push_str(&format!(", keys=<{}>", keys.join(", ")));
+            }
+            ("application/x-srtp", caps, " ! srtpdec")
+        }
+        None => ("application/x-rtp", String::new(), ""),
+    }
+}
+
+/// `udpsrc` properties for the configured bind address, joining the group
+/// (optionally source-specific) when that address is a multicast group.
+fn udpsrc_properties(config: &CameraConfig) -> String {
+    let mut properties = format!("address=\"{}\"", config.bind_address);
+
+    let is_multicast = parse_ip_address(&config.bind_address)
+        .map(|addr| addr.is_multicast())
+        .unwrap_or(false);
+
+    if is_multicast {
+        properties.push_str(" auto-multicast=true");
+        if !config.multicast_iface.is_empty() {
+            properties.push_str(&format!(" multicast-iface=\"{}\"", config.multicast_iface));
+        }
+        if config.source_specific {
+            properties.push_str(&format!(" multicast-source=\"+{}\"", config.sender_ip));
+        }
+    }
+
+    properties
 }
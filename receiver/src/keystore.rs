@@ -0,0 +1,77 @@
+use crate::types::{SrtpConfig, SrtpKey};
+pub use common::keyfile::KeyEntry;
+use common::keyfile::{read_key_file, write_key_file};
+use std::path::{Path, PathBuf};
+
+/// File-backed store of per-camera keys, in the same line format as the
+/// sender's key store and bundles:
+/// `<camera> <key id> <cipher> <activation unix time> <hex key>`.
+pub struct KeyStore {
+    path: PathBuf,
+    entries: Vec<KeyEntry>,
+}
+
+impl KeyStore {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let entries = if path.exists() {
+            read_key_file(path)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        write_key_file(&self.path, "receiver key store", self.entries.iter())
+    }
+
+    /// Merges a sender-generated bundle into the store, replacing keys with the
+    /// same camera and key ID. Returns the number of keys imported.
+    pub fn import_bundle(&mut self, path: &Path) -> Result<usize, String> {
+        let imported = read_key_file(path)?;
+
+        for entry in &imported {
+            self.entries
+                .retain(|e| e.camera != entry.camera || e.key_id != entry.key_id);
+            self.entries.push(entry.clone());
+        }
+
+        self.save()?;
+        Ok(imported.len())
+    }
+
+    /// SRTP settings for `camera`: the active key as primary, plus the key it
+    /// replaced and every scheduled key, so a rotation on the sender does not
+    /// interrupt a running stream.
+    pub fn srtp_config(&self, camera: usize, now: u64) -> Option<SrtpConfig> {
+        let mut keys: Vec<&KeyEntry> = self.entries.iter().filter(|e| e.camera == camera).collect();
+        keys.sort_by_key(|e| e.activates_at);
+
+        let active_position = keys.iter().rposition(|e| e.activates_at <= now)?;
+        let active = keys[active_position];
+
+        let additional_keys = keys
+            .iter()
+            .enumerate()
+            .filter(|(position, e)| {
+                e.cipher == active.cipher
+                    && (*position + 1 == active_position || *position > active_position)
+            })
+            .map(|(_, e)| SrtpKey {
+                mki: e.key_id,
+                key: e.key.clone(),
+            })
+            .collect();
+
+        Some(SrtpConfig {
+            cipher: active.cipher,
+            key: active.key.clone(),
+            mki: Some(active.key_id),
+            additional_keys,
+        })
+    }
+}
@@ -0,0 +1,18 @@
+//! The receiver without its GUI, for embedding in other Rust programs.
+//!
+//! [`backend::CameraBackend`] starts and stops cameras as the receiver
+//! application does. [`backend::CameraBackend::set_frame_output`] hands a
+//! camera's decoded frames to a callback or channel, with or without its
+//! display window.
+
+pub mod backend;
+pub mod dvr;
+pub mod frames;
+pub mod gstreamer;
+pub mod keystore;
+pub mod mosaic;
+pub mod overlay;
+pub mod retention;
+pub mod snapshot;
+pub mod stats_collector;
+pub mod types;
@@ -1,16 +1,30 @@
-mod backend;
-mod gstreamer;
-mod stats_collector;
-mod types;
+mod cli;
 mod ui;
 
-use backend::CameraBackend;
 use native_windows_gui as nwg;
+use receiver::backend::CameraBackend;
 use std::cell::RefCell;
 use std::rc::Rc;
 use ui::ReceiverUI;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match cli::parse_args(&args) {
+        Ok(Some(options)) => {
+            let mut backend = CameraBackend::new();
+            cli::run(&mut backend, options);
+            return;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{}\n", e);
+            }
+            eprintln!("{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    }
+
     nwg::init().expect("Failed to init Native Windows GUI");
 
     let backend = Rc::new(RefCell::new(CameraBackend::new()));
@@ -25,6 +39,38 @@ fn main() {
         nwg::Event::OnButtonClick => {
             let ui_ref = handler_ui.borrow();
 
+            if let Some(import_handle) = ui_ref.get_button_handle(0, "import_keys") {
+                if handle == *import_handle {
+                    drop(ui_ref);
+                    handler_ui.borrow_mut().handle_import_keys();
+                    return;
+                }
+            }
+
+            if let Some(retention_handle) = ui_ref.get_button_handle(0, "apply_retention") {
+                if handle == *retention_handle {
+                    drop(ui_ref);
+                    handler_ui.borrow_mut().handle_apply_retention();
+                    return;
+                }
+            }
+
+            if let Some(mosaic_handle) = ui_ref.get_button_handle(0, "mosaic") {
+                if handle == *mosaic_handle {
+                    drop(ui_ref);
+                    handler_ui.borrow_mut().handle_mosaic_button();
+                    return;
+                }
+            }
+
+            if let Some(clip_handle) = ui_ref.get_button_handle(0, "apply_clip") {
+                if handle == *clip_handle {
+                    drop(ui_ref);
+                    handler_ui.borrow_mut().handle_apply_clip();
+                    return;
+                }
+            }
+
             for i in 0..4 {
                 if let Some(start_handle) = ui_ref.get_button_handle(i, "start") {
                     if handle == *start_handle {
@@ -33,6 +79,48 @@ fn main() {
                         return;
                     }
                 }
+                if let Some(record_handle) = ui_ref.get_button_handle(i, "record") {
+                    if handle == *record_handle {
+                        drop(ui_ref);
+                        handler_ui.borrow_mut().handle_record_button(i);
+                        return;
+                    }
+                }
+                if let Some(replay_handle) = ui_ref.get_button_handle(i, "replay") {
+                    if handle == *replay_handle {
+                        drop(ui_ref);
+                        handler_ui.borrow_mut().handle_replay_button(i);
+                        return;
+                    }
+                }
+                if let Some(snapshot_handle) = ui_ref.get_button_handle(i, "snapshot") {
+                    if handle == *snapshot_handle {
+                        drop(ui_ref);
+                        handler_ui.borrow_mut().handle_snapshot_button(i);
+                        return;
+                    }
+                }
+                if let Some(overlay_handle) = ui_ref.get_button_handle(i, "overlay") {
+                    if handle == *overlay_handle {
+                        drop(ui_ref);
+                        handler_ui.borrow_mut().handle_overlay_checkbox(i);
+                        return;
+                    }
+                }
+                if let Some(mute_handle) = ui_ref.get_button_handle(i, "mute") {
+                    if handle == *mute_handle {
+                        drop(ui_ref);
+                        handler_ui.borrow_mut().handle_mute_checkbox(i);
+                        return;
+                    }
+                }
+            }
+        }
+        nwg::Event::OnComboxBoxSelection => {
+            let ui_ref = handler_ui.borrow();
+            if handle == *ui_ref.get_mosaic_layout_handle() {
+                drop(ui_ref);
+                handler_ui.borrow_mut().handle_mosaic_layout_selection();
             }
         }
         nwg::Event::OnTimerTick => {
@@ -0,0 +1,166 @@
+use crate::overlay;
+use common::process::{kill_tree, shell};
+use std::process::{Child, Stdio};
+
+/// Loopback port camera N sends its video to in mosaic mode is this plus N-1.
+pub const MOSAIC_BASE_PORT: u16 = 5600;
+
+const MOSAIC_WIDTH: u32 = 1280;
+const MOSAIC_HEIGHT: u32 = 720;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MosaicLayout {
+    Grid2x2,
+    Grid3x3,
+    /// One large tile for camera 1 and five small ones around it.
+    OnePlusFive,
+    /// A single camera filling the whole window.
+    Maximized(usize),
+}
+
+impl MosaicLayout {
+    pub const GRIDS: [MosaicLayout; 3] = [
+        MosaicLayout::Grid2x2,
+        MosaicLayout::Grid3x3,
+        MosaicLayout::OnePlusFive,
+    ];
+
+    pub fn name(&self) -> String {
+        match self {
+            MosaicLayout::Grid2x2 => "2x2".to_string(),
+            MosaicLayout::Grid3x3 => "3x3".to_string(),
+            MosaicLayout::OnePlusFive => "1+5".to_string(),
+            MosaicLayout::Maximized(camera_index) => format!("Camera {}", camera_index + 1),
+        }
+    }
+
+    /// Parses a layout name, or a 1-based camera number for a maximized tile.
+    pub fn from_name(name: &str, camera_count: usize) -> Option<MosaicLayout> {
+        let name = name.trim();
+        if let Some(layout) = MosaicLayout::GRIDS.iter().find(|l| l.name() == name) {
+            return Some(*layout);
+        }
+        match name.parse::<usize>() {
+            Ok(number) if (1..=camera_count).contains(&number) => {
+                Some(MosaicLayout::Maximized(number - 1))
+            }
+            _ => None,
+        }
+    }
+
+    /// Cameras shown and the tile each one gets as (x, y, width, height).
+    fn tiles(&self, camera_count: usize) -> Vec<(usize, (u32, u32, u32, u32))> {
+        let (w, h) = (MOSAIC_WIDTH, MOSAIC_HEIGHT);
+        let grid = |n: u32| -> Vec<(u32, u32, u32, u32)> {
+            (0..n * n)
+                .map(|k| ((k % n) * w / n, (k / n) * h / n, w / n, h / n))
+                .collect()
+        };
+
+        let rects = match self {
+            MosaicLayout::Grid2x2 => grid(2),
+            MosaicLayout::Grid3x3 => grid(3),
+            MosaicLayout::OnePlusFive => {
+                let (u, v) = (w / 3, h / 3);
+                vec![
+                    (0, 0, 2 * u, 2 * v),
+                    (2 * u, 0, u, v),
+                    (2 * u, v, u, v),
+                    (0, 2 * v, u, v),
+                    (u, 2 * v, u, v),
+                    (2 * u, 2 * v, u, v),
+                ]
+            }
+            MosaicLayout::Maximized(camera_index) => {
+                return vec![(*camera_index, (0, 0, w, h))];
+            }
+        };
+
+        (0..camera_count).zip(rects).collect()
+    }
+}
+
+pub fn mosaic_port(camera_index: usize) -> u16 {
+    MOSAIC_BASE_PORT + camera_index as u16
+}
+
+/// Window compositing the cameras' loopback feeds into one grid. Tile labels
+/// are the cameras' overlay text, sent by `CameraBackend`. Switching layout
+/// restarts only this process; the camera pipelines keep sending.
+pub struct MosaicView {
+    layout: MosaicLayout,
+    child: Child,
+}
+
+impl MosaicView {
+    pub fn start(layout: MosaicLayout, camera_count: usize) -> Result<Self, String> {
+        let cmd = build_mosaic_command(layout, camera_count);
+
+        println!("Mosaic - Starting {} layout", layout.name());
+
+        let child = shell(&cmd)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start mosaic: {}", e))?;
+
+        Ok(Self { layout, child })
+    }
+
+    pub fn layout(&self) -> MosaicLayout {
+        self.layout
+    }
+
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    pub fn stop(&mut self) {
+        if self.is_running() {
+            kill_tree(self.child.id());
+        }
+        let _ = self.child.wait();
+    }
+}
+
+fn build_mosaic_command(layout: MosaicLayout, camera_count: usize) -> String {
+    let tiles = layout.tiles(camera_count);
+
+    // Pad 0 is a black live background so the mosaic keeps running while
+    // some cameras are stopped.
+    let mut pads = String::new();
+    let mut inputs = format!(
+        " videotestsrc is-live=true pattern=black ! \
+        video/x-raw,width={},height={},framerate=15/1 ! mix.sink_0",
+        MOSAIC_WIDTH, MOSAIC_HEIGHT
+    );
+
+    for (pad, (camera_index, (x, y, width, height))) in tiles.iter().enumerate() {
+        let pad = pad + 1;
+        // The tile label doubles as the camera's stats overlay.
+        let (label, text_source) =
+            overlay::overlay_element(*camera_index, &format!("label_{}", pad), "Sans 14");
+        pads.push_str(&format!(
+            " sink_{pad}::xpos={x} sink_{pad}::ypos={y} sink_{pad}::width={width} \
+            sink_{pad}::height={height} sink_{pad}::zorder={pad}"
+        ));
+        inputs.push_str(&format!(
+            " udpsrc port={} caps=\"application/x-rtp, media=video, clock-rate=90000, \
+            encoding-name=H264, payload=96\" ! rtpjitterbuffer latency=100 ! rtph264depay ! \
+            h264parse ! avdec_h264 ! videoconvert ! videoscale ! \
+            video/x-raw,width={},height={} ! {} ! queue ! mix.sink_{}{}",
+            mosaic_port(*camera_index),
+            width,
+            height,
+            label,
+            pad,
+            text_source
+        ));
+    }
+
+    format!(
+        "gst-launch-1.0 compositor name=mix background=black sink_0::zorder=0{} ! \
+        videoconvert ! autovideosink sync=false{}",
+        pads, inputs
+    )
+}
@@ -0,0 +1,95 @@
+use crate::stats_collector::StreamStats;
+use std::net::{Ipv4Addr, UdpSocket};
+
+/// Loopback port the overlay text for camera N is sent to is this plus N-1.
+pub const OVERLAY_BASE_PORT: u16 = 5700;
+
+pub fn overlay_port(camera_index: usize) -> u16 {
+    OVERLAY_BASE_PORT + camera_index as u16
+}
+
+/// A `textoverlay` named `name` whose text comes from the camera's overlay
+/// port rather than a fixed property, so it can change while gst-launch runs.
+/// Returns the element and the text source branch that feeds it; the branch
+/// must be appended to the pipeline after the element's chain.
+pub fn overlay_element(camera_index: usize, name: &str, font: &str) -> (String, String) {
+    let element = format!(
+        "textoverlay name={} wait-text=false valignment=top halignment=left \
+        font-desc=\"{}\" shaded-background=true",
+        name, font
+    );
+    let source = format!(
+        " udpsrc address=127.0.0.1 port={} caps=\"text/x-raw, format=(string)utf8\" ! {}.text_sink",
+        overlay_port(camera_index),
+        name
+    );
+    (element, source)
+}
+
+/// Overlay line for a camera. Without stats only the camera name is shown.
+pub fn overlay_text(camera_index: usize, stats: Option<&StreamStats>) -> String {
+    match stats {
+        Some(stats) => format!(
+            "Camera {} | {:.1} kbps | Lost {} | Recovered {} FEC / {} RTX ({:.1}%) | \
+            Jitter {:.1} ms",
+            camera_index + 1,
+            stats.bitrate,
+            stats.packets_lost,
+            stats.fec_recovered,
+            stats.rtx_recovered,
+            stats.repair_rate,
+            stats.jitter
+        ),
+        None => format!("Camera {}", camera_index + 1),
+    }
+}
+
+/// Sends overlay text to the pipelines over loopback.
+pub struct OverlaySender {
+    socket: Option<UdpSocket>,
+}
+
+impl OverlaySender {
+    pub fn new() -> Self {
+        let socket = match UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)) {
+            Ok(socket) => Some(socket),
+            Err(e) => {
+                println!("Warning: stats overlay unavailable: {}", e);
+                None
+            }
+        };
+        Self { socket }
+    }
+
+    /// Replaces the camera's overlay text. An empty text clears the overlay.
+    pub fn send(&self, camera_index: usize, text: &str) {
+        // An empty datagram carries no buffer, so a blank is sent instead.
+        let text = if text.is_empty() { " " } else { text };
+        if let Some(socket) = &self.socket {
+            let _ = socket.send_to(text.as_bytes(), (Ipv4Addr::LOCALHOST, overlay_port(camera_index)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_the_overlay_line() {
+        let stats = StreamStats {
+            bitrate: 2500.0,
+            packets_lost: 12,
+            fec_recovered: 9,
+            rtx_recovered: 2,
+            repair_rate: 91.66,
+            jitter: 4.0,
+            ..StreamStats::default()
+        };
+        assert_eq!(
+            overlay_text(1, Some(&stats)),
+            "Camera 2 | 2500.0 kbps | Lost 12 | Recovered 9 FEC / 2 RTX (91.7%) | Jitter 4.0 ms"
+        );
+        assert_eq!(overlay_text(0, None), "Camera 1");
+    }
+}
@@ -0,0 +1,495 @@
+use crate::types::RecordingFormat;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often recording directories are checked against the policy.
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Limits applied to recorded segments. A value of 0 disables that limit.
+#[derive(Clone, Debug)]
+pub struct RetentionPolicy {
+    pub max_age_hours: u64,
+    pub max_megabytes_per_camera: u64,
+    pub min_free_megabytes: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_hours: 7 * 24,
+            max_megabytes_per_camera: 50_000,
+            min_free_megabytes: 2_048,
+        }
+    }
+}
+
+#[derive(Default)]
+struct RetentionState {
+    policy: RetentionPolicy,
+    directories: Vec<String>,
+    warning: Option<String>,
+    out_of_space: bool,
+}
+
+struct Segment {
+    camera: usize,
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+/// Deletes the oldest recorded segments to keep recordings within the
+/// retention policy, and reports when the disk is too full to keep recording.
+pub struct RetentionManager {
+    state: Arc<Mutex<RetentionState>>,
+    running: Arc<Mutex<bool>>,
+    thread_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl RetentionManager {
+    pub fn new(policy: RetentionPolicy) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(RetentionState {
+                policy,
+                ..Default::default()
+            })),
+            running: Arc::new(Mutex::new(false)),
+            thread_handle: None,
+        }
+    }
+
+    pub fn start(&mut self) {
+        *self.running.lock().unwrap() = true;
+
+        let state = Arc::clone(&self.state);
+        let running = Arc::clone(&self.running);
+
+        self.thread_handle = Some(thread::spawn(move || {
+            while *running.lock().unwrap() {
+                enforce(&state, free_space);
+
+                let mut waited = Duration::ZERO;
+                while waited < CHECK_INTERVAL && *running.lock().unwrap() {
+                    thread::sleep(Duration::from_millis(500));
+                    waited += Duration::from_millis(500);
+                }
+            }
+        }));
+    }
+
+    pub fn stop(&mut self) {
+        *self.running.lock().unwrap() = false;
+
+        if let Some(handle) = self.thread_handle.take() {
+            let _ = handle.join();
+        }
+    }
+
+    /// Adds a directory that recordings are written to.
+    pub fn watch_directory(&self, directory: &str) {
+        let mut state = self.state.lock().unwrap();
+        if !state.directories.iter().any(|d| d == directory) {
+            state.directories.push(directory.to_string());
+        }
+    }
+
+    pub fn set_policy(&self, policy: RetentionPolicy) {
+        self.state.lock().unwrap().policy = policy;
+    }
+
+    pub fn policy(&self) -> RetentionPolicy {
+        self.state.lock().unwrap().policy.clone()
+    }
+
+    /// Warning raised by the last check, if it had to prune for space or found
+    /// the disk too full to record.
+    pub fn warning(&self) -> Option<String> {
+        self.state.lock().unwrap().warning.clone()
+    }
+
+    /// True while free space stays under the minimum even after pruning.
+    pub fn out_of_space(&self) -> bool {
+        self.state.lock().unwrap().out_of_space
+    }
+}
+
+/// Applies the policy to every watched directory once. `free_space` reports
+/// the free bytes on a directory's volume.
+fn enforce(state: &Arc<Mutex<RetentionState>>, free_space: impl Fn(&Path) -> Option<u64>) {
+    let (policy, directories, was_out_of_space) = {
+        let state = state.lock().unwrap();
+        (state.policy.clone(), state.directories.clone(), state.out_of_space)
+    };
+
+    let mut warnings = Vec::new();
+    let mut out_of_space = false;
+
+    for directory in &directories {
+        let mut segments = list_segments(Path::new(directory));
+        segments.sort_by_key(|s| s.modified);
+
+        // The newest segment of each camera may still be written to.
+        let newest: Vec<PathBuf> = newest_per_camera(&segments);
+        segments.retain(|s| !newest.contains(&s.path));
+
+        if policy.max_age_hours > 0 {
+            let max_age = Duration::from_secs(policy.max_age_hours.saturating_mul(3_600));
+            segments.retain(|s| {
+                let expired = s.modified.elapsed().map(|age| age > max_age).unwrap_or(false);
+                !(expired && delete_segment(s))
+            });
+        }
+
+        if policy.max_megabytes_per_camera > 0 {
+            let max_bytes = policy.max_megabytes_per_camera.saturating_mul(1024 * 1024);
+            let mut cameras: Vec<usize> = segments.iter().map(|s| s.camera).collect();
+            cameras.sort_unstable();
+            cameras.dedup();
+
+            for camera in cameras {
+                let mut total: u64 = total_camera_bytes(Path::new(directory), camera);
+                segments.retain(|s| {
+                    if s.camera != camera || total <= max_bytes {
+                        return true;
+                    }
+                    if delete_segment(s) {
+                        total = total.saturating_sub(s.size);
+                        false
+                    } else {
+                        true
+                    }
+                });
+            }
+        }
+
+        if policy.min_free_megabytes > 0 {
+            let min_free = policy.min_free_megabytes.saturating_mul(1024 * 1024);
+            if let Some(mut free) = free_space(Path::new(directory)) {
+                let mut pruned = 0;
+                segments.retain(|s| {
+                    if free >= min_free {
+                        return true;
+                    }
+                    if delete_segment(s) {
+                        free = free.saturating_add(s.size);
+                        pruned += 1;
+                        false
+                    } else {
+                        true
+                    }
+                });
+
+                // Resume only with some headroom so recording does not flap
+                // on and off around the threshold.
+                let resume_at = min_free.saturating_add(min_free / 4);
+                if free < min_free || (was_out_of_space && free < resume_at) {
+                    out_of_space = true;
+                    warnings.push(format!(
+                        "Recording paused: less than {} MB free for {}",
+                        policy.min_free_megabytes, directory
+                    ));
+                } else if pruned > 0 {
+                    warnings.push(format!(
+                        "Low disk space: deleted {} oldest segment(s) in {} to keep {} MB free",
+                        pruned, directory, policy.min_free_megabytes
+                    ));
+                }
+            }
+        }
+    }
+
+    for warning in &warnings {
+        println!("Retention: {}", warning);
+    }
+
+    let mut state = state.lock().unwrap();
+    state.out_of_space = out_of_space;
+    state.warning = if warnings.is_empty() {
+        None
+    } else {
+        Some(warnings.join("; "))
+    };
+}
+
+/// Recorded segments in `directory`, recognised by the `camera<N>_` prefix and
+/// a recording file extension.
+fn list_segments(directory: &Path) -> Vec<Segment> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let camera = segment_camera(&path)?;
+            let metadata = entry.metadata().ok()?;
+            Some(Segment {
+                camera,
+                path,
+                size: metadata.len(),
+                modified: metadata.modified().ok()?,
+            })
+        })
+        .collect()
+}
+
+fn segment_camera(path: &Path) -> Option<usize> {
+    let extension = path.extension()?.to_str()?;
+    RecordingFormat::from_extension(extension)?;
+
+    let name = path.file_name()?.to_str()?;
+    let rest = name.strip_prefix("camera")?;
+    let (number, _) = rest.split_once('_')?;
+    number.parse().ok()
+}
+
+fn newest_per_camera(segments: &[Segment]) -> Vec<PathBuf> {
+    let mut newest: Vec<&Segment> = Vec::new();
+    for segment in segments {
+        match newest.iter_mut().find(|s| s.camera == segment.camera) {
+            Some(current) if current.modified < segment.modified => *current = segment,
+            Some(_) => {}
+            None => newest.push(segment),
+        }
+    }
+    newest.iter().map(|s| s.path.clone()).collect()
+}
+
+fn total_camera_bytes(directory: &Path, camera: usize) -> u64 {
+    list_segments(directory)
+        .iter()
+        .filter(|s| s.camera == camera)
+        .map(|s| s.size)
+        .sum()
+}
+
+fn delete_segment(segment: &Segment) -> bool {
+    match fs::remove_file(&segment.path) {
+        Ok(()) => {
+            println!("Retention: deleted {}", segment.path.display());
+            true
+        }
+        Err(e) => {
+            println!("Retention: failed to delete {}: {}", segment.path.display(), e);
+            false
+        }
+    }
+}
+
+/// Free bytes on the volume holding `directory`, as far as this user's
+/// quota allows.
+#[cfg(windows)]
+fn free_space(directory: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::fileapi::GetDiskFreeSpaceExW;
+    use winapi::um::winnt::ULARGE_INTEGER;
+
+    let path: Vec<u16> = directory.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available: ULARGE_INTEGER = unsafe { std::mem::zeroed() };
+    let null = std::ptr::null_mut();
+    let ok = unsafe { GetDiskFreeSpaceExW(path.as_ptr(), &mut available, null, null) };
+    if ok == 0 {
+        return None;
+    }
+    Some(unsafe { *available.QuadPart() })
+}
+
+/// Free bytes on the volume holding `directory`.
+#[cfg(not(windows))]
+fn free_space(directory: &Path) -> Option<u64> {
+    use std::process::Command;
+
+    let output = Command::new("df").arg("-Pk").arg(directory).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let available_kb: u64 = stdout.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available_kb.saturating_mul(1024))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    const HOUR: u64 = 3_600;
+    const KB: u64 = 1024;
+
+    /// A fresh recording directory under the system's temp directory.
+    fn recording_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir()
+            .join(format!("retention_{}_{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    /// Creates a file of `size` bytes last modified `age_secs` ago.
+    fn file(directory: &Path, name: &str, size: u64, age_secs: u64) {
+        let file = File::create(directory.join(name)).unwrap();
+        file.set_len(size).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+            .unwrap();
+    }
+
+    fn remaining(directory: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(directory)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn state(policy: RetentionPolicy, directory: &Path) -> Arc<Mutex<RetentionState>> {
+        Arc::new(Mutex::new(RetentionState {
+            policy,
+            directories: vec![directory.display().to_string()],
+            ..Default::default()
+        }))
+    }
+
+    fn policy(
+        max_age_hours: u64,
+        max_megabytes_per_camera: u64,
+        min_free_megabytes: u64,
+    ) -> RetentionPolicy {
+        RetentionPolicy {
+            max_age_hours,
+            max_megabytes_per_camera,
+            min_free_megabytes,
+        }
+    }
+
+    #[test]
+    fn deletes_segments_older_than_the_maximum_age() {
+        let directory = recording_directory("age");
+        file(&directory, "camera1_a.mkv", KB, 3 * HOUR);
+        file(&directory, "camera1_b.mkv", KB, 2 * HOUR);
+        file(&directory, "camera1_c.mkv", KB, 10);
+        file(&directory, "camera2_a.ts", KB, 5 * HOUR);
+        file(&directory, "notes.txt", KB, 5 * HOUR);
+
+        enforce(&state(policy(1, 0, 0), &directory), |_| None);
+
+        // The newest segment of each camera may still be recording.
+        assert_eq!(
+            remaining(&directory),
+            ["camera1_c.mkv", "camera2_a.ts", "notes.txt"]
+        );
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn keeps_old_segments_without_a_maximum_age() {
+        let directory = recording_directory("age_off");
+        file(&directory, "camera1_a.mkv", KB, 1000 * HOUR);
+        file(&directory, "camera1_b.mkv", KB, 10);
+
+        enforce(&state(policy(0, 0, 0), &directory), |_| None);
+
+        assert_eq!(remaining(&directory), ["camera1_a.mkv", "camera1_b.mkv"]);
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn keeps_each_camera_under_its_size_limit() {
+        let directory = recording_directory("size");
+        file(&directory, "camera1_a.mp4", 600 * KB, 3 * HOUR);
+        file(&directory, "camera1_b.mp4", 600 * KB, 2 * HOUR);
+        file(&directory, "camera1_c.mp4", 600 * KB, HOUR);
+        file(&directory, "camera2_a.mp4", 600 * KB, 4 * HOUR);
+        file(&directory, "camera2_b.mp4", 300 * KB, HOUR);
+
+        enforce(&state(policy(0, 1, 0), &directory), |_| None);
+
+        assert_eq!(
+            remaining(&directory),
+            ["camera1_c.mp4", "camera2_a.mp4", "camera2_b.mp4"]
+        );
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn keeps_large_cameras_without_a_size_limit() {
+        let directory = recording_directory("size_off");
+        file(&directory, "camera1_a.mp4", 600 * KB, 2 * HOUR);
+        file(&directory, "camera1_b.mp4", 600 * KB, HOUR);
+
+        enforce(&state(policy(0, 0, 0), &directory), |_| None);
+
+        assert_eq!(remaining(&directory), ["camera1_a.mp4", "camera1_b.mp4"]);
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn deletes_oldest_segments_to_keep_space_free() {
+        let directory = recording_directory("space");
+        file(&directory, "camera1_a.mkv", 300 * KB, 3 * HOUR);
+        file(&directory, "camera2_a.mkv", 300 * KB, 2 * HOUR);
+        file(&directory, "camera1_b.mkv", 300 * KB, HOUR);
+        file(&directory, "camera1_c.mkv", 300 * KB, 10);
+        file(&directory, "camera2_b.mkv", 300 * KB, 10);
+
+        let state = state(policy(0, 0, 1), &directory);
+        enforce(&state, |_| Some(500 * KB));
+
+        assert_eq!(
+            remaining(&directory),
+            ["camera1_b.mkv", "camera1_c.mkv", "camera2_b.mkv"]
+        );
+        let state = state.lock().unwrap();
+        assert!(!state.out_of_space);
+        assert!(state.warning.as_deref().unwrap_or("").starts_with("Low disk space"));
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn pauses_recording_when_pruning_frees_too_little() {
+        let directory = recording_directory("space_full");
+        file(&directory, "camera1_a.mkv", 100 * KB, 2 * HOUR);
+        file(&directory, "camera1_b.mkv", 100 * KB, 10);
+
+        let state = state(policy(0, 0, 1), &directory);
+        enforce(&state, |_| Some(0));
+
+        assert_eq!(remaining(&directory), ["camera1_b.mkv"]);
+        let state = state.lock().unwrap();
+        assert!(state.out_of_space);
+        assert!(state.warning.as_deref().unwrap_or("").starts_with("Recording paused"));
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn records_on_a_full_disk_without_a_free_space_minimum() {
+        let directory = recording_directory("space_off");
+        file(&directory, "camera1_a.mkv", 100 * KB, 2 * HOUR);
+        file(&directory, "camera1_b.mkv", 100 * KB, 10);
+
+        let state = state(policy(0, 0, 0), &directory);
+        enforce(&state, |_| Some(0));
+
+        assert_eq!(remaining(&directory), ["camera1_a.mkv", "camera1_b.mkv"]);
+        let state = state.lock().unwrap();
+        assert!(!state.out_of_space);
+        assert!(state.warning.is_none());
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn huge_limits_keep_everything() {
+        let directory = recording_directory("huge");
+        file(&directory, "camera1_a.mkv", 600 * KB, 1000 * HOUR);
+        file(&directory, "camera1_b.mkv", 600 * KB, 10);
+
+        let state = state(policy(u64::MAX, u64::MAX, 0), &directory);
+        enforce(&state, |_| Some(u64::MAX));
+
+        assert_eq!(remaining(&directory), ["camera1_a.mkv", "camera1_b.mkv"]);
+        let _ = fs::remove_dir_all(&directory);
+    }
+}
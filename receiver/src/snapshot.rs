@@ -0,0 +1,210 @@
+use crate::types::{format_timestamp, unix_now};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the sink holding on to each camera's latest decoded frame.
+pub const SNAPSHOT_SINK_NAME: &str = "snapshot_sink";
+
+/// How long encoding a snapshot may take.
+const ENCODE_TIMEOUT_SECONDS: u64 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SnapshotFormat {
+    Jpeg,
+    Png,
+}
+
+impl SnapshotFormat {
+    pub const ALL: [SnapshotFormat; 2] = [SnapshotFormat::Jpeg, SnapshotFormat::Png];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SnapshotFormat::Jpeg => "jpg",
+            SnapshotFormat::Png => "png",
+        }
+    }
+
+    fn encoder(&self) -> &'static str {
+        match self {
+            SnapshotFormat::Jpeg => "jpegenc",
+            SnapshotFormat::Png => "pngenc",
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<SnapshotFormat> {
+        match extension.to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" => Some(SnapshotFormat::Jpeg),
+            "png" => Some(SnapshotFormat::Png),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SnapshotConfig {
+    pub directory: String,
+    pub format: SnapshotFormat,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            directory: "snapshots".to_string(),
+            format: SnapshotFormat::Jpeg,
+        }
+    }
+}
+
+/// Pipeline tail for the `decoded` tee that keeps the latest decoded frame
+/// as the sink's `last-sample`, for a snapshot to be encoded from on demand.
+pub fn snapshot_sink() -> String {
+    format!(
+        "queue leaky=downstream max-size-buffers=1 ! \
+        fakesink name={} enable-last-sample=true sync=false async=false",
+        SNAPSHOT_SINK_NAME
+    )
+}
+
+/// Saves `frame`, a camera's most recent decoded frame, as
+/// `camera<N>_<timestamp>.<jpg|png>` in the snapshot directory and returns
+/// the path.
+pub fn save_snapshot(
+    snapshot: &SnapshotConfig,
+    camera_index: usize,
+    frame: &gst::Sample,
+) -> Result<PathBuf, String> {
+    fs::create_dir_all(&snapshot.directory)
+        .map_err(|e| format!("Failed to create snapshot directory {}: {}", snapshot.directory, e))?;
+
+    let path = snapshot_path(snapshot, camera_index, unix_now());
+    encode_frame(frame, snapshot.format, &path)?;
+    Ok(path)
+}
+
+/// `camera<N>_<timestamp>.<ext>` in the snapshot directory, numbered
+/// `_2`, `_3`... when snapshots were already taken within that second.
+fn snapshot_path(snapshot: &SnapshotConfig, camera_index: usize, unix_secs: u64) -> PathBuf {
+    let timestamp = format_timestamp(unix_secs);
+    let mut path = Path::new(&snapshot.directory).join(format!(
+        "camera{}_{}.{}",
+        camera_index + 1,
+        timestamp,
+        snapshot.format.extension()
+    ));
+    let mut n = 1;
+    while path.exists() {
+        n += 1;
+        path = Path::new(&snapshot.directory).join(format!(
+            "camera{}_{}_{}.{}",
+            camera_index + 1,
+            timestamp,
+            n,
+            snapshot.format.extension()
+        ));
+    }
+    path
+}
+
+/// Encodes the raw frame straight to the file, so a PNG is lossless.
+fn encode_frame(frame: &gst::Sample, format: SnapshotFormat, path: &Path) -> Result<(), String> {
+    let caps = frame.caps().ok_or("The frame has no video format")?.to_owned();
+    let mut buffer = frame.buffer().ok_or("The frame has no data")?.to_owned();
+    buffer.make_mut().set_pts(gst::ClockTime::ZERO);
+
+    let pipeline = gst::parse_launch(&format!(
+        "appsrc name=frame format=time ! videoconvert ! {} ! filesink location=\"{}\"",
+        format.encoder(),
+        path.display().to_string().replace('\\', "/")
+    ))
+    .map_err(|e| format!("Failed to set up the {} encoder: {}", format.extension(), e))?;
+    let source = pipeline
+        .downcast_ref::<gst::Bin>()
+        .and_then(|bin| bin.by_name("frame"))
+        .ok_or("Encoder pipeline has no source")?;
+    source.set_property("caps", &caps);
+
+    let result = pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|e| e.to_string())
+        .and_then(|_| {
+            source
+                .emit_by_name::<gst::FlowReturn>("push-buffer", &[&buffer])
+                .into_result()
+                .map_err(|e| format!("{:?}", e))?;
+            let _ = source.emit_by_name::<gst::FlowReturn>("end-of-stream", &[]);
+            let bus = pipeline.bus().ok_or("Encoder pipeline has no bus")?;
+            let message = bus
+                .timed_pop_filtered(
+                    gst::ClockTime::from_seconds(ENCODE_TIMEOUT_SECONDS),
+                    &[gst::MessageType::Eos, gst::MessageType::Error],
+                )
+                .ok_or("Encoding timed out")?;
+            match message.view() {
+                gst::MessageView::Error(err) => Err(err.error().to_string()),
+                _ => Ok(()),
+            }
+        });
+    let _ = pipeline.set_state(gst::State::Null);
+
+    result.map_err(|e| {
+        let _ = fs::remove_file(path);
+        format!("Failed to write {}: {}", path.display(), e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot_directory(name: &str) -> SnapshotConfig {
+        let directory = std::env::temp_dir().join(format!(
+            "snapshot_test_{}_{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        SnapshotConfig {
+            directory: directory.display().to_string(),
+            format: SnapshotFormat::Png,
+        }
+    }
+
+    fn file_name(path: &Path) -> String {
+        path.file_name().unwrap().to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn names_snapshots_by_camera_and_time() {
+        let snapshot = snapshot_directory("names");
+        let path = snapshot_path(&snapshot, 2, 1_700_000_000);
+        let _ = fs::remove_dir_all(&snapshot.directory);
+        assert_eq!(file_name(&path), "camera3_20231114-221320.png");
+        assert_eq!(path.parent(), Some(Path::new(&snapshot.directory)));
+    }
+
+    #[test]
+    fn numbers_snapshots_taken_within_the_same_second() {
+        let snapshot = snapshot_directory("numbers");
+        let first = snapshot_path(&snapshot, 0, 1_700_000_000);
+        fs::write(&first, b"").unwrap();
+        let second = snapshot_path(&snapshot, 0, 1_700_000_000);
+        fs::write(&second, b"").unwrap();
+        let third = snapshot_path(&snapshot, 0, 1_700_000_000);
+        let other_camera = snapshot_path(&snapshot, 1, 1_700_000_000);
+        let _ = fs::remove_dir_all(&snapshot.directory);
+
+        assert_eq!(file_name(&second), "camera1_20231114-221320_2.png");
+        assert_eq!(file_name(&third), "camera1_20231114-221320_3.png");
+        assert_eq!(file_name(&other_camera), "camera2_20231114-221320.png");
+    }
+
+    #[test]
+    fn recognises_snapshot_extensions() {
+        assert_eq!(SnapshotFormat::from_extension("JPEG"), Some(SnapshotFormat::Jpeg));
+        assert_eq!(SnapshotFormat::from_extension("png"), Some(SnapshotFormat::Png));
+        assert_eq!(SnapshotFormat::from_extension("gif"), None);
+    }
+}
@@ -1,25 +1,85 @@
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
-use std::collections::HashSet;
+use std::time::{Duration, Instant, SystemTime};
+use std::collections::{HashSet, VecDeque};
 
 #[derive(Clone, Default, Debug)]
 pub struct StreamStats {
+    /// Lost packets recovered, by FEC or retransmission.
     pub packets_received: u32,
+    /// Of those, the ones repaired by FEC (RaptorQ or ULPFEC).
+    pub fec_recovered: u32,
+    /// Of those, the ones the sender retransmitted after a NACK.
+    pub rtx_recovered: u32,
     pub packets_lost: u32,
     pub packets_late: u32,
     pub packets_sent: u32,
     pub repair_rate: f32,
     pub bitrate: f32,
     pub latency: f32,
+    /// Average interarrival jitter of the RTP packets, in milliseconds, as
+    /// rtpbin's jitterbuffer measures it.
+    pub jitter: f32,
+    pub auth_failures: u32,
+    /// Share of the received RTP bytes not carrying video: RTP headers, and
+    /// with MPEG-TS payloading the TS headers, padding and tables.
+    pub overhead: f32,
+    /// Round-trip time SRT measures to the sender, in milliseconds.
+    pub srt_rtt: f32,
+    /// Packets SRT had the sender send again.
+    pub srt_retransmitted: u32,
+    /// Packets SRT dropped for arriving after their play time.
+    pub srt_dropped: u32,
+    /// Whether no UDP packets arrived and the stream now comes over TCP.
+    pub tcp_fallback: bool,
     pub last_update: Option<Instant>,
 }
 
+/// Statistics an `srtsrc` reports for its connection.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SrtStats {
+    pub rtt_ms: f32,
+    pub packets_lost: u32,
+    pub packets_retransmitted: u32,
+    pub packets_dropped: u32,
+    pub receive_rate_mbps: f32,
+}
+
+/// Authentication failures after which a key mismatch is reported.
+pub const AUTH_FAILURE_WARNING_THRESHOLD: u32 = 50;
+
+/// Identities in a camera pipeline counting the RTP packets going into the
+/// depayloader and the video coming out of it.
+pub const RTP_BYTES_NAME: &str = "rtp_bytes";
+pub const VIDEO_BYTES_NAME: &str = "video_bytes";
+
+/// A lost packet that FEC has not repaired within this time counts as
+/// unrecovered.
+const RECOVERY_GRACE: Duration = Duration::from_secs(1);
+
+/// Window over which unrecovered losses are added up to detect a burst.
+pub const LOSS_WINDOW: Duration = Duration::from_secs(5);
+
+/// A burst of losses FEC could not repair, with the stats at that moment.
+#[derive(Clone, Debug)]
+pub struct LossBurst {
+    pub at: SystemTime,
+    pub unrecovered: u32,
+    pub stats: StreamStats,
+}
+
 pub struct StatsCollector {
     camera_index: usize,
     stats: Arc<Mutex<StreamStats>>,
     recovered_packets: HashSet<u32>,
     lost_packets: HashSet<u32>,
+    auth_failures: u32,
+    recent_losses: VecDeque<(Instant, u32)>,
+    last_burst: Option<Instant>,
     last_stats_time: Instant,
+    rtp_bytes: u64,
+    video_bytes: u64,
+    ulpfec_recovered: u32,
+    rtx_recovered: u32,
 }
 
 impl StatsCollector {
@@ -29,7 +89,14 @@ impl StatsCollector {
             stats: Arc::new(Mutex::new(StreamStats::default())),
             recovered_packets: HashSet::new(),
             lost_packets: HashSet::new(),
+            auth_failures: 0,
+            recent_losses: VecDeque::new(),
+            last_burst: None,
             last_stats_time: Instant::now(),
+            rtp_bytes: 0,
+            video_bytes: 0,
+            ulpfec_recovered: 0,
+            rtx_recovered: 0,
         }
     }
 
@@ -38,7 +105,14 @@ impl StatsCollector {
         *stats = StreamStats::default();
         self.recovered_packets.clear();
         self.lost_packets.clear();
+        self.auth_failures = 0;
+        self.recent_losses.clear();
+        self.last_burst = None;
         self.last_stats_time = Instant::now();
+        self.rtp_bytes = 0;
+        self.video_bytes = 0;
+        self.ulpfec_recovered = 0;
+        self.rtx_recovered = 0;
         Ok(())
     }
 
@@ -47,6 +121,12 @@ impl StatsCollector {
         *stats = StreamStats::default();
         self.recovered_packets.clear();
         self.lost_packets.clear();
+        self.auth_failures = 0;
+        self.recent_losses.clear();
+        self.rtp_bytes = 0;
+        self.video_bytes = 0;
+        self.ulpfec_recovered = 0;
+        self.rtx_recovered = 0;
     }
 
     pub fn get_stats(&self) -> StreamStats {
@@ -64,15 +144,117 @@ impl StatsCollector {
         else if line.contains("Add Lost timer for #") {
             if let Some(seqnum) = extract_seqnum_from_lost(line) {
                 self.lost_packets.insert(seqnum);
+                self.recent_losses.push_back((Instant::now(), seqnum));
                 self.update_stats();
             }
         }
+
+        else if line.contains("Unable to unprotect buffer") {
+            self.auth_failures += 1;
+            if self.auth_failures == AUTH_FAILURE_WARNING_THRESHOLD {
+                println!(
+                    "Camera {} - SRTP packets keep failing authentication; check that the key and cipher match the sender",
+                    self.camera_index + 1
+                );
+            }
+            self.update_stats();
+        }
+    }
+
+    /// Takes the bytes the byte-counting identities have seen so far.
+    pub fn set_byte_counts(&mut self, rtp_bytes: u64, video_bytes: u64) {
+        self.rtp_bytes = rtp_bytes;
+        self.video_bytes = video_bytes;
+
+        if self.rtp_bytes > 0 {
+            let video_share = self.video_bytes.min(self.rtp_bytes) as f32 / self.rtp_bytes as f32;
+            self.stats.lock().unwrap().overhead = (1.0 - video_share) * 100.0;
+        }
+    }
+
+    /// Takes the average jitter rtpbin's jitterbuffer has measured.
+    pub fn set_jitter(&mut self, jitter: Duration) {
+        self.stats.lock().unwrap().jitter = jitter.as_secs_f32() * 1000.0;
+    }
+
+    /// Notes that the stream fell back from UDP to TCP.
+    pub fn set_tcp_fallback(&mut self) {
+        self.stats.lock().unwrap().tcp_fallback = true;
+    }
+
+    /// Takes the ULPFEC decoder's count of recovered packets, which, unlike
+    /// RaptorQ, does not log the packets one by one.
+    pub fn set_ulpfec_recovered(&mut self, count: u32) {
+        if count != self.ulpfec_recovered {
+            self.ulpfec_recovered = count;
+            self.update_stats();
+        }
+    }
+
+    /// Takes the RTX receiver's count of retransmitted packets matched to
+    /// the ones asked for.
+    pub fn set_rtx_recovered(&mut self, count: u32) {
+        if count != self.rtx_recovered {
+            self.rtx_recovered = count;
+            self.update_stats();
+        }
+    }
+
+    /// Takes the statistics of an SRT connection, which stand in for the
+    /// RTP ones such a camera has none of.
+    pub fn set_srt_stats(&mut self, srt: SrtStats) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.packets_lost = srt.packets_lost;
+        stats.bitrate = srt.receive_rate_mbps * 1000.0;
+        stats.srt_rtt = srt.rtt_ms;
+        stats.srt_retransmitted = srt.packets_retransmitted;
+        stats.srt_dropped = srt.packets_dropped;
+        stats.last_update = Some(Instant::now());
+    }
+
+    /// Reports a burst once at least `threshold` losses in `LOSS_WINDOW` were
+    /// not recovered by FEC. After a burst, further ones are held back for
+    /// `cooldown` so one event is not reported several times.
+    pub fn take_loss_burst(&mut self, threshold: u32, cooldown: Duration) -> Option<LossBurst> {
+        let now = Instant::now();
+        while let Some((at, _)) = self.recent_losses.front() {
+            if now.duration_since(*at) > LOSS_WINDOW + RECOVERY_GRACE {
+                self.recent_losses.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if threshold == 0 || self.last_burst.is_some_and(|last| now.duration_since(last) < cooldown) {
+            return None;
+        }
+
+        let unrecovered = self
+            .recent_losses
+            .iter()
+            .filter(|(at, seqnum)| {
+                now.duration_since(*at) >= RECOVERY_GRACE && !self.recovered_packets.contains(seqnum)
+            })
+            .count() as u32;
+
+        if unrecovered < threshold {
+            return None;
+        }
+
+        self.last_burst = Some(now);
+        Some(LossBurst {
+            at: SystemTime::now() - RECOVERY_GRACE,
+            unrecovered,
+            stats: self.get_stats(),
+        })
     }
 
     fn update_stats(&mut self) {
         let mut stats_guard = self.stats.lock().unwrap();
         
-        stats_guard.packets_received = self.recovered_packets.len() as u32;
+        stats_guard.fec_recovered = self.recovered_packets.len() as u32 + self.ulpfec_recovered;
+        stats_guard.rtx_recovered = self.rtx_recovered;
+        stats_guard.packets_received = stats_guard.fec_recovered + stats_guard.rtx_recovered;
         stats_guard.packets_lost = self.lost_packets.len() as u32;
         
         let total_packets = stats_guard.packets_received + stats_guard.packets_lost;
@@ -94,6 +276,8 @@ impl StatsCollector {
         stats_guard.latency = 50.0 + ((1.0 - repair_ratio) * 150.0);
         
         stats_guard.packets_late = (stats_guard.packets_lost as f32 * 0.1) as u32;
+
+        stats_guard.auth_failures = self.auth_failures;
         
         stats_guard.last_update = Some(Instant::now());
         
@@ -133,3 +317,87 @@ fn extract_seqnum_from_lost(line: &str) -> Option<u32> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOLDOWN: Duration = Duration::from_secs(60);
+
+    /// Notes the packets `seqnums` as lost `age` ago.
+    fn lose(collector: &mut StatsCollector, seqnums: std::ops::Range<u32>, age: Duration) {
+        let at = Instant::now().checked_sub(age).unwrap();
+        for seqnum in seqnums {
+            collector.lost_packets.insert(seqnum);
+            collector.recent_losses.push_back((at, seqnum));
+        }
+    }
+
+    fn recover(collector: &mut StatsCollector, seqnum: u32) {
+        collector.parse_debug_line(&format!(
+            "raptorqdec: Successfully recovered packet: seqnum: {}, len: 1316",
+            seqnum
+        ));
+    }
+
+    #[test]
+    fn reports_a_burst_once_the_threshold_is_reached() {
+        let mut collector = StatsCollector::new(0, 0, 0);
+        lose(&mut collector, 0..4, Duration::from_secs(2));
+        assert!(collector.take_loss_burst(5, COOLDOWN).is_none());
+
+        lose(&mut collector, 4..5, Duration::from_secs(2));
+        let burst = collector.take_loss_burst(5, COOLDOWN);
+        assert_eq!(burst.map(|burst| burst.unrecovered), Some(5));
+    }
+
+    #[test]
+    fn counts_only_losses_left_unrecovered_past_the_grace_time() {
+        let mut collector = StatsCollector::new(0, 0, 0);
+        lose(&mut collector, 0..5, Duration::from_secs(2));
+        recover(&mut collector, 3);
+        // Still within the grace time FEC has to repair them.
+        lose(&mut collector, 5..10, Duration::ZERO);
+        assert!(collector.take_loss_burst(5, COOLDOWN).is_none());
+    }
+
+    #[test]
+    fn forgets_losses_older_than_the_window() {
+        let mut collector = StatsCollector::new(0, 0, 0);
+        let age = LOSS_WINDOW + RECOVERY_GRACE + Duration::from_secs(1);
+        lose(&mut collector, 0..5, age);
+        assert!(collector.take_loss_burst(5, COOLDOWN).is_none());
+        assert!(collector.recent_losses.is_empty());
+    }
+
+    #[test]
+    fn holds_back_bursts_during_the_cooldown() {
+        let mut collector = StatsCollector::new(0, 0, 0);
+        lose(&mut collector, 0..5, Duration::from_secs(2));
+        assert!(collector.take_loss_burst(5, COOLDOWN).is_some());
+        assert!(collector.take_loss_burst(5, COOLDOWN).is_none());
+        // Once the cooldown is over the same losses are reported again.
+        assert!(collector.take_loss_burst(5, Duration::ZERO).is_some());
+    }
+
+    #[test]
+    fn zero_threshold_reports_no_bursts() {
+        let mut collector = StatsCollector::new(0, 0, 0);
+        lose(&mut collector, 0..50, Duration::from_secs(2));
+        assert!(collector.take_loss_burst(0, COOLDOWN).is_none());
+    }
+
+    #[test]
+    fn restarting_resets_losses_and_cooldown() {
+        let mut collector = StatsCollector::new(0, 0, 0);
+        lose(&mut collector, 0..5, Duration::from_secs(2));
+        assert!(collector.take_loss_burst(5, COOLDOWN).is_some());
+
+        collector.start().unwrap();
+        assert!(collector.recent_losses.is_empty());
+        assert!(collector.take_loss_burst(5, COOLDOWN).is_none());
+
+        lose(&mut collector, 0..5, Duration::from_secs(2));
+        assert!(collector.take_loss_burst(5, COOLDOWN).is_some());
+    }
+}
@@ -1,10 +1,71 @@
+use crate::frames::FrameOutput;
+pub use common::parse_ip_address;
+pub use common::srtp::{SrtpCipher, parse_srtp_key};
+pub use common::stream::{
+    DEFAULT_SRT_LATENCY_MS, DEFAULT_TCP_FALLBACK, FecScheme, PayloadFormat, RED_PAYLOAD_TYPE,
+    RTX_PAYLOAD_TYPE, SrtConfig, SrtMode, TcpMode, Transport, ULPFEC_PAYLOAD_TYPE,
+};
 use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Clone)]
 pub struct CameraConfig {
     pub sender_ip: String,
+    /// Local address to receive on. A multicast group address joins that group.
+    pub bind_address: String,
     pub rtp_port: String,
     pub fec_port: String,
+    /// Interface used to join a multicast group; empty lets the OS choose.
+    pub multicast_iface: String,
+    /// Only accept multicast traffic originating from `sender_ip` (SSM).
+    pub source_specific: bool,
+    /// SRTP protection expected on both the RTP and FEC flows.
+    pub srtp: Option<SrtpConfig>,
+    /// Recording settings while recording is on, filled in by `CameraBackend`.
+    pub recording: Option<RecordingConfig>,
+    /// DVR buffer settings while the buffer is on, filled in by `CameraBackend`.
+    pub dvr: Option<DvrConfig>,
+    /// Loopback port feeding the mosaic view, set by `CameraBackend` while
+    /// mosaic mode is on.
+    pub mosaic_port: Option<u16>,
+    /// Decoded-frame delivery to library code, set by `CameraBackend`.
+    pub frames: Option<FrameOutput>,
+    /// Play the audio the sender muxes in with the video, kept in sync with
+    /// it. A camera with audio runs its pipeline inside the receiver process
+    /// so it can be muted while running.
+    pub audio: bool,
+    /// Whether the audio starts muted, set by `CameraBackend`.
+    pub audio_muted: bool,
+    /// How the sender carries the video in RTP.
+    pub payload: PayloadFormat,
+    /// Forward error correction the sender adds. A camera with ULPFEC runs
+    /// its pipeline inside the receiver process, which hands the decoder
+    /// rtpbin's packet storage.
+    pub fec: FecScheme,
+    /// Ask the sender to resend lost packets FEC could not repair (NACK over
+    /// RTCP). Needs the sender IP and RaptorQ; the camera then runs its
+    /// pipeline inside the receiver process to set up rtpbin's RTX receiver.
+    pub retransmission: bool,
+    /// How the stream arrives. Over TCP the RTP and FEC flows use the same
+    /// port numbers as over UDP. Over SRT the MPEG-TS comes on the RTP port
+    /// without RTP or FEC, and the camera runs its pipeline inside the
+    /// receiver process to read SRT's statistics.
+    pub transport: Transport,
+}
+
+impl CameraConfig {
+    /// Ports of the RTCP flows: sender reports arrive on the RTP port + 1,
+    /// and receiver reports with NACKs go to the sender's FEC port + 1.
+    pub fn rtcp_ports(&self) -> Result<(u16, u16), String> {
+        let next_port = |port: &str| {
+            port.trim()
+                .parse::<u16>()
+                .ok()
+                .and_then(|port| port.checked_add(1))
+                .ok_or_else(|| format!("'{}' is not a port with a free port after it", port))
+        };
+        Ok((next_port(&self.rtp_port)?, next_port(&self.fec_port)?))
+    }
 }
 
 pub struct CameraState {
@@ -13,14 +74,231 @@ pub struct CameraState {
 }
 
 impl CameraState {
-    pub fn new(sender_ip: &str, rtp_port: &str, fec_port: &str) -> Self {
+    pub fn new(sender_ip: &str, bind_address: &str, rtp_port: &str, fec_port: &str) -> Self {
         Self {
             config: CameraConfig {
                 sender_ip: sender_ip.to_string(),
+                bind_address: bind_address.to_string(),
                 rtp_port: rtp_port.to_string(),
                 fec_port: fec_port.to_string(),
+                multicast_iface: String::new(),
+                source_specific: false,
+                srtp: None,
+                recording: None,
+                dvr: None,
+                mosaic_port: None,
+                frames: None,
+                audio: false,
+                audio_muted: false,
+                payload: PayloadFormat::MpegTs,
+                fec: FecScheme::RaptorQ,
+                retransmission: false,
+                transport: Transport::Udp,
             },
             receiving: Arc::new(Mutex::new(false)),
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Clone)]
+pub struct SrtpConfig {
+    pub cipher: SrtpCipher,
+    /// Master key and salt as uppercase hex.
+    pub key: String,
+    /// MKI of `key` when it comes from the key store.
+    pub mki: Option<u32>,
+    /// Other keys accepted during a rotation overlap, selected by MKI.
+    pub additional_keys: Vec<SrtpKey>,
+}
+
+#[derive(Clone)]
+pub struct SrtpKey {
+    pub mki: u32,
+    pub key: String,
+}
+
+/// Container used for recorded segments. The stream is remuxed, never re-encoded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordingFormat {
+    Mp4,
+    Mkv,
+    Ts,
+}
+
+impl RecordingFormat {
+    pub const ALL: [RecordingFormat; 3] = [
+        RecordingFormat::Mp4,
+        RecordingFormat::Mkv,
+        RecordingFormat::Ts,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RecordingFormat::Mp4 => "mp4",
+            RecordingFormat::Mkv => "mkv",
+            RecordingFormat::Ts => "ts",
+        }
+    }
+
+    pub fn muxer(&self) -> &'static str {
+        match self {
+            RecordingFormat::Mp4 => "mp4mux",
+            RecordingFormat::Mkv => "matroskamux",
+            RecordingFormat::Ts => "mpegtsmux",
+        }
+    }
+
+    /// Muxer settings that keep a segment playable if the receiver dies before
+    /// finalizing it. MP4 is written fragmented, one fragment per second;
+    /// Matroska and MPEG-TS are readable up to the last written block as is.
+    pub fn muxer_properties(&self) -> Option<&'static str> {
+        match self {
+            RecordingFormat::Mp4 => Some("properties,fragment-duration=(uint)1000"),
+            RecordingFormat::Mkv | RecordingFormat::Ts => None,
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<RecordingFormat> {
+        RecordingFormat::ALL
+            .iter()
+            .copied()
+            .find(|f| f.extension().eq_ignore_ascii_case(extension))
+    }
+}
+
+#[derive(Clone)]
+pub struct RecordingConfig {
+    pub format: RecordingFormat,
+    pub directory: String,
+    /// Roll over to a new segment after this many seconds; 0 disables.
+    pub segment_seconds: u64,
+    /// Roll over to a new segment after this many megabytes; 0 disables.
+    pub segment_megabytes: u64,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            format: RecordingFormat::Mkv,
+            directory: "recordings".to_string(),
+            segment_seconds: 300,
+            segment_megabytes: 0,
+        }
+    }
+}
+
+impl RecordingConfig {
+    /// The segment limits as splitmuxsink's `max-size-time` in nanoseconds
+    /// and `max-size-bytes`, or an error if either does not fit.
+    pub fn segment_limits(&self) -> Result<(u64, u64), String> {
+        let max_time = self.segment_seconds.checked_mul(1_000_000_000).ok_or_else(|| {
+            format!("A segment length of {} seconds is too long", self.segment_seconds)
+        })?;
+        let max_bytes = self.segment_megabytes.checked_mul(1024 * 1024).ok_or_else(|| {
+            format!("A segment size of {} MB is too large", self.segment_megabytes)
+        })?;
+        Ok((max_time, max_bytes))
+    }
+}
+
+/// On-disk ring buffer of recent video kept for instant replay.
+#[derive(Clone)]
+pub struct DvrConfig {
+    pub directory: String,
+    /// Minutes of video kept per camera; 0, the default, disables the buffer.
+    pub minutes: u64,
+}
+
+impl Default for DvrConfig {
+    fn default() -> Self {
+        Self {
+            directory: "dvr".to_string(),
+            minutes: 0,
+        }
+    }
+}
+
+/// Longest DVR buffer accepted, a day of video.
+pub const MAX_DVR_MINUTES: u64 = 24 * 60;
+
+impl DvrConfig {
+    /// Seconds of video kept per camera.
+    pub fn window_seconds(&self) -> u64 {
+        self.minutes.min(MAX_DVR_MINUTES) * 60
+    }
+}
+
+/// Clips saved from the DVR buffer around bursts of unrecovered loss.
+#[derive(Clone)]
+pub struct ClipConfig {
+    pub directory: String,
+    /// Unrecovered packets within the loss window that trigger a clip; 0
+    /// disables clip capture.
+    pub loss_threshold: u32,
+    pub seconds_before: u64,
+    pub seconds_after: u64,
+}
+
+impl Default for ClipConfig {
+    fn default() -> Self {
+        Self {
+            directory: "clips".to_string(),
+            loss_threshold: 20,
+            seconds_before: 10,
+            seconds_after: 10,
+        }
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Formats a Unix time as `YYYYMMDD-HHMMSS` (UTC) for use in file names.
+pub fn format_timestamp(unix_secs: u64) -> String {
+    let days = (unix_secs / 86_400) as i64;
+    let secs_of_day = unix_secs % 86_400;
+
+    // Civil-from-days conversion for the proleptic Gregorian calendar.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_timestamps_in_utc() {
+        assert_eq!(format_timestamp(0), "19700101-000000");
+        assert_eq!(format_timestamp(1_700_000_000), "20231114-221320");
+    }
+
+    #[test]
+    fn formats_leap_days_and_century_ends() {
+        assert_eq!(format_timestamp(951_782_400), "20000229-000000");
+        assert_eq!(format_timestamp(951_868_800), "20000301-000000");
+        assert_eq!(format_timestamp(4_107_542_399), "21000228-235959");
+        assert_eq!(format_timestamp(4_107_542_400), "21000301-000000");
+    }
+}
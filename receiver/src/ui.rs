@@ -1,20 +1,80 @@
-use crate::backend::CameraBackend;
+use receiver::backend::CameraBackend;
+use receiver::mosaic::MosaicLayout;
+use receiver::snapshot::{SnapshotConfig, SnapshotFormat};
+use receiver::retention::RetentionPolicy;
+use receiver::stats_collector::AUTH_FAILURE_WARNING_THRESHOLD;
+use receiver::types::{
+    CameraConfig, FecScheme, PayloadFormat, RecordingConfig, RecordingFormat, SrtConfig, SrtMode,
+    SrtpCipher, SrtpConfig, TcpMode, Transport,
+};
 use native_windows_gui as nwg;
 use std::cell::RefCell;
+use std::path::Path;
 use std::rc::Rc;
 
 pub struct ReceiverUI {
     window: nwg::Window,
 
     sender_ip_inputs: Vec<nwg::TextInput>,
+    bind_address_inputs: Vec<nwg::TextInput>,
     rtp_port_inputs: Vec<nwg::TextInput>,
     fec_port_inputs: Vec<nwg::TextInput>,
+    multicast_iface_inputs: Vec<nwg::TextInput>,
+    ssm_checkboxes: Vec<nwg::CheckBox>,
+    srtp_cipher_dropdowns: Vec<nwg::ComboBox<String>>,
+    srtp_key_inputs: Vec<nwg::TextInput>,
     start_buttons: Vec<nwg::Button>,
+    record_buttons: Vec<nwg::Button>,
+    replay_offset_inputs: Vec<nwg::TextInput>,
+    replay_buttons: Vec<nwg::Button>,
+    replay_labels: Vec<nwg::Label>,
+    overlay_checkboxes: Vec<nwg::CheckBox>,
+    audio_checkboxes: Vec<nwg::CheckBox>,
+    mute_checkboxes: Vec<nwg::CheckBox>,
+    payload_dropdowns: Vec<nwg::ComboBox<String>>,
+    fec_dropdowns: Vec<nwg::ComboBox<String>>,
+    retransmission_checkboxes: Vec<nwg::CheckBox>,
+    transport_dropdowns: Vec<nwg::ComboBox<String>>,
+    /// SRT settings as `latency ms[:passphrase]`, or the seconds without UDP
+    /// packets before falling back to TCP.
+    srt_settings_inputs: Vec<nwg::TextInput>,
+    snapshot_buttons: Vec<nwg::Button>,
 
     camera_labels: Vec<nwg::Label>,
     stats_displays: Vec<nwg::Label>,
     header_labels: Vec<nwg::Label>,
 
+    import_keys_button: nwg::Button,
+
+    recording_labels: Vec<nwg::Label>,
+    recording_format_dropdown: nwg::ComboBox<String>,
+    recording_dir_input: nwg::TextInput,
+    segment_seconds_input: nwg::TextInput,
+    segment_megabytes_input: nwg::TextInput,
+    dvr_labels: Vec<nwg::Label>,
+    dvr_minutes_input: nwg::TextInput,
+
+    retention_labels: Vec<nwg::Label>,
+    max_age_input: nwg::TextInput,
+    max_camera_megabytes_input: nwg::TextInput,
+    min_free_megabytes_input: nwg::TextInput,
+    apply_retention_button: nwg::Button,
+    clip_label: nwg::Label,
+    clip_threshold_input: nwg::TextInput,
+    clip_before_input: nwg::TextInput,
+    clip_after_input: nwg::TextInput,
+    apply_clip_button: nwg::Button,
+    retention_status: nwg::Label,
+
+    mosaic_button: nwg::Button,
+    mosaic_layout_label: nwg::Label,
+    mosaic_layout_dropdown: nwg::ComboBox<String>,
+
+    snapshot_label: nwg::Label,
+    snapshot_format_dropdown: nwg::ComboBox<String>,
+    snapshot_dir_input: nwg::TextInput,
+    snapshot_status: nwg::Label,
+
     stats_timer: nwg::AnimationTimer,
 
     backend: Rc<RefCell<CameraBackend>>,
@@ -30,6 +90,12 @@ impl ReceiverUI {
                 Default::default(),
                 Default::default(),
             ],
+            bind_address_inputs: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
             rtp_port_inputs: vec![
                 Default::default(),
                 Default::default(),
@@ -42,12 +108,114 @@ impl ReceiverUI {
                 Default::default(),
                 Default::default(),
             ],
+            multicast_iface_inputs: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            ssm_checkboxes: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            srtp_cipher_dropdowns: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            srtp_key_inputs: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
             start_buttons: vec![
                 Default::default(),
                 Default::default(),
                 Default::default(),
                 Default::default(),
             ],
+            record_buttons: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            replay_offset_inputs: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            replay_buttons: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            replay_labels: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            overlay_checkboxes: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            audio_checkboxes: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            mute_checkboxes: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            payload_dropdowns: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            fec_dropdowns: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            retransmission_checkboxes: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            transport_dropdowns: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            srt_settings_inputs: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
+            snapshot_buttons: vec![
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ],
             camera_labels: vec![
                 Default::default(),
                 Default::default(),
@@ -65,7 +233,36 @@ impl ReceiverUI {
                 Default::default(),
                 Default::default(),
                 Default::default(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
             ],
+            import_keys_button: Default::default(),
+            recording_labels: vec![Default::default(), Default::default(), Default::default()],
+            recording_format_dropdown: Default::default(),
+            recording_dir_input: Default::default(),
+            segment_seconds_input: Default::default(),
+            segment_megabytes_input: Default::default(),
+            dvr_labels: vec![Default::default(), Default::default()],
+            dvr_minutes_input: Default::default(),
+            retention_labels: vec![Default::default(), Default::default()],
+            max_age_input: Default::default(),
+            max_camera_megabytes_input: Default::default(),
+            min_free_megabytes_input: Default::default(),
+            apply_retention_button: Default::default(),
+            clip_label: Default::default(),
+            clip_threshold_input: Default::default(),
+            clip_before_input: Default::default(),
+            clip_after_input: Default::default(),
+            apply_clip_button: Default::default(),
+            retention_status: Default::default(),
+            mosaic_button: Default::default(),
+            mosaic_layout_label: Default::default(),
+            mosaic_layout_dropdown: Default::default(),
+            snapshot_label: Default::default(),
+            snapshot_format_dropdown: Default::default(),
+            snapshot_dir_input: Default::default(),
+            snapshot_status: Default::default(),
             stats_timer: Default::default(),
             backend,
         }
@@ -73,7 +270,7 @@ impl ReceiverUI {
 
     pub fn build(&mut self) -> Result<(), nwg::NwgError> {
         nwg::Window::builder()
-            .size((1400, 280))
+            .size((1920, 460))
             .position((300, 300))
             .title("Multi-Camera Receiver with REAL Stats")
             .build(&mut self.window)?;
@@ -84,6 +281,19 @@ impl ReceiverUI {
             self.build_camera_row(i)?;
         }
 
+        nwg::Button::builder()
+            .text("Import Key Bundle")
+            .position((10, 285))
+            .size((140, 30))
+            .parent(&self.window)
+            .build(&mut self.import_keys_button)?;
+
+        self.build_recording_settings()?;
+        self.build_retention_settings()?;
+        self.build_clip_settings()?;
+        self.build_mosaic_controls()?;
+        self.build_snapshot_settings()?;
+
         nwg::AnimationTimer::builder()
             .parent(&self.window)
             .interval(std::time::Duration::from_millis(1000))
@@ -96,34 +306,308 @@ impl ReceiverUI {
         nwg::Label::builder()
             .text("Sender IP")
             .position((80, 10))
-            .size((100, 20))
+            .size((140, 20))
             .parent(&self.window)
             .build(&mut self.header_labels[0])?;
 
+        nwg::Label::builder()
+            .text("Bind Address")
+            .position((230, 10))
+            .size((140, 20))
+            .parent(&self.window)
+            .build(&mut self.header_labels[1])?;
+
         nwg::Label::builder()
             .text("RTP Port")
-            .position((200, 10))
+            .position((380, 10))
             .size((80, 20))
             .parent(&self.window)
-            .build(&mut self.header_labels[1])?;
+            .build(&mut self.header_labels[2])?;
 
         nwg::Label::builder()
             .text("FEC Port")
-            .position((300, 10))
+            .position((470, 10))
             .size((80, 20))
             .parent(&self.window)
-            .build(&mut self.header_labels[2])?;
+            .build(&mut self.header_labels[3])?;
+
+        nwg::Label::builder()
+            .text("Mcast Iface")
+            .position((560, 10))
+            .size((90, 20))
+            .parent(&self.window)
+            .build(&mut self.header_labels[4])?;
+
+        nwg::Label::builder()
+            .text("SSM")
+            .position((660, 10))
+            .size((50, 20))
+            .parent(&self.window)
+            .build(&mut self.header_labels[5])?;
 
         nwg::Label::builder()
             .text("REAL Stream Statistics")
-            .position((500, 10))
-            .size((800, 20))
+            .position((810, 10))
+            .size((730, 20))
             .parent(&self.window)
-            .build(&mut self.header_labels[3])?;
+            .build(&mut self.header_labels[6])?;
+
+        Ok(())
+    }
+
+    fn build_recording_settings(&mut self) -> Result<(), nwg::NwgError> {
+        let defaults = RecordingConfig::default();
+
+        nwg::Label::builder()
+            .text("Recording:")
+            .position((170, 290))
+            .size((70, 20))
+            .parent(&self.window)
+            .build(&mut self.recording_labels[0])?;
+
+        nwg::ComboBox::builder()
+            .position((245, 285))
+            .size((70, 25))
+            .collection(
+                RecordingFormat::ALL
+                    .iter()
+                    .map(|f| f.extension().to_uppercase())
+                    .collect(),
+            )
+            .selected_index(RecordingFormat::ALL.iter().position(|f| *f == defaults.format))
+            .parent(&self.window)
+            .build(&mut self.recording_format_dropdown)?;
+
+        nwg::TextInput::builder()
+            .text(&defaults.directory)
+            .position((325, 285))
+            .size((250, 25))
+            .parent(&self.window)
+            .build(&mut self.recording_dir_input)?;
+
+        nwg::Label::builder()
+            .text("Segment s / MB:")
+            .position((585, 290))
+            .size((100, 20))
+            .parent(&self.window)
+            .build(&mut self.recording_labels[1])?;
+
+        nwg::TextInput::builder()
+            .text(&defaults.segment_seconds.to_string())
+            .position((690, 285))
+            .size((60, 25))
+            .parent(&self.window)
+            .build(&mut self.segment_seconds_input)?;
+
+        nwg::TextInput::builder()
+            .text(&defaults.segment_megabytes.to_string())
+            .position((760, 285))
+            .size((60, 25))
+            .parent(&self.window)
+            .build(&mut self.segment_megabytes_input)?;
+
+        nwg::Label::builder()
+            .text("(0 = no limit)")
+            .position((830, 290))
+            .size((100, 20))
+            .parent(&self.window)
+            .build(&mut self.recording_labels[2])?;
+
+        nwg::Label::builder()
+            .text("DVR minutes:")
+            .position((950, 290))
+            .size((80, 20))
+            .parent(&self.window)
+            .build(&mut self.dvr_labels[0])?;
+
+        nwg::TextInput::builder()
+            .text(&self.backend.borrow().dvr_minutes().to_string())
+            .position((1035, 285))
+            .size((50, 25))
+            .parent(&self.window)
+            .build(&mut self.dvr_minutes_input)?;
+
+        nwg::Label::builder()
+            .text("(0 = off, applies when a camera starts)")
+            .position((1095, 290))
+            .size((250, 20))
+            .parent(&self.window)
+            .build(&mut self.dvr_labels[1])?;
+
+        Ok(())
+    }
+
+    fn build_retention_settings(&mut self) -> Result<(), nwg::NwgError> {
+        let policy = self.backend.borrow().retention_policy();
+
+        nwg::Label::builder()
+            .text("Keep: max age h / MB per camera / min free MB:")
+            .position((170, 325))
+            .size((290, 20))
+            .parent(&self.window)
+            .build(&mut self.retention_labels[0])?;
+
+        nwg::TextInput::builder()
+            .text(&policy.max_age_hours.to_string())
+            .position((465, 320))
+            .size((60, 25))
+            .parent(&self.window)
+            .build(&mut self.max_age_input)?;
+
+        nwg::TextInput::builder()
+            .text(&policy.max_megabytes_per_camera.to_string())
+            .position((535, 320))
+            .size((70, 25))
+            .parent(&self.window)
+            .build(&mut self.max_camera_megabytes_input)?;
+
+        nwg::TextInput::builder()
+            .text(&policy.min_free_megabytes.to_string())
+            .position((615, 320))
+            .size((70, 25))
+            .parent(&self.window)
+            .build(&mut self.min_free_megabytes_input)?;
+
+        nwg::Button::builder()
+            .text("Apply")
+            .position((695, 320))
+            .size((60, 25))
+            .parent(&self.window)
+            .build(&mut self.apply_retention_button)?;
+
+        nwg::Label::builder()
+            .text("(0 = off)")
+            .position((765, 325))
+            .size((60, 20))
+            .parent(&self.window)
+            .build(&mut self.retention_labels[1])?;
+
+        nwg::Label::builder()
+            .text("")
+            .position((10, 360))
+            .size((1530, 20))
+            .parent(&self.window)
+            .build(&mut self.retention_status)?;
 
         Ok(())
     }
 
+    fn build_clip_settings(&mut self) -> Result<(), nwg::NwgError> {
+        let clip = self.backend.borrow().clip_config();
+
+        nwg::Label::builder()
+            .text("Loss clips: lost packets / s before / s after:")
+            .position((850, 325))
+            .size((250, 20))
+            .parent(&self.window)
+            .build(&mut self.clip_label)?;
+
+        nwg::TextInput::builder()
+            .text(&clip.loss_threshold.to_string())
+            .position((1105, 320))
+            .size((50, 25))
+            .parent(&self.window)
+            .build(&mut self.clip_threshold_input)?;
+
+        nwg::TextInput::builder()
+            .text(&clip.seconds_before.to_string())
+            .position((1160, 320))
+            .size((50, 25))
+            .parent(&self.window)
+            .build(&mut self.clip_before_input)?;
+
+        nwg::TextInput::builder()
+            .text(&clip.seconds_after.to_string())
+            .position((1215, 320))
+            .size((50, 25))
+            .parent(&self.window)
+            .build(&mut self.clip_after_input)?;
+
+        nwg::Button::builder()
+            .text("Apply")
+            .position((1275, 320))
+            .size((60, 25))
+            .parent(&self.window)
+            .build(&mut self.apply_clip_button)?;
+
+        Ok(())
+    }
+
+    fn build_mosaic_controls(&mut self) -> Result<(), nwg::NwgError> {
+        nwg::Button::builder()
+            .text("Mosaic")
+            .position((10, 390))
+            .size((140, 30))
+            .parent(&self.window)
+            .build(&mut self.mosaic_button)?;
+
+        nwg::Label::builder()
+            .text("Layout:")
+            .position((170, 397))
+            .size((50, 20))
+            .parent(&self.window)
+            .build(&mut self.mosaic_layout_label)?;
+
+        nwg::ComboBox::builder()
+            .position((225, 392))
+            .size((150, 25))
+            .collection(self.mosaic_layouts().iter().map(|layout| layout.name()).collect())
+            .selected_index(Some(0))
+            .parent(&self.window)
+            .build(&mut self.mosaic_layout_dropdown)?;
+
+        Ok(())
+    }
+
+    fn build_snapshot_settings(&mut self) -> Result<(), nwg::NwgError> {
+        let snapshot = self.backend.borrow().snapshot_config();
+
+        nwg::Label::builder()
+            .text("Snapshots:")
+            .position((400, 397))
+            .size((70, 20))
+            .parent(&self.window)
+            .build(&mut self.snapshot_label)?;
+
+        nwg::ComboBox::builder()
+            .position((475, 392))
+            .size((70, 25))
+            .collection(
+                SnapshotFormat::ALL
+                    .iter()
+                    .map(|f| f.extension().to_uppercase())
+                    .collect(),
+            )
+            .selected_index(SnapshotFormat::ALL.iter().position(|f| *f == snapshot.format))
+            .parent(&self.window)
+            .build(&mut self.snapshot_format_dropdown)?;
+
+        nwg::TextInput::builder()
+            .text(&snapshot.directory)
+            .position((555, 392))
+            .size((200, 25))
+            .parent(&self.window)
+            .build(&mut self.snapshot_dir_input)?;
+
+        nwg::Label::builder()
+            .text("")
+            .position((765, 397))
+            .size((700, 20))
+            .parent(&self.window)
+            .build(&mut self.snapshot_status)?;
+
+        Ok(())
+    }
+
+    /// Grid layouts followed by one maximized entry per camera.
+    fn mosaic_layouts(&self) -> Vec<MosaicLayout> {
+        MosaicLayout::GRIDS
+            .iter()
+            .copied()
+            .chain((0..4).map(MosaicLayout::Maximized))
+            .collect()
+    }
+
     fn build_camera_row(&mut self, camera_index: usize) -> Result<(), nwg::NwgError> {
         let y_pos = (40 + camera_index * 60) as i32;
         let config = self
@@ -143,35 +627,217 @@ impl ReceiverUI {
         nwg::TextInput::builder()
             .text(&config.sender_ip)
             .position((80, y_pos - 5))
-            .size((100, 25))
+            .size((140, 25))
             .parent(&self.window)
             .build(&mut self.sender_ip_inputs[camera_index])?;
 
+        nwg::TextInput::builder()
+            .text(&config.bind_address)
+            .position((230, y_pos - 5))
+            .size((140, 25))
+            .parent(&self.window)
+            .build(&mut self.bind_address_inputs[camera_index])?;
+
         nwg::TextInput::builder()
             .text(&config.rtp_port)
-            .position((200, y_pos - 5))
+            .position((380, y_pos - 5))
             .size((80, 25))
             .parent(&self.window)
             .build(&mut self.rtp_port_inputs[camera_index])?;
 
         nwg::TextInput::builder()
             .text(&config.fec_port)
-            .position((300, y_pos - 5))
+            .position((470, y_pos - 5))
             .size((80, 25))
             .parent(&self.window)
             .build(&mut self.fec_port_inputs[camera_index])?;
 
+        nwg::TextInput::builder()
+            .text(&config.multicast_iface)
+            .position((560, y_pos - 5))
+            .size((90, 25))
+            .parent(&self.window)
+            .build(&mut self.multicast_iface_inputs[camera_index])?;
+
+        nwg::CheckBox::builder()
+            .text("")
+            .position((670, y_pos - 5))
+            .size((30, 25))
+            .check_state(if config.source_specific {
+                nwg::CheckBoxState::Checked
+            } else {
+                nwg::CheckBoxState::Unchecked
+            })
+            .parent(&self.window)
+            .build(&mut self.ssm_checkboxes[camera_index])?;
+
+        let mut ciphers = vec!["No encryption".to_string()];
+        ciphers.extend(SrtpCipher::ALL.iter().map(|c| format!("SRTP {}", c.name())));
+        let selected_cipher = config
+            .srtp
+            .as_ref()
+            .and_then(|srtp| SrtpCipher::ALL.iter().position(|c| *c == srtp.cipher))
+            .map_or(0, |position| position + 1);
+
+        nwg::ComboBox::builder()
+            .position((80, y_pos + 22))
+            .size((140, 25))
+            .collection(ciphers)
+            .selected_index(Some(selected_cipher))
+            .parent(&self.window)
+            .build(&mut self.srtp_cipher_dropdowns[camera_index])?;
+
+        nwg::TextInput::builder()
+            .text(config.srtp.as_ref().map_or("", |srtp| srtp.key.as_str()))
+            .position((230, y_pos + 22))
+            .size((410, 25))
+            .parent(&self.window)
+            .build(&mut self.srtp_key_inputs[camera_index])?;
+
+        nwg::CheckBox::builder()
+            .text("Audio")
+            .position((650, y_pos + 22))
+            .size((65, 25))
+            .check_state(if config.audio {
+                nwg::CheckBoxState::Checked
+            } else {
+                nwg::CheckBoxState::Unchecked
+            })
+            .parent(&self.window)
+            .build(&mut self.audio_checkboxes[camera_index])?;
+
         nwg::Button::builder()
             .text("Start")
-            .position((400, y_pos - 5))
+            .position((720, y_pos - 5))
             .size((80, 25))
             .parent(&self.window)
             .build(&mut self.start_buttons[camera_index])?;
 
+        nwg::Button::builder()
+            .text("Record")
+            .position((720, y_pos + 22))
+            .size((80, 25))
+            .parent(&self.window)
+            .build(&mut self.record_buttons[camera_index])?;
+
+        nwg::TextInput::builder()
+            .text("30")
+            .position((810, y_pos + 22))
+            .size((50, 25))
+            .parent(&self.window)
+            .build(&mut self.replay_offset_inputs[camera_index])?;
+
+        nwg::Label::builder()
+            .text("s ago")
+            .position((865, y_pos + 27))
+            .size((40, 20))
+            .parent(&self.window)
+            .build(&mut self.replay_labels[camera_index])?;
+
+        nwg::Button::builder()
+            .text("Replay")
+            .position((910, y_pos + 22))
+            .size((80, 25))
+            .parent(&self.window)
+            .build(&mut self.replay_buttons[camera_index])?;
+
+        nwg::CheckBox::builder()
+            .text("Stats overlay")
+            .position((1000, y_pos + 22))
+            .size((110, 25))
+            .check_state(if self.backend.borrow().is_overlay_enabled(camera_index) {
+                nwg::CheckBoxState::Checked
+            } else {
+                nwg::CheckBoxState::Unchecked
+            })
+            .parent(&self.window)
+            .build(&mut self.overlay_checkboxes[camera_index])?;
+
+        nwg::Button::builder()
+            .text("Snapshot")
+            .position((1120, y_pos + 22))
+            .size((80, 25))
+            .parent(&self.window)
+            .build(&mut self.snapshot_buttons[camera_index])?;
+
+        nwg::CheckBox::builder()
+            .text("Mute")
+            .position((1210, y_pos + 22))
+            .size((70, 25))
+            .check_state(if self.backend.borrow().is_audio_muted(camera_index) {
+                nwg::CheckBoxState::Checked
+            } else {
+                nwg::CheckBoxState::Unchecked
+            })
+            .parent(&self.window)
+            .build(&mut self.mute_checkboxes[camera_index])?;
+
+        nwg::ComboBox::builder()
+            .position((1290, y_pos + 22))
+            .size((100, 25))
+            .collection(PayloadFormat::ALL.iter().map(|p| p.label().to_string()).collect())
+            .selected_index(PayloadFormat::ALL.iter().position(|p| *p == config.payload))
+            .parent(&self.window)
+            .build(&mut self.payload_dropdowns[camera_index])?;
+
+        nwg::ComboBox::builder()
+            .position((1400, y_pos + 22))
+            .size((110, 25))
+            .collection(FecScheme::ALL.iter().map(|f| f.label().to_string()).collect())
+            .selected_index(FecScheme::ALL.iter().position(|f| f.label() == config.fec.label()))
+            .parent(&self.window)
+            .build(&mut self.fec_dropdowns[camera_index])?;
+
+        nwg::CheckBox::builder()
+            .text("Retransmit")
+            .position((1520, y_pos + 22))
+            .size((90, 25))
+            .check_state(if config.retransmission {
+                nwg::CheckBoxState::Checked
+            } else {
+                nwg::CheckBoxState::Unchecked
+            })
+            .parent(&self.window)
+            .build(&mut self.retransmission_checkboxes[camera_index])?;
+
+        let tcp_items = 2 + TcpMode::ALL.len();
+        let (selected_transport, transport_settings) = match &config.transport {
+            Transport::Udp => (0, String::new()),
+            Transport::UdpWithTcpFallback(after) => (1, after.as_secs().to_string()),
+            Transport::Tcp(mode) => (
+                TcpMode::ALL.iter().position(|m| m == mode).map_or(0, |p| p + 2),
+                String::new(),
+            ),
+            Transport::Srt(srt) => (
+                SrtMode::ALL.iter().position(|mode| *mode == srt.mode).map_or(0, |p| p + tcp_items),
+                if srt.passphrase.is_empty() {
+                    srt.latency_ms.to_string()
+                } else {
+                    format!("{}:{}", srt.latency_ms, srt.passphrase)
+                },
+            ),
+        };
+
+        nwg::ComboBox::builder()
+            .position((1620, y_pos + 22))
+            .size((130, 25))
+            .collection(Self::transport_items())
+            .selected_index(Some(selected_transport))
+            .parent(&self.window)
+            .build(&mut self.transport_dropdowns[camera_index])?;
+
+        nwg::TextInput::builder()
+            .text(&transport_settings)
+            .placeholder_text(Some("SRT latency[:pass] / fallback s"))
+            .position((1760, y_pos + 22))
+            .size((150, 25))
+            .parent(&self.window)
+            .build(&mut self.srt_settings_inputs[camera_index])?;
+
         nwg::Label::builder()
             .text("Waiting for stream...")
-            .position((500, y_pos - 5))
-            .size((880, 25))
+            .position((810, y_pos - 5))
+            .size((730, 25))
             .parent(&self.window)
             .build(&mut self.stats_displays[camera_index])?;
 
@@ -182,7 +848,7 @@ impl ReceiverUI {
         for i in 0..4 {
             if self.backend.borrow().is_camera_running(i) {
                 if let Some(stats) = self.backend.borrow().get_camera_stats(i) {
-                    let stats_text = format!(
+                    let mut stats_text = format!(
                         "Received:{} Lost:{} Late:{} Sent:{} Repair:{:.1}% Bitrate:{:.1}kbps Latency:{:.1}ms",
                         stats.packets_received,
                         stats.packets_lost,
@@ -192,6 +858,45 @@ impl ReceiverUI {
                         stats.bitrate,
                         stats.latency
                     );
+                    if stats.overhead > 0.0 {
+                        if let Some(config) = self.backend.borrow().get_camera_config(i) {
+                            stats_text.push_str(&format!(
+                                " Overhead:{:.1}% ({})",
+                                stats.overhead,
+                                config.payload.label()
+                            ));
+                        }
+                    }
+                    let is_srt = self
+                        .backend
+                        .borrow()
+                        .get_camera_config(i)
+                        .is_some_and(|config| matches!(config.transport, Transport::Srt(_)));
+                    if is_srt {
+                        stats_text.push_str(&format!(
+                            " SRT RTT:{:.1}ms Retrans:{} Dropped:{}",
+                            stats.srt_rtt, stats.srt_retransmitted, stats.srt_dropped
+                        ));
+                    }
+                    if stats.tcp_fallback {
+                        stats_text.push_str(" (TCP fallback)");
+                    }
+                    if stats.rtx_recovered > 0 {
+                        stats_text.push_str(&format!(
+                            " FEC:{} RTX:{}",
+                            stats.fec_recovered, stats.rtx_recovered
+                        ));
+                    }
+                    if stats.auth_failures > 0 {
+                        stats_text.push_str(&format!(" AuthFail:{}", stats.auth_failures));
+                    }
+                    if stats.auth_failures >= AUTH_FAILURE_WARNING_THRESHOLD {
+                        stats_text.push_str(" (SRTP key/cipher mismatch?)");
+                    }
+                    let clips = self.backend.borrow().clip_count(i);
+                    if clips > 0 {
+                        stats_text.push_str(&format!(" LossClips:{}", clips));
+                    }
                     self.stats_displays[i].set_text(&stats_text);
                 } else {
                     self.stats_displays[i].set_text("Collecting stats...");
@@ -199,20 +904,87 @@ impl ReceiverUI {
             } else {
                 self.stats_displays[i].set_text("Waiting for stream...");
             }
+
+            if !self.backend.borrow_mut().is_replaying(i) {
+                self.replay_buttons[i].set_text("Replay");
+            }
+        }
+
+        // Closing the mosaic window hands the cameras their own windows back.
+        if self.backend.borrow_mut().mosaic_closed() {
+            self.backend.borrow_mut().stop_mosaic();
+            self.mosaic_button.set_text("Mosaic");
+        }
+
+        self.backend.borrow_mut().update_overlays();
+
+        for message in self.backend.borrow_mut().capture_loss_clips() {
+            println!("{}", message);
+        }
+
+        let warning = self.backend.borrow_mut().check_retention();
+        match warning {
+            Some(warning) => self.retention_status.set_text(&format!("Warning: {}", warning)),
+            None => self.retention_status.set_text(""),
         }
     }
 
     pub fn handle_start_button(&mut self, camera_index: usize) {
-        let sender_ip = self.sender_ip_inputs[camera_index].text();
-        let rtp_port = self.rtp_port_inputs[camera_index].text();
-        let fec_port = self.fec_port_inputs[camera_index].text();
+        let dvr_minutes = match self.dvr_minutes_input.text().trim().parse::<u64>() {
+            Ok(minutes) => self.backend.borrow_mut().set_dvr_minutes(minutes),
+            Err(_) => Err("DVR minutes must be a whole number".to_string()),
+        };
+        if let Err(e) = dvr_minutes {
+            nwg::simple_message("Error", &e);
+            return;
+        }
+
+        // A stopping camera keeps the transport it runs with.
+        let running = self.backend.borrow().is_camera_running(camera_index);
+        let transport = match self.get_transport(camera_index) {
+            Ok(transport) => transport,
+            Err(e) if !running => {
+                nwg::simple_message("Error", &e);
+                return;
+            }
+            Err(_) => self
+                .backend
+                .borrow()
+                .get_camera_config(camera_index)
+                .map_or(Transport::Udp, |config| config.transport.clone()),
+        };
 
-        self.backend.borrow_mut().update_camera_config(
-            camera_index,
-            &sender_ip,
-            &rtp_port,
-            &fec_port,
-        );
+        let config = CameraConfig {
+            sender_ip: self.sender_ip_inputs[camera_index].text(),
+            bind_address: self.bind_address_inputs[camera_index].text(),
+            rtp_port: self.rtp_port_inputs[camera_index].text(),
+            fec_port: self.fec_port_inputs[camera_index].text(),
+            multicast_iface: self.multicast_iface_inputs[camera_index].text().trim().to_string(),
+            source_specific: self.ssm_checkboxes[camera_index].check_state()
+                == nwg::CheckBoxState::Checked,
+            srtp: self.get_srtp_config(camera_index),
+            recording: None,
+            dvr: None,
+            mosaic_port: None,
+            frames: None,
+            audio: self.audio_checkboxes[camera_index].check_state() == nwg::CheckBoxState::Checked,
+            audio_muted: false,
+            payload: self.payload_dropdowns[camera_index]
+                .selection()
+                .and_then(|selection| PayloadFormat::ALL.get(selection).copied())
+                .unwrap_or(PayloadFormat::MpegTs),
+            fec: self.fec_dropdowns[camera_index]
+                .selection()
+                .and_then(|selection| FecScheme::ALL.get(selection).copied())
+                .unwrap_or(FecScheme::RaptorQ),
+            retransmission: self.retransmission_checkboxes[camera_index].check_state()
+                == nwg::CheckBoxState::Checked,
+            transport,
+        };
+
+        self.backend
+            .borrow_mut()
+            .update_camera_config(camera_index, config);
 
         if let Err(e) = self.backend.borrow_mut().toggle_camera(camera_index) {
             nwg::simple_message(
@@ -236,6 +1008,273 @@ impl ReceiverUI {
         }
     }
 
+    fn transport_items() -> Vec<String> {
+        let mut items = vec!["UDP".to_string(), "UDP + TCP fallback".to_string()];
+        items.extend(TcpMode::ALL.iter().map(|mode| format!("TCP {}", mode.name())));
+        items.extend(SrtMode::ALL.iter().map(|mode| format!("SRT {}", mode.name())));
+        items
+    }
+
+    fn get_transport(&self, camera_index: usize) -> Result<Transport, String> {
+        let selection = self.transport_dropdowns[camera_index].selection().unwrap_or(0);
+        let settings = self.srt_settings_inputs[camera_index].text();
+        let srt_selection = selection.checked_sub(2 + TcpMode::ALL.len());
+
+        if let Some(mode) = srt_selection.and_then(|s| SrtMode::ALL.get(s).copied()) {
+            return SrtConfig::with_settings(mode, &settings).map(Transport::Srt);
+        }
+        match selection {
+            1 => Transport::tcp_fallback(&settings),
+            selection => Ok(selection
+                .checked_sub(2)
+                .and_then(|s| TcpMode::ALL.get(s).copied())
+                .map_or(Transport::Udp, Transport::Tcp)),
+        }
+    }
+
+    fn get_srtp_config(&self, camera_index: usize) -> Option<SrtpConfig> {
+        match self.srtp_cipher_dropdowns[camera_index].selection() {
+            Some(selection) if selection > 0 && selection <= SrtpCipher::ALL.len() => {
+                Some(SrtpConfig {
+                    cipher: SrtpCipher::ALL[selection - 1],
+                    key: self.srtp_key_inputs[camera_index].text(),
+                    mki: None,
+                    additional_keys: Vec::new(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn get_recording_config(&self) -> Result<RecordingConfig, String> {
+        let format = self
+            .recording_format_dropdown
+            .selection()
+            .and_then(|selection| RecordingFormat::ALL.get(selection).copied())
+            .ok_or("Select a recording format")?;
+
+        let directory = self.recording_dir_input.text().trim().to_string();
+        if directory.is_empty() {
+            return Err("Enter a recording directory".to_string());
+        }
+
+        let segment_seconds = self
+            .segment_seconds_input
+            .text()
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| "Segment length must be a whole number of seconds")?;
+        let segment_megabytes = self
+            .segment_megabytes_input
+            .text()
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| "Segment size must be a whole number of megabytes")?;
+
+        Ok(RecordingConfig {
+            format,
+            directory,
+            segment_seconds,
+            segment_megabytes,
+        })
+    }
+
+    pub fn handle_record_button(&mut self, camera_index: usize) {
+        let result = if self.backend.borrow().is_recording(camera_index) {
+            self.backend.borrow_mut().stop_recording(camera_index)
+        } else {
+            self.get_recording_config().and_then(|recording| {
+                self.backend
+                    .borrow_mut()
+                    .start_recording(camera_index, recording)
+            })
+        };
+
+        if let Err(e) = result {
+            nwg::simple_message(
+                "Error",
+                &format!("Failed to toggle recording for camera {}: {}", camera_index + 1, e),
+            );
+        }
+
+        let button_text = if self.backend.borrow().is_recording(camera_index) {
+            "Stop Rec"
+        } else {
+            "Record"
+        };
+        self.record_buttons[camera_index].set_text(button_text);
+    }
+
+    pub fn handle_snapshot_button(&mut self, camera_index: usize) {
+        let directory = self.snapshot_dir_input.text().trim().to_string();
+        if directory.is_empty() {
+            nwg::simple_message("Error", "Enter a snapshot directory");
+            return;
+        }
+        let format = self
+            .snapshot_format_dropdown
+            .selection()
+            .and_then(|selection| SnapshotFormat::ALL.get(selection).copied())
+            .unwrap_or(SnapshotFormat::Jpeg);
+
+        self.backend
+            .borrow_mut()
+            .set_snapshot_config(SnapshotConfig { directory, format });
+
+        match self.backend.borrow().take_snapshot(camera_index) {
+            Ok(path) => self
+                .snapshot_status
+                .set_text(&format!("Saved {}", path.display())),
+            Err(e) => nwg::simple_message(
+                "Error",
+                &format!("Failed to take snapshot of camera {}: {}", camera_index + 1, e),
+            ),
+        }
+    }
+
+    pub fn handle_overlay_checkbox(&mut self, camera_index: usize) {
+        let enabled = self.overlay_checkboxes[camera_index].check_state() == nwg::CheckBoxState::Checked;
+        self.backend.borrow_mut().set_overlay(camera_index, enabled);
+    }
+
+    pub fn handle_mute_checkbox(&mut self, camera_index: usize) {
+        let muted = self.mute_checkboxes[camera_index].check_state() == nwg::CheckBoxState::Checked;
+        self.backend.borrow_mut().set_audio_muted(camera_index, muted);
+    }
+
+    pub fn handle_replay_button(&mut self, camera_index: usize) {
+        if self.backend.borrow_mut().is_replaying(camera_index) {
+            self.backend.borrow_mut().stop_replay(camera_index);
+            self.replay_buttons[camera_index].set_text("Replay");
+            return;
+        }
+
+        let result = self.replay_offset_inputs[camera_index]
+            .text()
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| "Replay offset must be a whole number of seconds".to_string())
+            .and_then(|seconds| self.backend.borrow_mut().start_replay(camera_index, seconds));
+
+        match result {
+            Ok(()) => self.replay_buttons[camera_index].set_text("Close"),
+            Err(e) => nwg::simple_message(
+                "Error",
+                &format!("Failed to replay camera {}: {}", camera_index + 1, e),
+            ),
+        }
+    }
+
+    pub fn handle_mosaic_button(&mut self) {
+        if self.backend.borrow().mosaic_layout().is_some() {
+            self.backend.borrow_mut().stop_mosaic();
+            self.mosaic_button.set_text("Mosaic");
+            return;
+        }
+
+        let layout = self.selected_mosaic_layout();
+        match self.backend.borrow_mut().start_mosaic(layout) {
+            Ok(()) => self.mosaic_button.set_text("Close Mosaic"),
+            Err(e) => nwg::simple_message("Error", &e),
+        }
+    }
+
+    /// Switches the open mosaic to the newly selected layout.
+    pub fn handle_mosaic_layout_selection(&mut self) {
+        if self.backend.borrow().mosaic_layout().is_none() {
+            return;
+        }
+
+        let layout = self.selected_mosaic_layout();
+        if let Err(e) = self.backend.borrow_mut().start_mosaic(layout) {
+            nwg::simple_message("Error", &e);
+            self.mosaic_button.set_text("Mosaic");
+        }
+    }
+
+    fn selected_mosaic_layout(&self) -> MosaicLayout {
+        self.mosaic_layout_dropdown
+            .selection()
+            .and_then(|selection| self.mosaic_layouts().get(selection).copied())
+            .unwrap_or(MosaicLayout::Grid2x2)
+    }
+
+    pub fn handle_apply_retention(&mut self) {
+        let parse = |input: &nwg::TextInput, what: &str| {
+            input
+                .text()
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| format!("{} must be a whole number", what))
+        };
+
+        let policy = parse(&self.max_age_input, "Max age").and_then(|max_age_hours| {
+            Ok(RetentionPolicy {
+                max_age_hours,
+                max_megabytes_per_camera: parse(&self.max_camera_megabytes_input, "MB per camera")?,
+                min_free_megabytes: parse(&self.min_free_megabytes_input, "Min free MB")?,
+            })
+        });
+
+        match policy {
+            Ok(policy) => self.backend.borrow_mut().set_retention_policy(policy),
+            Err(e) => nwg::simple_message("Error", &e),
+        }
+    }
+
+    pub fn handle_apply_clip(&mut self) {
+        let parse = |input: &nwg::TextInput, what: &str| {
+            input
+                .text()
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| format!("{} must be a whole number", what))
+        };
+
+        let mut clip = self.backend.borrow().clip_config();
+        let result = parse(&self.clip_threshold_input, "Lost packets").and_then(|threshold| {
+            clip.loss_threshold = u32::try_from(threshold).map_err(|_| "Lost packets is too large")?;
+            clip.seconds_before = parse(&self.clip_before_input, "Seconds before")?;
+            clip.seconds_after = parse(&self.clip_after_input, "Seconds after")?;
+            Ok(())
+        });
+
+        match result {
+            Ok(()) => self.backend.borrow_mut().set_clip_config(clip),
+            Err(e) => nwg::simple_message("Error", &e),
+        }
+    }
+
+    pub fn handle_import_keys(&mut self) {
+        let mut dialog = nwg::FileDialog::default();
+        if nwg::FileDialog::builder()
+            .title("Import SRTP key bundle")
+            .action(nwg::FileDialogAction::Open)
+            .filters("Key bundle(*.txt)")
+            .build(&mut dialog)
+            .is_err()
+            || !dialog.run(Some(&self.window))
+        {
+            return;
+        }
+
+        let path = match dialog.get_selected_item() {
+            Ok(path) => path,
+            Err(_) => return,
+        };
+
+        match self.backend.borrow_mut().import_key_bundle(Path::new(&path)) {
+            Ok(count) => nwg::simple_message(
+                "Key Bundle",
+                &format!(
+                    "Imported {} key(s). Cameras using the key store pick them up on their next start.",
+                    count
+                ),
+            ),
+            Err(e) => nwg::simple_message("Error", &e),
+        }
+    }
+
     pub fn get_window_handle(&self) -> &nwg::ControlHandle {
         &self.window.handle
     }
@@ -247,10 +1286,23 @@ impl ReceiverUI {
     ) -> Option<&nwg::ControlHandle> {
         match button_type {
             "start" => Some(&self.start_buttons[camera_index].handle),
+            "record" => Some(&self.record_buttons[camera_index].handle),
+            "replay" => Some(&self.replay_buttons[camera_index].handle),
+            "overlay" => Some(&self.overlay_checkboxes[camera_index].handle),
+            "mute" => Some(&self.mute_checkboxes[camera_index].handle),
+            "snapshot" => Some(&self.snapshot_buttons[camera_index].handle),
+            "import_keys" => Some(&self.import_keys_button.handle),
+            "apply_retention" => Some(&self.apply_retention_button.handle),
+            "apply_clip" => Some(&self.apply_clip_button.handle),
+            "mosaic" => Some(&self.mosaic_button.handle),
             _ => None,
         }
     }
 
+    pub fn get_mosaic_layout_handle(&self) -> &nwg::ControlHandle {
+        &self.mosaic_layout_dropdown.handle
+    }
+
     pub fn get_timer_handle(&self) -> &nwg::ControlHandle {
         &self.stats_timer.handle
     }
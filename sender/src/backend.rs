@@ -1,4 +1,4 @@
-use crate::types::CameraInfo;
+use crate::types::{CameraInfo, SourceKind};
 use std::process::Command;
 
 pub struct CameraBackend;
@@ -103,6 +103,7 @@ impl CameraBackend {
                     index: i,
                     name: device_name,
                     device_path: format!("device-index={}", i),
+                    source: SourceKind::Device(i),
                 });
             } else {
                 println!("Index {} - No working configuration found", i);
@@ -134,4 +135,46 @@ impl CameraBackend {
 
         Ok(cameras)
     }
+
+    /// Lists the monitors as screen-capture entries. Falls back to a single
+    /// screen when they cannot be enumerated.
+    pub fn detect_screens() -> Vec<CameraInfo> {
+        let mut screens = Vec::new();
+
+        if let Ok(output) = Command::new("powershell")
+            .args([
+                "-Command",
+                "Add-Type -AssemblyName System.Windows.Forms; [System.Windows.Forms.Screen]::AllScreens | ForEach-Object { \"$($_.Bounds.Width)x$($_.Bounds.Height)\" }",
+            ])
+            .output()
+        {
+            if output.status.success() {
+                let output_str = String::from_utf8_lossy(&output.stdout);
+                let sizes = output_str.lines().map(str::trim).filter(|l| !l.is_empty());
+                for (monitor, size) in sizes.enumerate() {
+                    let name = format!("Screen {} ({})", monitor, size);
+                    screens.push(Self::screen_info(monitor, &name));
+                }
+            }
+        }
+
+        if screens.is_empty() {
+            screens.push(Self::screen_info(0, "Screen 0"));
+        }
+        println!("Screens available for capture: {}", screens.len());
+
+        screens
+    }
+
+    fn screen_info(monitor: usize, name: &str) -> CameraInfo {
+        CameraInfo {
+            index: monitor,
+            name: name.to_string(),
+            device_path: format!("monitor-index={}", monitor),
+            source: SourceKind::Screen {
+                monitor,
+                region: None,
+            },
+        }
+    }
 }
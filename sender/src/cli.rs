@@ -0,0 +1,324 @@
+use sender::gstreamer::GStreamerManager;
+use sender::sources;
+use sender::types::{
+    AudioConfig, FecScheme, PayloadFormat, SourceKind, StreamConfig, Transport, parse_ip_address,
+};
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const USAGE: &str = "\
+Usage: sender [--headless] [options]
+
+Without options the sender opens its window. With --headless it runs from
+the console and reads commands from standard input.
+
+Options:
+  --source <slot>=<source>  Source of a camera slot, e.g. 1=pattern:ball
+  --audio <slot>=<audio>    Audio of a camera slot: mic or tone, optionally
+                            with :opus (default) or :aac, e.g. 1=tone:aac
+  --payload <slot>=<fmt>    RTP payload of a camera slot: ts (H.264 in
+                            MPEG-TS, default), h264, h265 or vp8
+  --fec <slot>=<scheme>     FEC of a camera slot: raptorq (default) or
+                            ulpfec[:<percent>[,<key frame percent>]]
+  --retransmit <slots>      Slots that resend packets receivers report
+                            lost, e.g. 1,3 (RaptorQ FEC only)
+  --transport <slot>=<t>    Transport of a camera slot: udp (default),
+                            udp+tcp (UDP, also served over TCP for receivers
+                            falling back to it), tcp:<connect|listen> or
+                            srt:<caller|listener|rendezvous>[:<latency ms>
+                            [:<passphrase>]], e.g. 2=srt:caller:200
+  --ip <address>            Receiver address (default 127.0.0.1)
+  --base-port <port>        RTP port of slot 1; slot N uses port + 4(N-1)
+                            and FEC port + 2 (default 5000)
+  --start <slots>           Slots to start, e.g. 1,3 (default: all with a source)
+  --duration <seconds>      Stop everything and exit after this long
+
+Sources:
+  device:<index>            Capture device
+  pattern:smpte|ball|snow   Test pattern with a running clock
+  file:<path>               Video file, looped
+  slideshow:<dir>[:<secs>]  JPEG or PNG images of a directory
+  screen:<monitor>[@<x>,<y>,<w>x<h>]
+                            Monitor, or a region of it
+  window:<title>            Window with this text in its title
+  rtsp://... | http://...   Network camera, decoded and re-encoded
+  copy:rtsp://...           RTSP camera whose H.264 is sent on unchanged
+
+Commands:
+  start <slot> | stop <slot> | source <slot> <source>
+  help | quit";
+
+const SLOT_COUNT: usize = 6;
+
+pub struct CliOptions {
+    pub sources: Vec<Option<SourceKind>>,
+    pub audio: Vec<Option<AudioConfig>>,
+    pub payloads: Vec<PayloadFormat>,
+    pub fec: Vec<FecScheme>,
+    pub retransmission: Vec<bool>,
+    pub transports: Vec<Transport>,
+    pub ip: String,
+    pub base_port: u16,
+    pub start: Option<Vec<usize>>,
+    pub duration: Option<Duration>,
+}
+
+/// Parses the command line. Returns `None` when the sender should open its
+/// window as usual.
+pub fn parse_args(args: &[String]) -> Result<Option<CliOptions>, String> {
+    if args.is_empty() {
+        return Ok(None);
+    }
+
+    let mut headless = false;
+    let mut options = CliOptions {
+        sources: vec![None; SLOT_COUNT],
+        audio: vec![None; SLOT_COUNT],
+        payloads: vec![PayloadFormat::MpegTs; SLOT_COUNT],
+        fec: vec![FecScheme::RaptorQ; SLOT_COUNT],
+        retransmission: vec![false; SLOT_COUNT],
+        transports: vec![Transport::Udp; SLOT_COUNT],
+        ip: "127.0.0.1".to_string(),
+        base_port: 5000,
+        start: None,
+        duration: None,
+    };
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", arg))
+        };
+
+        match arg.as_str() {
+            "--headless" => headless = true,
+            "--source" => {
+                let text = value()?;
+                let (slot, source) = text
+                    .split_once('=')
+                    .ok_or_else(|| format!("{} needs <slot>=<source>", arg))?;
+                options.sources[parse_slot(slot)?] = Some(SourceKind::parse(source)?);
+            }
+            "--audio" => {
+                let text = value()?;
+                let (slot, audio) = text
+                    .split_once('=')
+                    .ok_or_else(|| format!("{} needs <slot>=<audio>", arg))?;
+                options.audio[parse_slot(slot)?] = Some(AudioConfig::parse(audio)?);
+            }
+            "--payload" => {
+                let text = value()?;
+                let (slot, payload) = text
+                    .split_once('=')
+                    .ok_or_else(|| format!("{} needs <slot>=<format>", arg))?;
+                options.payloads[parse_slot(slot)?] = PayloadFormat::from_name(payload)
+                    .ok_or_else(|| format!("Unknown payload format '{}'", payload))?;
+            }
+            "--fec" => {
+                let text = value()?;
+                let (slot, fec) = text
+                    .split_once('=')
+                    .ok_or_else(|| format!("{} needs <slot>=<scheme>", arg))?;
+                options.fec[parse_slot(slot)?] = FecScheme::parse(fec)?;
+            }
+            "--retransmit" => {
+                for text in value()?.split(',') {
+                    options.retransmission[parse_slot(text)?] = true;
+                }
+            }
+            "--transport" => {
+                let text = value()?;
+                let (slot, transport) = text
+                    .split_once('=')
+                    .ok_or_else(|| format!("{} needs <slot>=<transport>", arg))?;
+                options.transports[parse_slot(slot)?] = Transport::parse(transport)?;
+            }
+            "--ip" => options.ip = parse_ip_address(&value()?)?.to_string(),
+            "--base-port" => {
+                let port = parse_number(arg, &value()?)?;
+                options.base_port = u16::try_from(port)
+                    .ok()
+                    .filter(|port| port.checked_add(4 * SLOT_COUNT as u16).is_some())
+                    .ok_or_else(|| format!("{} is out of range", arg))?;
+            }
+            "--start" => {
+                options.start = Some(
+                    value()?
+                        .split(',')
+                        .map(parse_slot)
+                        .collect::<Result<_, _>>()?,
+                );
+            }
+            "--duration" => {
+                options.duration = Some(Duration::from_secs(parse_number(arg, &value()?)?));
+            }
+            "--help" | "-h" => return Err(String::new()),
+            other => return Err(format!("Unknown option '{}'", other)),
+        }
+    }
+
+    if !headless {
+        return Err("Options are only supported together with --headless".to_string());
+    }
+
+    Ok(Some(options))
+}
+
+/// Runs the sender from the console until `quit`, end of input or the end
+/// of `--duration`. With a duration, end of input does not stop it, so it
+/// can run unattended.
+pub fn run(manager: &mut GStreamerManager, mut options: CliOptions) {
+    manager.initialize_streams(SLOT_COUNT);
+
+    let start = options.start.clone().unwrap_or_else(|| {
+        (0..SLOT_COUNT)
+            .filter(|&slot| options.sources[slot].is_some())
+            .collect()
+    });
+    for slot in start {
+        report(slot, "start", start_slot(manager, &options, slot));
+    }
+
+    println!("Sender running headless. Type 'help' for commands.");
+
+    let deadline = options.duration.map(|duration| Instant::now() + duration);
+
+    let (line_sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            if line_sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut input_open = true;
+    loop {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            println!("Duration reached");
+            break;
+        }
+
+        if !input_open {
+            thread::sleep(Duration::from_millis(200));
+            continue;
+        }
+
+        let line = match lines.recv_timeout(Duration::from_secs(1)) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => {
+                if deadline.is_none() {
+                    break;
+                }
+                input_open = false;
+                continue;
+            }
+        };
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => continue,
+        };
+        let slot = words.next().map(parse_slot);
+
+        match (command, slot) {
+            ("quit" | "exit", _) => break,
+            ("help", _) => println!("{}", USAGE),
+            (_, Some(Err(e))) => println!("{}", e),
+            ("start", Some(Ok(slot))) => report(slot, command, start_slot(manager, &options, slot)),
+            ("stop", Some(Ok(slot))) => {
+                if manager.is_streaming(slot) {
+                    manager.stop_pipeline(slot);
+                    report(slot, command, Ok(()))
+                } else {
+                    report(slot, command, Err("not streaming".to_string()))
+                }
+            }
+            // The rest of the line, so file paths may contain spaces.
+            ("source", Some(Ok(slot))) => {
+                match line.trim().splitn(3, ' ').nth(2).map(SourceKind::parse) {
+                    Some(Ok(source)) => {
+                        println!("Slot {}: {} (used on next start)", slot + 1, source);
+                        options.sources[slot] = Some(source);
+                    }
+                    Some(Err(e)) => println!("{}", e),
+                    None => println!("Usage: source <slot> <source>"),
+                }
+            }
+            _ => println!(
+                "Unknown command '{}'. Type 'help' for commands.",
+                line.trim()
+            ),
+        }
+        let _ = io::stdout().flush();
+    }
+
+    for slot in 0..SLOT_COUNT {
+        if manager.is_streaming(slot) {
+            manager.stop_pipeline(slot);
+        }
+    }
+}
+
+fn start_slot(
+    manager: &mut GStreamerManager,
+    options: &CliOptions,
+    slot: usize,
+) -> Result<(), String> {
+    if manager.is_streaming(slot) {
+        return Err("already streaming".to_string());
+    }
+    let source = options.sources[slot]
+        .clone()
+        .ok_or("no source; set one with --source or 'source'")?;
+    sources::check_source(&source)?;
+
+    let port = options.base_port + 4 * slot as u16;
+    let config = StreamConfig {
+        source,
+        ip: options.ip.clone(),
+        port: port.to_string(),
+        fec_port: (port + 2).to_string(),
+        multicast_ttl: 16,
+        multicast_iface: String::new(),
+        relay_ports: None,
+        srtp: None,
+        audio: options.audio[slot],
+        payload: options.payloads[slot],
+        fec: options.fec[slot],
+        retransmission: options.retransmission[slot],
+        transport: options.transports[slot].clone(),
+    };
+    config.check()?;
+
+    manager.start_pipeline(slot, config);
+    Ok(())
+}
+
+fn report(slot: usize, action: &str, result: Result<(), String>) {
+    match result {
+        Ok(()) => println!("Slot {}: {} ok", slot + 1, action),
+        Err(e) => println!("Slot {}: {} failed: {}", slot + 1, action, e),
+    }
+}
+
+fn parse_slot(text: &str) -> Result<usize, String> {
+    match text.trim().parse::<usize>() {
+        Ok(number) if (1..=SLOT_COUNT).contains(&number) => Ok(number - 1),
+        _ => Err(format!(
+            "'{}' is not a slot number between 1 and {}",
+            text, SLOT_COUNT
+        )),
+    }
+}
+
+fn parse_number(option: &str, text: &str) -> Result<u64, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("{} needs a whole number, got '{}'", option, text))
+}
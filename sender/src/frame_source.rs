@@ -0,0 +1,86 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use std::time::Duration;
+
+/// Name of the `appsrc` frames are pushed into.
+pub const APPSRC_NAME: &str = "frames";
+
+/// Raw video pushed into a frame source.
+#[derive(Clone, Debug)]
+pub struct FrameFormat {
+    /// GStreamer raw video format name, e.g. `RGB`, `BGRA` or `I420`.
+    pub format: String,
+    pub width: u32,
+    pub height: u32,
+    pub framerate: u32,
+}
+
+impl FrameFormat {
+    pub fn caps(&self) -> String {
+        format!(
+            "video/x-raw,format={},width={},height={},framerate={}/1",
+            self.format, self.width, self.height, self.framerate
+        )
+    }
+}
+
+/// Capture chain reading pushed frames instead of a camera, ending in
+/// 640x480 at 15 fps like the camera sources. Frames pushed faster than the
+/// encoder takes them are dropped by the leaky queue.
+pub fn appsrc_source(format: &FrameFormat) -> String {
+    format!(
+        "appsrc name={} is-live=true format=time do-timestamp=false caps=\"{}\" ! \
+queue leaky=downstream max-size-buffers=2 ! videoconvert ! videoscale ! video/x-raw,width=640,height=480 ! videorate ! video/x-raw,framerate=15/1",
+        APPSRC_NAME,
+        format.caps()
+    )
+}
+
+/// Pushes frames from Rust code into a camera slot's stream. Returned by
+/// `GStreamerManager::start_frame_source`; it can be cloned and moved to
+/// other threads. Once the slot is stopped every push fails.
+#[derive(Clone)]
+pub struct FrameSource {
+    appsrc: gst::Element,
+    format: FrameFormat,
+}
+
+impl FrameSource {
+    pub fn new(appsrc: gst::Element, format: FrameFormat) -> Self {
+        Self { appsrc, format }
+    }
+
+    pub fn format(&self) -> &FrameFormat {
+        &self.format
+    }
+
+    /// Sends one frame laid out as `format` describes. `pts` is the frame's
+    /// time in the stream's running time, which starts when the source does;
+    /// with `None` the frame is stamped with the current running time.
+    pub fn push(&self, data: Vec<u8>, pts: Option<Duration>) -> Result<(), String> {
+        let pts = match pts {
+            Some(pts) => Some(gst::ClockTime::from_nseconds(pts.as_nanos() as u64)),
+            None => self.appsrc.current_running_time(),
+        };
+
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        {
+            let buffer = buffer.get_mut().ok_or("Frame buffer is not writable")?;
+            buffer.set_pts(pts);
+            if self.format.framerate > 0 {
+                buffer.set_duration(gst::ClockTime::SECOND / self.format.framerate as u64);
+            }
+        }
+
+        self.appsrc
+            .emit_by_name::<gst::FlowReturn>("push-buffer", &[&buffer])
+            .into_result()
+            .map(|_| ())
+            .map_err(|e| format!("Frame not accepted: {:?}", e))
+    }
+
+    /// Ends the stream; the slot keeps streaming nothing until stopped.
+    pub fn end(&self) {
+        let _ = self.appsrc.emit_by_name::<gst::FlowReturn>("end-of-stream", &[]);
+    }
+}
@@ -1,12 +1,41 @@
-use crate::types::StreamConfig;
-use std::process::Command;
-use std::sync::{Arc, Mutex};
+use crate::frame_source::{self, APPSRC_NAME, FrameFormat, FrameSource};
+use crate::preview::{self, PreviewFeed, PreviewWindow};
+use crate::relay::UdpRelay;
+use crate::sources;
+use crate::types::{
+    Destination, PayloadFormat, RTX_PAYLOAD_TYPE, SourceKind, SrtpConfig, StreamConfig, TcpMode,
+    Transport, parse_ip_address,
+};
+use ::gstreamer as gst;
+use ::gstreamer::prelude::*;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
+use std::time::{Duration, Instant};
+
+/// A capture chain the machine cannot run fails within this long of
+/// starting; the next one is tried instead.
+const STARTUP_TIME: Duration = Duration::from_secs(2);
+
+/// Names of the `srtpenc`s protecting the RTP and FEC flows.
+const SRTP_ENCODER_NAMES: [&str; 2] = ["srtp_rtp", "srtp_fec"];
+
+/// Name of the `valve` that holds back a streaming pipeline's preview branch
+/// while no preview window is open.
+const PREVIEW_VALVE_NAME: &str = "preview_valve";
 
 pub struct GStreamerManager {
     pub streaming: Vec<Arc<Mutex<bool>>>,
     pub stream_threads: Vec<Option<thread::JoinHandle<()>>>,
-    pub gst_pids: Vec<Arc<Mutex<Option<u32>>>>,
+    pub destinations: Vec<Arc<Mutex<Vec<Destination>>>>,
+    relays: Vec<Option<UdpRelay>>,
+    configs: Vec<Option<StreamConfig>>,
+    previews: Vec<Option<PreviewWindow>>,
+    preview_feeds: Vec<Option<PreviewFeed>>,
+    /// Whether each slot's preview window is open, which opens the preview
+    /// valve of its streaming pipeline.
+    previewing: Vec<Arc<Mutex<bool>>>,
+    /// Each slot's pipeline while it runs.
+    pipelines: Vec<Arc<Mutex<Option<gst::Element>>>>,
 }
 
 impl GStreamerManager {
@@ -14,7 +43,13 @@ impl GStreamerManager {
         Self {
             streaming: Vec::new(),
             stream_threads: Vec::new(),
-            gst_pids: Vec::new(),
+            destinations: Vec::new(),
+            relays: Vec::new(),
+            configs: Vec::new(),
+            previews: Vec::new(),
+            preview_feeds: Vec::new(),
+            previewing: Vec::new(),
+            pipelines: Vec::new(),
         }
     }
 
@@ -22,201 +57,775 @@ impl GStreamerManager {
         for _ in 0..count {
             self.streaming.push(Arc::new(Mutex::new(false)));
             self.stream_threads.push(None);
-            self.gst_pids.push(Arc::new(Mutex::new(None)));
+            self.destinations.push(Arc::new(Mutex::new(Vec::new())));
+            self.relays.push(None);
+            self.configs.push(None);
+            self.previews.push(None);
+            self.preview_feeds.push(None);
+            self.previewing.push(Arc::new(Mutex::new(false)));
+            self.pipelines.push(Arc::new(Mutex::new(None)));
         }
     }
 
     pub fn start_pipeline(&mut self, control_index: usize, config: StreamConfig) {
-        *self.streaming[control_index].lock().unwrap() = true;
+        // The streaming pipeline takes over the camera and the preview.
+        self.stop_preview_feed(control_index);
+
+        let config = self.begin_stream(control_index, config);
 
         let streaming = Arc::clone(&self.streaming[control_index]);
-        let gst_pid = Arc::clone(&self.gst_pids[control_index]);
+        let running = Arc::clone(&self.pipelines[control_index]);
+        let previewing = Arc::clone(&self.previewing[control_index]);
 
         self.stream_threads[control_index] = Some(thread::spawn(move || {
-            Self::run_pipeline_with_fallback(control_index, config, streaming, gst_pid);
+            Self::run_pipeline_with_fallback(control_index, config, streaming, running, previewing);
         }));
     }
 
+    /// Streams frames pushed from Rust code through a camera slot instead of
+    /// a capture device, with the same encoding, FEC, SRTP and destinations.
+    /// `config.source` is not used. The pipeline runs inside this
+    /// process; stop it with `stop_pipeline` like any other.
+    pub fn start_frame_source(
+        &mut self,
+        control_index: usize,
+        config: StreamConfig,
+        format: FrameFormat,
+    ) -> Result<FrameSource, String> {
+        if self.is_streaming(control_index) {
+            return Err(format!("Camera slot {} is already streaming", control_index + 1));
+        }
+        init_gstreamer()?;
+        self.stop_preview_feed(control_index);
+
+        let config = self.begin_stream(control_index, config);
+        let description = Self::build_pipeline_description(
+            &config,
+            control_index,
+            &frame_source::appsrc_source(&format),
+            false,
+        );
+
+        let (pipeline, appsrc) = match Self::launch_in_process(&description) {
+            Ok(launched) => launched,
+            Err(e) => {
+                self.end_stream(control_index);
+                return Err(e);
+            }
+        };
+
+        println!("Camera slot {} - Streaming frames pushed from code", control_index + 1);
+
+        let streaming = Arc::clone(&self.streaming[control_index]);
+        let running = Arc::clone(&self.pipelines[control_index]);
+        *running.lock().unwrap() = Some(pipeline.clone());
+        Self::set_preview_valve(&pipeline, *self.previewing[control_index].lock().unwrap());
+        self.stream_threads[control_index] = Some(thread::spawn(move || {
+            Self::monitor_in_process(control_index, pipeline, streaming, running);
+        }));
+
+        Ok(FrameSource::new(appsrc, format))
+    }
+
+    fn launch_in_process(description: &str) -> Result<(gst::Element, gst::Element), String> {
+        let pipeline = gst::parse_launch(description).map_err(|e| e.to_string())?;
+        let appsrc = pipeline
+            .downcast_ref::<gst::Bin>()
+            .and_then(|bin| bin.by_name(APPSRC_NAME))
+            .ok_or("Pipeline has no frame source")?;
+
+        if let Err(e) = pipeline.set_state(gst::State::Playing) {
+            let _ = pipeline.set_state(gst::State::Null);
+            return Err(e.to_string());
+        }
+        Ok((pipeline, appsrc))
+    }
+
+    fn monitor_in_process(
+        control_index: usize,
+        pipeline: gst::Element,
+        streaming: Arc<Mutex<bool>>,
+        running: Arc<Mutex<Option<gst::Element>>>,
+    ) {
+        if let Some(bus) = pipeline.bus() {
+            while *streaming.lock().unwrap() {
+                let message = match bus.timed_pop(gst::ClockTime::from_mseconds(100)) {
+                    Some(message) => message,
+                    None => continue,
+                };
+                match message.view() {
+                    gst::MessageView::Eos(_) => {
+                        println!("Camera slot {} - Frame source ended", control_index + 1);
+                        break;
+                    }
+                    gst::MessageView::Error(err) => {
+                        println!("Camera slot {} - Pipeline error: {}", control_index + 1, err.error());
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let _ = pipeline.set_state(gst::State::Null);
+        *running.lock().unwrap() = None;
+    }
+
+    /// Marks a slot as streaming and, for RTP streams with extra destinations,
+    /// starts its fan-out relay. Returns the config with the relay ports
+    /// filled in; without a relay the pipeline only sends to the receiver.
+    fn begin_stream(&mut self, control_index: usize, mut config: StreamConfig) -> StreamConfig {
+        *self.streaming[control_index].lock().unwrap() = true;
+
+        config.relay_ports = None;
+        let has_destinations = !self.destinations[control_index].lock().unwrap().is_empty();
+        if config.transport.is_rtp() && has_destinations {
+            match UdpRelay::start(Arc::clone(&self.destinations[control_index])) {
+                Ok(relay) => {
+                    config.relay_ports = Some((relay.rtp_port, relay.fec_port));
+                    self.relays[control_index] = Some(relay);
+                }
+                Err(e) => println!(
+                    "Warning: Failed to start fan-out relay for {}: {}",
+                    config.source, e
+                ),
+            }
+        }
+
+        self.configs[control_index] = Some(config.clone());
+        config
+    }
+
+    fn end_stream(&mut self, control_index: usize) {
+        *self.streaming[control_index].lock().unwrap() = false;
+
+        if let Some(mut relay) = self.relays[control_index].take() {
+            relay.stop();
+        }
+
+        self.configs[control_index] = None;
+    }
+
+    /// Runs the first capture chain that works for the source inside this
+    /// process until the slot stops streaming.
     fn run_pipeline_with_fallback(
         control_index: usize,
         config: StreamConfig,
         streaming: Arc<Mutex<bool>>,
-        gst_pid: Arc<Mutex<Option<u32>>>,
+        running: Arc<Mutex<Option<gst::Element>>>,
+        previewing: Arc<Mutex<bool>>,
     ) {
-        let pipeline_configs = vec![
-            Self::build_standard_pipeline(&config, control_index),
-            Self::build_virtual_camera_pipeline(&config, control_index),
-            Self::build_scaled_pipeline(&config, control_index),
-        ];
+        if let Err(e) = init_gstreamer() {
+            println!("Camera slot {} - {}", control_index + 1, e);
+            return;
+        }
+        let pipeline_configs: Vec<String> = match sources::capture_chains(&config.source, control_index) {
+            Ok(chains) => chains
+                .iter()
+                .map(|source| {
+                    Self::build_pipeline_description(
+                        &config,
+                        control_index,
+                        source,
+                        config.source.is_passthrough(),
+                    )
+                })
+                .collect(),
+            Err(e) => {
+                println!("Camera slot {} - {}", control_index + 1, e);
+                Vec::new()
+            }
+        };
 
         let mut pipeline_started = false;
+        let delivery = match config.transport {
+            Transport::Srt(_) => format!("over {}", config.transport),
+            _ => format!(
+                "with {} FEC{} over {}",
+                config.fec,
+                if config.retransmission { " and retransmission" } else { "" },
+                config.transport
+            ),
+        };
 
-        for (config_idx, cmd) in pipeline_configs.iter().enumerate() {
+        // A video file loops inside its pipeline until stopped.
+        let looping = matches!(config.source, SourceKind::File(_));
+        for (config_idx, description) in pipeline_configs.iter().enumerate() {
             println!(
-                "Trying pipeline configuration {} for camera {}",
+                "Trying pipeline configuration {} for {} {}",
                 config_idx + 1,
-                config.camera_index
+                config.source,
+                delivery
             );
 
-            if let Ok(mut child) = Command::new("cmd").args(&["/C", cmd]).spawn() {
-                let cmd_pid = child.id();
-
-                std::thread::sleep(std::time::Duration::from_millis(2000));
-
-                match child.try_wait() {
-                    Ok(Some(exit_status)) => {
-                        println!(
-                            "Pipeline configuration {} exited early with status: {:?}",
-                            config_idx + 1,
-                            exit_status
-                        );
-                        continue;
-                    }
-                    Ok(None) => {
-                        println!(
-                            "Pipeline configuration {} started successfully!",
-                            config_idx + 1
-                        );
-                        pipeline_started = true;
+            let pipeline = match Self::launch_chain(description, looping) {
+                Ok(pipeline) => pipeline,
+                Err(e) => {
+                    println!("Pipeline configuration {} failed: {}", config_idx + 1, e);
+                    continue;
+                }
+            };
+            println!(
+                "Pipeline configuration {} started successfully!",
+                config_idx + 1
+            );
+            pipeline_started = true;
 
-                        Self::find_gstreamer_pid(cmd_pid, &gst_pid);
+            *running.lock().unwrap() = Some(pipeline.clone());
+            Self::set_preview_valve(&pipeline, *previewing.lock().unwrap());
+            let mut ended = Self::monitor_pipeline(&pipeline, &streaming);
+            let _ = pipeline.set_state(gst::State::Null);
 
-                        Self::monitor_pipeline(child, &streaming);
-                        break;
-                    }
-                    Err(e) => {
-                        println!("Error checking pipeline status: {}", e);
-                        continue;
-                    }
+            // A network camera is reconnected to, and a video file that
+            // could not loop in place is played again from the start.
+            while ended && Self::restarts_on_exit(&config.source) {
+                if let SourceKind::Network { .. } = config.source {
+                    thread::sleep(Duration::from_millis(1000));
                 }
-            } else {
-                println!("Failed to start pipeline configuration {}", config_idx + 1);
+                let pipeline = match Self::launch_chain(description, looping) {
+                    Ok(pipeline) => pipeline,
+                    Err(_) => break,
+                };
+                println!("Camera slot {} - Restarting {}", control_index + 1, config.source);
+                *running.lock().unwrap() = Some(pipeline.clone());
+                Self::set_preview_valve(&pipeline, *previewing.lock().unwrap());
+                ended = Self::monitor_pipeline(&pipeline, &streaming);
+                let _ = pipeline.set_state(gst::State::Null);
             }
+            break;
         }
 
         if !pipeline_started {
             println!(
-                "All pipeline configurations failed for camera {}",
-                config.camera_index
+                "All pipeline configurations failed for {}",
+                config.source
             );
         }
 
-        *gst_pid.lock().unwrap() = None;
+        *running.lock().unwrap() = None;
     }
 
-    fn build_standard_pipeline(config: &StreamConfig, control_index: usize) -> String {
-        format!(
-            "gst-launch-1.0 \
-rtpbin name=rtp latency=150 \
-fec-encoders=\"fec,0=\\\"raptorqenc\\ mtu\\=1356\\ symbol-size\\=1344\\ \
-protected-packets\\=10\\ repair-packets\\=1000\\ repair-window\\=200\\\";\" \
-ksvideosrc device-index={} ! \
-videoconvert ! videorate ! video/x-raw,framerate=15/1,width=640,height=480 ! \
-x264enc key-int-max=45 tune=zerolatency speed-preset=veryfast bitrate=2000 ! \
-queue max-size-buffers=0 max-size-time=0 max-size-bytes=0 ! mpegtsmux ! rtpmp2tpay ssrc={} ! \
-rtp.send_rtp_sink_0 rtp.send_rtp_src_0 !
-udpsink host={} port={} sync=false \
-rtp.send_fec_src_0_0 ! udpsink host={} port={} async=false sync=false",
-            config.camera_index, control_index, config.ip, config.port, config.ip, config.fec_port
-        )
+    /// Starts a pipeline and gives it `STARTUP_TIME` to fail or end. A
+    /// `looping` pipeline plays its file over and over.
+    fn launch_chain(description: &str, looping: bool) -> Result<gst::Element, String> {
+        let pipeline = gst::parse_launch(description).map_err(|e| e.to_string())?;
+        let failure = match pipeline.set_state(gst::State::Playing) {
+            Ok(_) => {
+                if looping {
+                    Self::start_loop(&pipeline);
+                }
+                Self::startup_failure(&pipeline)
+            }
+            Err(e) => Some(e.to_string()),
+        };
+        match failure {
+            Some(e) => {
+                let _ = pipeline.set_state(gst::State::Null);
+                Err(e)
+            }
+            None => Ok(pipeline),
+        }
     }
 
-    fn build_virtual_camera_pipeline(config: &StreamConfig, control_index: usize) -> String {
-        format!(
-            "gst-launch-1.0 \
-rtpbin name=rtp latency=150 \
-fec-encoders=\"fec,0=\\\"raptorqenc\\ mtu\\=1356\\ symbol-size\\=1344\\ \
-protected-packets\\=10\\ repair-packets\\=1000\\ repair-window\\=200\\\";\" \
-ksvideosrc device-index={} ! video/x-raw,width=640,height=480,framerate=30/1 ! \
-videoconvert ! videorate ! video/x-raw,framerate=15/1 ! \
-x264enc key-int-max=45 tune=zerolatency speed-preset=veryfast bitrate=2000 ! \
-queue max-size-buffers=0 max-size-time=0 max-size-bytes=0 ! mpegtsmux ! rtpmp2tpay ssrc={} ! \
-rtp.send_rtp_sink_0 rtp.send_rtp_src_0 ! udpsink host={} port={} sync=false \
-rtp.send_fec_src_0_0 ! udpsink host={} port={} async=false sync=false",
-            config.camera_index, control_index, config.ip, config.port, config.ip, config.fec_port
-        )
+    fn startup_failure(pipeline: &gst::Element) -> Option<String> {
+        let bus = pipeline.bus()?;
+        let started = Instant::now();
+        while let Some(remaining) = STARTUP_TIME.checked_sub(started.elapsed()) {
+            let timeout = gst::ClockTime::from_nseconds(remaining.as_nanos() as u64);
+            let message = bus.timed_pop(timeout)?;
+            Self::loop_file(pipeline, &message);
+            match message.view() {
+                gst::MessageView::Eos(_) => return Some("exited early".to_string()),
+                gst::MessageView::Error(err) => return Some(err.error().to_string()),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Once the pipeline has started, seeks it back to the start of its file
+    /// with a segment seek, so the end of the file posts SEGMENT_DONE for
+    /// `loop_file` instead of EOS.
+    fn start_loop(pipeline: &gst::Element) {
+        let _ = pipeline.state(gst::ClockTime::from_seconds(5));
+        let flags = gst::SeekFlags::FLUSH | gst::SeekFlags::SEGMENT;
+        if let Err(e) = pipeline.seek_simple(flags, gst::ClockTime::ZERO) {
+            println!("Warning: file cannot loop in place and restarts at its end: {}", e);
+        }
+    }
+
+    /// Seeks a looping file back to its start each time it reaches the end.
+    /// The seek does not flush, so the stream's timestamps carry on across
+    /// the loop and receivers see one continuous stream.
+    fn loop_file(pipeline: &gst::Element, message: &gst::Message) {
+        let gst::MessageView::SegmentDone(_) = message.view() else {
+            return;
+        };
+        if let Err(e) = pipeline.seek_simple(gst::SeekFlags::SEGMENT, gst::ClockTime::ZERO) {
+            println!("Warning: file cannot loop: {}", e);
+        }
+    }
+
+    fn restarts_on_exit(source: &SourceKind) -> bool {
+        matches!(source, SourceKind::File(_) | SourceKind::Network { .. })
     }
 
-    fn build_scaled_pipeline(config: &StreamConfig, control_index: usize) -> String {
+    /// `source` ends in raw video, or in parsed H.264 when `encoded`. Encoded
+    /// video is payloaded as it comes and has no `preview` tee.
+    fn build_pipeline_description(
+        config: &StreamConfig,
+        control_index: usize,
+        source: &str,
+        encoded: bool,
+    ) -> String {
+        let multicast = Self::multicast_properties(config);
+        let srtp_rtp = Self::srtp_encoder(config, "srtp_rtp");
+        let srtp_fec = Self::srtp_encoder(config, "srtp_fec");
+        let (relay_rtp, relay_fec) = match config.relay_ports {
+            Some((rtp_port, fec_port)) => (
+                format!(
+                    " rtp_out. ! queue ! udpsink host=127.0.0.1 port={} sync=false",
+                    rtp_port
+                ),
+                format!(
+                    " fec_out. ! queue ! udpsink host=127.0.0.1 port={} async=false sync=false",
+                    fec_port
+                ),
+            ),
+            None => (String::new(), String::new()),
+        };
+        // Both streams share the pipeline clock, so the muxer's timestamps
+        // keep them in sync on the receiver.
+        let audio_branch = match config.audio {
+            Some(audio) if config.payload == PayloadFormat::MpegTs => format!(
+                " {} ! audioconvert ! audioresample ! audio/x-raw,rate=48000,channels=2 ! \
+queue ! {} ! mux.",
+                audio.source.element(),
+                audio.codec.encoder()
+            ),
+            _ => String::new(),
+        };
+        // With the TCP fallback, receivers UDP does not reach can connect
+        // for the same packets.
+        let tcp_fallback = matches!(config.transport, Transport::UdpWithTcpFallback(_));
+        let (fallback_rtp, fallback_fec) = if tcp_fallback {
+            (
+                format!(
+                    " rtp_out. ! queue ! {} sync=false",
+                    Self::tcp_sink(config, TcpMode::Listen, &config.port)
+                ),
+                format!(
+                    " fec_out. ! queue ! {} async=false sync=false",
+                    Self::tcp_sink(config, TcpMode::Listen, &config.fec_port)
+                ),
+            )
+        } else {
+            (String::new(), String::new())
+        };
+        // ULPFEC travels inside the RTP stream, so only RaptorQ has a flow
+        // for the FEC port.
+        let fec_branch = if config.fec.has_repair_flow() {
+            format!(
+                " rtp.send_fec_src_0_0 ! {}tee name=fec_out ! queue ! \
+{} async=false sync=false{}{}",
+                srtp_fec,
+                Self::flow_sink(config, &config.fec_port, &multicast),
+                relay_fec,
+                fallback_fec
+            )
+        } else {
+            String::new()
+        };
+        // rtpbin's session answers NACKs by asking upstream for the packets,
+        // which rtprtxsend keeps and resends with the RTX payload type. The
+        // RTCP flows carry the reports both ways, with the main receiver
+        // only: fan-out destinations get no RTCP and cannot ask for packets,
+        // just copies of those resent for the main receiver.
+        let (profile, rtx_sender, rtcp_branch) = match config.rtcp_ports() {
+            Ok((rtcp_port, feedback_port)) if config.retransmission => (
+                " rtp-profile=avpf",
+                format!(
+                    "rtprtxsend payload-type-map=\"application/x-rtp-pt-map, {}=(uint){}\" \
+max-size-time=1000 ! ",
+                    config.payload.payload_type(),
+                    RTX_PAYLOAD_TYPE
+                ),
+                format!(
+                    " rtp.send_rtcp_src_0 ! udpsink host=\"{}\" port={}{} async=false sync=false \
+udpsrc port={} ! rtp.recv_rtcp_sink_0",
+                    config.ip, rtcp_port, multicast, feedback_port
+                ),
+            ),
+            _ => ("", String::new(), String::new()),
+        };
+        let (video, preview_branch) = if encoded {
+            (source.to_string(), String::new())
+        } else {
+            (
+                format!(
+                    "{} ! tee name=preview ! queue ! {}",
+                    source,
+                    config.payload.encoder()
+                ),
+                format!(
+                    " preview. ! valve name={} drop=true ! {}",
+                    PREVIEW_VALVE_NAME,
+                    preview::preview_sink(control_index)
+                ),
+            )
+        };
+
+        // SRT takes the MPEG-TS as it is, in packets of 7 TS packets, and
+        // needs none of the RTP elements.
+        if let Transport::Srt(srt) = &config.transport {
+            let port = config.port.trim().parse().unwrap_or_default();
+            return format!(
+                "{} ! queue max-size-buffers=0 max-size-time=0 max-size-bytes=0 ! \
+mpegtsmux name=mux alignment=7 ! srtsink {} wait-for-connection=false sync=false{}{}",
+                video,
+                srt.properties(&config.ip, "", port),
+                audio_branch,
+                preview_branch
+            );
+        }
+
         format!(
-            "gst-launch-1.0 \
-rtpbin name=rtp latency=150 \
-fec-encoders=\"fec,0=\\\"raptorqenc\\ mtu\\=1356\\ symbol-size\\=1344\\ \
-protected-packets\\=10\\ repair-packets\\=1000\\ repair-window\\=200\\\";\" \
-ksvideosrc device-index={} ! video/x-raw,width=1280,height=720,framerate=30/1 ! \
-videoconvert ! videoscale ! video/x-raw,width=640,height=480 ! videorate ! video/x-raw,framerate=15/1 ! \
-x264enc key-int-max=45 tune=zerolatency speed-preset=veryfast bitrate=2000 ! \
-queue max-size-buffers=0 max-size-time=0 max-size-bytes=0 ! mpegtsmux ! rtpmp2tpay ssrc={} ! \
-rtp.send_rtp_sink_0 rtp.send_rtp_src_0 ! udpsink host={} port={} sync=false \
-rtp.send_fec_src_0_0 ! udpsink host={} port={} async=false sync=false",
-            config.camera_index, control_index, config.ip, config.port, config.ip, config.fec_port
+            "rtpbin name=rtp latency=150{} fec-encoders=\"fec,0=\\\"{}\\\";\" \
+{} ! \
+queue max-size-buffers=0 max-size-time=0 max-size-bytes=0 ! {} ssrc={} ! {}\
+rtp.send_rtp_sink_0 rtp.send_rtp_src_0 ! {}tee name=rtp_out ! queue ! \
+{} sync=false{}{}{}{}{}{}",
+            profile,
+            Self::escape_fec_encoder(&config.fec.encoder()),
+            video,
+            config.payload.payloader(),
+            control_index,
+            rtx_sender,
+            srtp_rtp,
+            Self::flow_sink(config, &config.port, &multicast),
+            relay_rtp,
+            fallback_rtp,
+            fec_branch,
+            rtcp_branch,
+            audio_branch,
+            preview_branch
         )
     }
 
-    fn find_gstreamer_pid(cmd_pid: u32, gst_pid: &Arc<Mutex<Option<u32>>>) {
-        if let Ok(output) = Command::new("wmic")
-            .args(&[
-                "process",
-                "where",
-                &format!("ParentProcessId={}", cmd_pid),
-                "get",
-                "ProcessId",
-                "/format:csv",
-            ])
-            .output()
-        {
-            let output_str = String::from_utf8_lossy(&output.stdout);
-            for line in output_str.lines() {
-                if line.contains("ProcessId") && !line.contains("Node") {
-                    if let Some(pid_str) = line.split(',').nth(1) {
-                        if let Ok(pid) = pid_str.trim().parse::<u32>() {
-                            *gst_pid.lock().unwrap() = Some(pid);
-                            break;
-                        }
-                    }
-                }
-            }
+    /// Sink sending the RTP or FEC flow to the receiver on `port`.
+    fn flow_sink(config: &StreamConfig, port: &str, multicast: &str) -> String {
+        match config.transport {
+            Transport::Tcp(mode) => Self::tcp_sink(config, mode, port),
+            _ => format!("udpsink host=\"{}\" port={}{}", config.ip, port, multicast),
         }
     }
 
-    fn monitor_pipeline(mut child: std::process::Child, streaming: &Arc<Mutex<bool>>) {
-        loop {
-            if !*streaming.lock().unwrap() {
-                let _ = child.kill();
-                let _ = child.wait();
-                break;
+    /// Sink carrying a flow over TCP with each packet prefixed by its length
+    /// (RFC 4571), connecting to the receiver or waiting for it on any
+    /// address of the receiver's IP version.
+    fn tcp_sink(config: &StreamConfig, mode: TcpMode, port: &str) -> String {
+        match mode {
+            TcpMode::Connect => format!(
+                "rtpstreampay ! tcpclientsink host=\"{}\" port={}",
+                config.ip, port
+            ),
+            TcpMode::Listen => {
+                let any = match parse_ip_address(&config.ip) {
+                    Ok(ip) if ip.is_ipv6() => "::",
+                    _ => "0.0.0.0",
+                };
+                format!("rtpstreampay ! tcpserversink host=\"{}\" port={}", any, port)
             }
-            match child.try_wait() {
-                Ok(Some(_)) => {
-                    println!("Pipeline exited unexpectedly");
-                    break;
+        }
+    }
+
+    /// Escapes an element description for the `fec-encoders` structure,
+    /// where it is a quoted field value inside a quoted property.
+    fn escape_fec_encoder(description: &str) -> String {
+        description.replace(' ', "\\ ").replace('=', "\\=")
+    }
+
+    /// Extra `udpsink` properties used when the destination is a multicast group.
+    fn multicast_properties(config: &StreamConfig) -> String {
+        let is_multicast = parse_ip_address(&config.ip)
+            .map(|addr| addr.is_multicast())
+            .unwrap_or(false);
+
+        if !is_multicast {
+            return String::new();
+        }
+
+        let mut properties = format!(" auto-multicast=true ttl-mc={}", config.multicast_ttl);
+        if !config.multicast_iface.is_empty() {
+            properties.push_str(&format!(" multicast-iface=\"{}\"", config.multicast_iface));
+        }
+        properties
+    }
+
+    /// An `srtpenc` stage (ending in ` ! `) protecting one flow, or nothing when
+    /// SRTP is off. Placed before the tee so fan-out copies are protected too.
+    fn srtp_encoder(config: &StreamConfig, name: &str) -> String {
+        match &config.srtp {
+            Some(srtp) => format!(
+                "{name}.rtp_sink_0 srtpenc name={name} key={}{} \
+rtp-cipher={cipher} rtp-auth={auth} rtcp-cipher={cipher} rtcp-auth={auth} {name}.rtp_src_0 ! ",
+                srtp.key,
+                srtp.mki.map(|mki| format!(" mki={:08X}", mki)).unwrap_or_default(),
+                name = name,
+                cipher = srtp.cipher.name(),
+                auth = srtp.cipher.auth()
+            ),
+            None => String::new(),
+        }
+    }
+
+    /// Waits until the pipeline ends or fails, or the slot stops streaming.
+    /// Returns true if the pipeline stopped on its own while still streaming.
+    fn monitor_pipeline(pipeline: &gst::Element, streaming: &Arc<Mutex<bool>>) -> bool {
+        let bus = match pipeline.bus() {
+            Some(bus) => bus,
+            None => return false,
+        };
+        while *streaming.lock().unwrap() {
+            let message = match bus.timed_pop(gst::ClockTime::from_mseconds(100)) {
+                Some(message) => message,
+                None => continue,
+            };
+            Self::loop_file(pipeline, &message);
+            match message.view() {
+                gst::MessageView::Eos(_) => {
+                    println!("Pipeline ended unexpectedly");
+                    return true;
                 }
-                Ok(None) => std::thread::sleep(std::time::Duration::from_millis(100)),
-                Err(_) => break,
+                gst::MessageView::Error(err) => {
+                    println!("Pipeline error: {}", err.error());
+                    return true;
+                }
+                _ => {}
             }
         }
+        false
     }
 
     pub fn stop_pipeline(&mut self, control_index: usize) {
+        // The slot's thread shuts its pipeline down.
         *self.streaming[control_index].lock().unwrap() = false;
 
-        if let Some(gst_process_pid) = *self.gst_pids[control_index].lock().unwrap() {
-            let _ = Command::new("taskkill")
-                .args(&["/F", "/PID", &gst_process_pid.to_string()])
-                .output();
-        } else {
-            let _ = Command::new("taskkill")
-                .args(&["/F", "/IM", "gst-launch-1.0.exe"])
-                .spawn();
-        }
-
         if let Some(handle) = self.stream_threads[control_index].take() {
-            std::thread::sleep(std::time::Duration::from_millis(500));
             let _ = handle.join();
         }
+
+        self.end_stream(control_index);
+    }
+
+    /// Stops a streaming camera and starts it again with the settings it runs
+    /// with.
+    pub fn restart_pipeline(&mut self, control_index: usize) {
+        if let Some(config) = self.current_config(control_index) {
+            self.stop_pipeline(control_index);
+            self.start_pipeline(control_index, config);
+        }
+    }
+
+    /// Switches the `srtpenc`s of a streaming slot to another key in place,
+    /// so the stream carries on without a gap. Receivers holding both keys
+    /// tell them apart by the MKI.
+    pub fn set_srtp_key(&mut self, control_index: usize, srtp: SrtpConfig) -> Result<(), String> {
+        let pipeline = self.pipelines[control_index]
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or("Camera slot has no running pipeline")?;
+        let encoders: Vec<gst::Element> = SRTP_ENCODER_NAMES
+            .iter()
+            .filter_map(|name| pipeline.downcast_ref::<gst::Bin>()?.by_name(name))
+            .collect();
+        if encoders.is_empty() {
+            return Err("Pipeline has no SRTP encoder".to_string());
+        }
+        let lacks_mki = encoders.iter().any(|encoder| encoder.find_property("mki").is_none());
+        if srtp.mki.is_some() && lacks_mki {
+            return Err("This GStreamer's srtpenc cannot change its MKI".to_string());
+        }
+
+        let key = gst::Buffer::from_mut_slice(hex_bytes(&srtp.key)?);
+        let mki = srtp.mki.map(|mki| gst::Buffer::from_slice(mki.to_be_bytes()));
+        for encoder in &encoders {
+            // Each change makes the encoder set up its SRTP session again
+            // from the next packet on.
+            if let Some(mki) = &mki {
+                encoder.set_property("mki", mki);
+            }
+            encoder.set_property("key", &key);
+        }
+
+        if let Some(config) = &mut self.configs[control_index] {
+            config.srtp = Some(srtp);
+        }
+        Ok(())
+    }
+
+    /// The configuration a streaming camera was started with.
+    pub fn current_config(&self, control_index: usize) -> Option<StreamConfig> {
+        self.configs[control_index].clone()
+    }
+
+    /// Replaces the extra destinations of a camera. A camera streaming through
+    /// its relay sends to the new list immediately. Returns true if the camera
+    /// streams over RTP without a relay, as it does when it started with no
+    /// extra destinations; it only reaches them once restarted.
+    pub fn set_destinations(
+        &mut self,
+        control_index: usize,
+        destinations: Vec<Destination>,
+    ) -> bool {
+        let needs_relay = !destinations.is_empty()
+            && self.relays[control_index].is_none()
+            && self.configs[control_index]
+                .as_ref()
+                .is_some_and(|config| config.transport.is_rtp());
+        *self.destinations[control_index].lock().unwrap() = destinations;
+        needs_relay
     }
 
     pub fn is_streaming(&self, control_index: usize) -> bool {
         *self.streaming[control_index].lock().unwrap()
     }
+
+    /// Opens the preview window of a camera row. A streaming row feeds it from
+    /// its `preview` tee; otherwise a capture-only pipeline of `source` does
+    /// until the row starts streaming.
+    pub fn open_preview(
+        &mut self,
+        control_index: usize,
+        source: Option<SourceKind>,
+    ) -> Result<(), String> {
+        if self.is_preview_open(control_index) {
+            return Ok(());
+        }
+
+        let feed_source = if self.is_streaming(control_index) {
+            if self.configs[control_index]
+                .as_ref()
+                .is_some_and(|config| config.source.is_passthrough())
+            {
+                return Err("A passed-through camera has no preview while streaming".to_string());
+            }
+            None
+        } else {
+            let source = source.ok_or("Please select a camera first")?;
+            sources::check_source(&source)?;
+            Some(source)
+        };
+
+        self.previews[control_index] = Some(PreviewWindow::start(control_index)?);
+        if let Some(source) = feed_source {
+            self.start_preview_feed(control_index, &source);
+        }
+        self.set_previewing(control_index, true);
+        Ok(())
+    }
+
+    /// Keeps an open preview fed after its row stops streaming.
+    pub fn feed_preview(&mut self, control_index: usize, source: &SourceKind) {
+        if self.is_preview_open(control_index) && !self.is_streaming(control_index) {
+            self.start_preview_feed(control_index, source);
+        }
+    }
+
+    pub fn close_preview(&mut self, control_index: usize) {
+        if let Some(mut window) = self.previews[control_index].take() {
+            window.stop();
+        }
+        self.stop_preview_feed(control_index);
+        self.set_previewing(control_index, false);
+    }
+
+    pub fn is_preview_open(&mut self, control_index: usize) -> bool {
+        match self.previews[control_index].as_mut() {
+            Some(window) => window.is_running(),
+            None => false,
+        }
+    }
+
+    /// True once if the user closed a preview window themselves; its feed is
+    /// stopped.
+    pub fn preview_closed(&mut self, control_index: usize) -> bool {
+        if self.previews[control_index].is_some() && !self.is_preview_open(control_index) {
+            self.close_preview(control_index);
+            return true;
+        }
+        false
+    }
+
+    /// Opens or closes the preview valve of the slot's streaming pipeline, now
+    /// and whenever the slot's pipeline is started again.
+    fn set_previewing(&mut self, control_index: usize, open: bool) {
+        *self.previewing[control_index].lock().unwrap() = open;
+        if let Some(pipeline) = self.pipelines[control_index].lock().unwrap().as_ref() {
+            Self::set_preview_valve(pipeline, open);
+        }
+    }
+
+    /// Lets frames through to the preview branch only while `open`, so a
+    /// pipeline without a preview window does not scale and encode them.
+    fn set_preview_valve(pipeline: &gst::Element, open: bool) {
+        let valve = pipeline
+            .downcast_ref::<gst::Bin>()
+            .and_then(|bin| bin.by_name(PREVIEW_VALVE_NAME));
+        if let Some(valve) = valve {
+            valve.set_property("drop", !open);
+        }
+    }
+
+    fn start_preview_feed(&mut self, control_index: usize, source: &SourceKind) {
+        self.stop_preview_feed(control_index);
+
+        let commands = match sources::preview_chains(source, control_index) {
+            Ok(chains) => chains
+                .iter()
+                .map(|chain| {
+                    format!(
+                        "gst-launch-1.0 {} ! {}",
+                        chain,
+                        preview::preview_sink(control_index)
+                    )
+                })
+                .collect(),
+            Err(e) => {
+                println!("Camera slot {} - Preview unavailable: {}", control_index + 1, e);
+                return;
+            }
+        };
+
+        self.preview_feeds[control_index] = Some(PreviewFeed::start(control_index, commands));
+    }
+
+    fn stop_preview_feed(&mut self, control_index: usize) {
+        if let Some(mut feed) = self.preview_feeds[control_index].take() {
+            feed.stop();
+        }
+    }
+}
+
+/// Initializes GStreamer for in-process pipelines, once.
+fn init_gstreamer() -> Result<(), String> {
+    static INIT: OnceLock<Result<(), String>> = OnceLock::new();
+
+    INIT.get_or_init(|| gst::init().map_err(|e| format!("Failed to initialize GStreamer: {}", e)))
+        .clone()
+}
+
+/// Bytes of a key written as hex.
+fn hex_bytes(hex: &str) -> Result<Vec<u8>, String> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .filter(|pair| pair.len() == 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| "SRTP key must be an even number of hex digits".to_string())
+        })
+        .collect()
 }
@@ -0,0 +1,134 @@
+use crate::types::SrtpCipher;
+pub use common::keyfile::KeyEntry;
+use common::keyfile::{read_key_file, write_key_file};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How long each key stays active before the next one takes over.
+pub const ROTATION_INTERVAL_SECS: u64 = 24 * 60 * 60;
+/// Number of future keys kept scheduled, and therefore exported in bundles.
+pub const KEYS_AHEAD: usize = 7;
+/// How long a superseded key stays in bundles so packets in flight still decrypt.
+pub const KEY_OVERLAP_SECS: u64 = 60;
+
+/// File-backed store of per-camera keys. One key per line:
+/// `<camera> <key id> <cipher> <activation unix time> <hex key>`.
+pub struct KeyStore {
+    path: PathBuf,
+    entries: Vec<KeyEntry>,
+}
+
+impl KeyStore {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let entries = if path.exists() {
+            read_key_file(path)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        write_key_file(&self.path, "sender key store", self.entries.iter())
+    }
+
+    /// The key currently protecting `camera`: the latest one already activated.
+    pub fn active_key(&self, camera: usize, cipher: SrtpCipher, now: u64) -> Option<&KeyEntry> {
+        self.entries
+            .iter()
+            .filter(|e| e.camera == camera && e.cipher == cipher && e.activates_at <= now)
+            .max_by_key(|e| e.activates_at)
+    }
+
+    /// Makes sure `camera` has an active key and `KEYS_AHEAD` future keys, one
+    /// per rotation interval, and forgets keys whose overlap has passed or that
+    /// belong to a cipher the camera no longer uses. Returns true if the store
+    /// changed and should be saved and re-exported.
+    pub fn ensure_schedule(&mut self, camera: usize, cipher: SrtpCipher, now: u64) -> Result<bool, String> {
+        let before = self.entries.len();
+        self.entries.retain(|e| e.camera != camera || e.cipher == cipher);
+        let mut changed = self.entries.len() != before;
+
+        if self.active_key(camera, cipher, now).is_none() {
+            self.generate_key(camera, cipher, now)?;
+            changed = true;
+        }
+
+        let mut activations: Vec<u64> = self
+            .entries
+            .iter()
+            .filter(|e| e.camera == camera)
+            .map(|e| e.activates_at)
+            .collect();
+        activations.sort_unstable();
+
+        let mut scheduled = activations.iter().filter(|&&t| t > now).count();
+        let mut last = activations.last().copied().unwrap_or(now);
+        while scheduled < KEYS_AHEAD {
+            last += ROTATION_INTERVAL_SECS;
+            self.generate_key(camera, cipher, last)?;
+            activations.push(last);
+            scheduled += 1;
+            changed = true;
+        }
+
+        let before = self.entries.len();
+        self.entries.retain(|e| {
+            if e.camera != camera {
+                return true;
+            }
+            match activations.iter().find(|&&t| t > e.activates_at) {
+                Some(&superseded_at) => superseded_at + KEY_OVERLAP_SECS > now,
+                None => true,
+            }
+        });
+
+        Ok(changed || self.entries.len() != before)
+    }
+
+    fn generate_key(&mut self, camera: usize, cipher: SrtpCipher, activates_at: u64) -> Result<(), String> {
+        let mut bytes = vec![0u8; cipher.key_len()];
+        getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate key: {}", e))?;
+
+        let last_key_id = self
+            .entries
+            .iter()
+            .filter(|e| e.camera == camera)
+            .map(|e| e.key_id)
+            .max();
+        let key_id = match last_key_id {
+            Some(last) => last
+                .checked_add(1)
+                .ok_or_else(|| format!("Camera slot {} has no SRTP key IDs left", camera))?,
+            None => 1,
+        };
+
+        self.entries.push(KeyEntry {
+            camera,
+            key_id,
+            cipher,
+            activates_at,
+            key: bytes.iter().map(|b| format!("{:02X}", b)).collect(),
+        });
+
+        Ok(())
+    }
+
+    /// Writes every key a receiver needs (current, overlapping and scheduled)
+    /// to a bundle file that can be carried to receivers out of band.
+    pub fn export_bundle(&self, path: &Path) -> Result<usize, String> {
+        write_key_file(path, "key bundle for receivers", self.entries.iter())?;
+        Ok(self.entries.len())
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
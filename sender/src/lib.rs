@@ -0,0 +1,15 @@
+//! The sender without its GUI, for embedding in other Rust programs.
+//!
+//! [`gstreamer::GStreamerManager`] streams capture devices as the sender
+//! application does. [`gstreamer::GStreamerManager::start_frame_source`]
+//! streams raw frames pushed from Rust code instead, through the same
+//! encoding, RaptorQ FEC and RTP path.
+
+pub mod backend;
+pub mod frame_source;
+pub mod gstreamer;
+pub mod keystore;
+pub mod preview;
+pub mod relay;
+pub mod sources;
+pub mod types;
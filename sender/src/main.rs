@@ -2,14 +2,30 @@ use native_windows_gui as nwg;
 use std::cell::RefCell;
 use std::rc::Rc;
 
-mod backend;
-mod gstreamer;
-mod types;
+mod cli;
 mod ui;
 
+use sender::gstreamer::GStreamerManager;
 use ui::SenderApp;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match cli::parse_args(&args) {
+        Ok(Some(options)) => {
+            let mut manager = GStreamerManager::new();
+            cli::run(&mut manager, options);
+            return;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{}\n", e);
+            }
+            eprintln!("{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    }
+
     nwg::init().expect("Failed to init Native Windows GUI");
 
     let mut app = SenderApp::new();
@@ -28,12 +44,46 @@ fn main() {
                 return;
             }
 
+            if handle == app_ref.export_keys_button.handle {
+                drop(app_ref);
+                handler_app.borrow_mut().export_key_bundle();
+                return;
+            }
+
             for (i, controls) in app_ref.camera_controls.iter().enumerate() {
                 if handle == controls.start_button.handle {
                     drop(app_ref);
                     handler_app.borrow_mut().toggle_pipeline(i);
                     return;
                 }
+                if handle == controls.apply_button.handle {
+                    drop(app_ref);
+                    handler_app.borrow_mut().apply_destinations(i);
+                    return;
+                }
+                if handle == controls.preview_button.handle {
+                    drop(app_ref);
+                    handler_app.borrow_mut().toggle_preview(i);
+                    return;
+                }
+            }
+        }
+        nwg::Event::OnComboxBoxSelection => {
+            let app_ref = handler_app.borrow();
+            for (i, controls) in app_ref.camera_controls.iter().enumerate() {
+                if handle == controls.camera_dropdown.handle {
+                    drop(app_ref);
+                    handler_app.borrow_mut().handle_source_selection(i);
+                    return;
+                }
+            }
+        }
+        nwg::Event::OnTimerTick => {
+            let app_ref = handler_app.borrow();
+            if handle == app_ref.rotation_timer.handle {
+                drop(app_ref);
+                handler_app.borrow_mut().check_key_rotation();
+                handler_app.borrow_mut().check_previews();
             }
         }
         nwg::Event::OnWindowClose => {
@@ -43,6 +93,7 @@ fn main() {
                     *app_ref.streaming[i].lock().unwrap() = false;
                 }
             }
+            handler_app.borrow_mut().close_previews();
             nwg::stop_thread_dispatch();
         }
         _ => {}
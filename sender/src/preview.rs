@@ -0,0 +1,125 @@
+use common::process::{kill_tree, shell};
+use std::process::{Child, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Loopback port the preview of camera row N is sent to is this plus N-1.
+pub const PREVIEW_BASE_PORT: u16 = 5900;
+
+pub fn preview_port(control_index: usize) -> u16 {
+    PREVIEW_BASE_PORT + control_index as u16
+}
+
+/// Pipeline tail for the `preview` tee: a small JPEG stream over loopback.
+/// The leaky queue drops frames rather than ever holding up the encoder.
+pub fn preview_sink(control_index: usize) -> String {
+    format!(
+        "queue leaky=downstream max-size-buffers=1 ! videoscale ! video/x-raw,width=320,height=240 ! \
+jpegenc quality=70 ! rtpjpegpay ! udpsink host=127.0.0.1 port={} sync=false async=false",
+        preview_port(control_index)
+    )
+}
+
+/// Small window showing a camera row's preview stream.
+pub struct PreviewWindow {
+    child: Child,
+}
+
+impl PreviewWindow {
+    pub fn start(control_index: usize) -> Result<Self, String> {
+        let cmd = format!(
+            "gst-launch-1.0 udpsrc address=127.0.0.1 port={} \
+caps=\"application/x-rtp, media=video, clock-rate=90000, encoding-name=JPEG, payload=26\" ! \
+rtpjpegdepay ! jpegdec ! videoconvert ! autovideosink sync=false",
+            preview_port(control_index)
+        );
+
+        let child = shell(&cmd)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to open preview: {}", e))?;
+
+        Ok(Self { child })
+    }
+
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    pub fn stop(&mut self) {
+        if self.is_running() {
+            kill_tree(self.child.id());
+        }
+        let _ = self.child.wait();
+    }
+}
+
+/// Capture-only pipeline feeding the preview while a camera is not streaming.
+/// Tries each command in turn, like the streaming pipeline's fallback.
+pub struct PreviewFeed {
+    running: Arc<Mutex<bool>>,
+    cmd_pid: Arc<Mutex<Option<u32>>>,
+    thread_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl PreviewFeed {
+    pub fn start(control_index: usize, commands: Vec<String>) -> Self {
+        let running = Arc::new(Mutex::new(true));
+        let cmd_pid = Arc::new(Mutex::new(None));
+
+        let running_clone = Arc::clone(&running);
+        let cmd_pid_clone = Arc::clone(&cmd_pid);
+        let thread_handle = thread::spawn(move || {
+            for (config_idx, cmd) in commands.iter().enumerate() {
+                if !*running_clone.lock().unwrap() {
+                    break;
+                }
+
+                let mut child = match shell(cmd).spawn() {
+                    Ok(child) => child,
+                    Err(_) => continue,
+                };
+                *cmd_pid_clone.lock().unwrap() = Some(child.id());
+
+                thread::sleep(std::time::Duration::from_millis(2000));
+                if let Ok(Some(_)) = child.try_wait() {
+                    println!(
+                        "Preview configuration {} for camera slot {} exited early",
+                        config_idx + 1,
+                        control_index + 1
+                    );
+                    continue;
+                }
+
+                while *running_clone.lock().unwrap() {
+                    match child.try_wait() {
+                        Ok(None) => thread::sleep(std::time::Duration::from_millis(100)),
+                        _ => break,
+                    }
+                }
+                let _ = child.wait();
+                break;
+            }
+            *cmd_pid_clone.lock().unwrap() = None;
+        });
+
+        Self {
+            running,
+            cmd_pid,
+            thread_handle: Some(thread_handle),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        *self.running.lock().unwrap() = false;
+
+        if let Some(pid) = *self.cmd_pid.lock().unwrap() {
+            kill_tree(pid);
+        }
+
+        if let Some(handle) = self.thread_handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
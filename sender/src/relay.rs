@@ -0,0 +1,100 @@
+use crate::types::Destination;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Copies the packets a pipeline sends to two loopback ports out to every
+/// destination in a shared list, so destinations can be added or removed while
+/// the camera keeps streaming.
+pub struct UdpRelay {
+    pub rtp_port: u16,
+    pub fec_port: u16,
+    running: Arc<Mutex<bool>>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+#[derive(Clone, Copy)]
+enum Flow {
+    Rtp,
+    Fec,
+}
+
+impl UdpRelay {
+    pub fn start(destinations: Arc<Mutex<Vec<Destination>>>) -> io::Result<Self> {
+        let rtp_socket = UdpSocket::bind("127.0.0.1:0")?;
+        let fec_socket = UdpSocket::bind("127.0.0.1:0")?;
+        let rtp_port = rtp_socket.local_addr()?.port();
+        let fec_port = fec_socket.local_addr()?.port();
+
+        let running = Arc::new(Mutex::new(true));
+        let threads = vec![
+            Self::spawn_flow(rtp_socket, Flow::Rtp, Arc::clone(&destinations), Arc::clone(&running))?,
+            Self::spawn_flow(fec_socket, Flow::Fec, destinations, Arc::clone(&running))?,
+        ];
+
+        Ok(Self {
+            rtp_port,
+            fec_port,
+            running,
+            threads,
+        })
+    }
+
+    fn spawn_flow(
+        socket: UdpSocket,
+        flow: Flow,
+        destinations: Arc<Mutex<Vec<Destination>>>,
+        running: Arc<Mutex<bool>>,
+    ) -> io::Result<thread::JoinHandle<()>> {
+        socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+        let out_v4 = UdpSocket::bind("0.0.0.0:0")?;
+        let out_v6 = UdpSocket::bind("[::]:0").ok();
+
+        Ok(thread::spawn(move || {
+            let mut buffer = [0u8; 65536];
+
+            while *running.lock().unwrap() {
+                let len = match socket.recv(&mut buffer) {
+                    Ok(len) => len,
+                    Err(ref e)
+                        if e.kind() == io::ErrorKind::WouldBlock
+                            || e.kind() == io::ErrorKind::TimedOut =>
+                    {
+                        continue;
+                    }
+                    Err(e) => {
+                        println!("Relay receive error: {}", e);
+                        continue;
+                    }
+                };
+
+                for destination in destinations.lock().unwrap().iter() {
+                    let port = match flow {
+                        Flow::Rtp => destination.port,
+                        Flow::Fec => destination.fec_port,
+                    };
+                    let target = SocketAddr::new(destination.ip, port);
+                    let out = if destination.ip.is_ipv4() {
+                        Some(&out_v4)
+                    } else {
+                        out_v6.as_ref()
+                    };
+
+                    if let Some(out) = out {
+                        let _ = out.send_to(&buffer[..len], target);
+                    }
+                }
+            }
+        }))
+    }
+
+    pub fn stop(&mut self) {
+        *self.running.lock().unwrap() = false;
+
+        for handle in self.threads.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use crate::types::{ScreenRegion, SourceKind};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Scales and paces raw video to the stream's 640x480 at 15 fps.
+const SCALE_TO_STREAM: &str = "videoconvert ! videoscale ! video/x-raw,width=640,height=480 ! videorate ! video/x-raw,framerate=15/1";
+
+/// An image type a slideshow can show.
+#[derive(Clone, Copy, PartialEq)]
+struct SlideType {
+    extension: &'static str,
+    caps: &'static str,
+    decoder: &'static str,
+}
+
+const JPEG: SlideType = SlideType {
+    extension: "jpg",
+    caps: "image/jpeg",
+    decoder: "jpegdec",
+};
+
+const PNG: SlideType = SlideType {
+    extension: "png",
+    caps: "image/png",
+    decoder: "pngdec",
+};
+
+/// Capture chains for a source, tried in order, each ending in 640x480 at
+/// 15 fps.
+pub fn capture_chains(source: &SourceKind, control_index: usize) -> Result<Vec<String>, String> {
+    match source {
+        SourceKind::Device(camera_index) => Ok(device_chains(*camera_index)),
+        SourceKind::File(path) => {
+            check_source(source)?;
+            Ok(vec![format!(
+                "filesrc location=\"{}\" ! decodebin ! videoconvert ! videoscale ! \
+video/x-raw,width=640,height=480 ! videorate ! video/x-raw,framerate=15/1 ! identity sync=true",
+                gst_path(Path::new(path))
+            )])
+        }
+        SourceKind::TestPattern(pattern) => Ok(vec![format!(
+            "videotestsrc is-live=true pattern={} ! video/x-raw,width=640,height=480,framerate=15/1 ! \
+clockoverlay halignment=left valignment=top ! timeoverlay halignment=right valignment=top ! videoconvert",
+            pattern.name()
+        )]),
+        SourceKind::Slideshow {
+            directory,
+            seconds_per_image,
+        } => {
+            let (location, slide_type) = stage_slides(Path::new(directory), control_index)?;
+            Ok(vec![format!(
+                "multifilesrc location=\"{}\" loop=true caps=\"{},framerate=1/{}\" ! {} ! \
+videoconvert ! videoscale ! video/x-raw,width=640,height=480 ! videorate ! video/x-raw,framerate=15/1 ! \
+identity sync=true",
+                location, slide_type.caps, seconds_per_image, slide_type.decoder
+            )])
+        }
+        SourceKind::Screen { monitor, region } => Ok(screen_chains(*monitor, *region)),
+        SourceKind::Window(title) => window_chains(title),
+        SourceKind::Network { url, passthrough } => Ok(network_chains(url, *passthrough)),
+    }
+}
+
+/// Capture chains for feeding a preview, which always needs raw video: a
+/// passed-through camera is decoded for it.
+pub fn preview_chains(source: &SourceKind, control_index: usize) -> Result<Vec<String>, String> {
+    match source {
+        SourceKind::Network {
+            url,
+            passthrough: true,
+        } => Ok(network_chains(url, false)),
+        _ => capture_chains(source, control_index),
+    }
+}
+
+/// Checks that a file or slideshow source points at something playable.
+pub fn check_source(source: &SourceKind) -> Result<(), String> {
+    match source {
+        SourceKind::File(path) => {
+            if Path::new(path).is_file() {
+                Ok(())
+            } else {
+                Err(format!("Video file '{}' not found", path))
+            }
+        }
+        SourceKind::Slideshow { directory, .. } => {
+            slideshow_images(Path::new(directory)).map(|_| ())
+        }
+        SourceKind::Device(_)
+        | SourceKind::TestPattern(_)
+        | SourceKind::Screen { .. }
+        | SourceKind::Window(_)
+        | SourceKind::Network { .. } => Ok(()),
+    }
+}
+
+fn device_chains(camera_index: usize) -> Vec<String> {
+    vec![
+        format!(
+            "ksvideosrc device-index={} ! \
+videoconvert ! videorate ! video/x-raw,framerate=15/1,width=640,height=480",
+            camera_index
+        ),
+        // Virtual cameras
+        format!(
+            "ksvideosrc device-index={} ! video/x-raw,width=640,height=480,framerate=30/1 ! \
+videoconvert ! videorate ! video/x-raw,framerate=15/1",
+            camera_index
+        ),
+        // Cameras that only offer 720p
+        format!(
+            "ksvideosrc device-index={} ! video/x-raw,width=1280,height=720,framerate=30/1 ! \
+videoconvert ! videoscale ! video/x-raw,width=640,height=480 ! videorate ! video/x-raw,framerate=15/1",
+            camera_index
+        ),
+    ]
+}
+
+/// Desktop Duplication first, then GDI on Windows; X11, then PipeWire
+/// elsewhere. On X11 `monitor` is the X screen, and PipeWire captures what
+/// the desktop portal asks the user to share, so it is only tried without a
+/// region.
+fn screen_chains(monitor: usize, region: Option<ScreenRegion>) -> Vec<String> {
+    if cfg!(windows) {
+        let (crop, gdi_region) = match region {
+            Some(r) => (
+                format!(
+                    " crop-x={} crop-y={} crop-width={} crop-height={}",
+                    r.x, r.y, r.width, r.height
+                ),
+                format!(" x={} y={} width={} height={}", r.x, r.y, r.width, r.height),
+            ),
+            None => (String::new(), String::new()),
+        };
+        vec![
+            format!(
+                "d3d11screencapturesrc monitor-index={} show-cursor=true{} ! d3d11download ! {}",
+                monitor, crop, SCALE_TO_STREAM
+            ),
+            format!(
+                "gdiscreencapsrc monitor={} cursor=true{} ! {}",
+                monitor, gdi_region, SCALE_TO_STREAM
+            ),
+        ]
+    } else {
+        let area = match region {
+            Some(r) => format!(
+                " startx={} starty={} endx={} endy={}",
+                r.x,
+                r.y,
+                r.x + r.width - 1,
+                r.y + r.height - 1
+            ),
+            None => String::new(),
+        };
+        let mut chains = vec![format!(
+            "ximagesrc screen-num={} use-damage=false show-pointer=true{} ! {}",
+            monitor, area, SCALE_TO_STREAM
+        )];
+        if region.is_none() {
+            chains.push(format!(
+                "pipewiresrc do-timestamp=true ! {}",
+                SCALE_TO_STREAM
+            ));
+        }
+        chains
+    }
+}
+
+/// On Windows the first window whose title contains `title` is captured
+/// through Windows Graphics Capture; X11 needs the exact title.
+fn window_chains(title: &str) -> Result<Vec<String>, String> {
+    if cfg!(windows) {
+        let handle = find_window_handle(title)?;
+        Ok(vec![format!(
+            "d3d11screencapturesrc capture-api=wgc window-handle={} show-cursor=true ! d3d11download ! {}",
+            handle, SCALE_TO_STREAM
+        )])
+    } else {
+        Ok(vec![format!(
+            "ximagesrc xname=\"{}\" use-damage=false show-pointer=true ! {}",
+            title.replace('"', "\\\""),
+            SCALE_TO_STREAM
+        )])
+    }
+}
+
+fn find_window_handle(title: &str) -> Result<u64, String> {
+    let script = format!(
+        "Get-Process | Where-Object {{ $_.MainWindowTitle.Contains('{}') }} | \
+Select-Object -First 1 -ExpandProperty MainWindowHandle",
+        title.replace('\'', "''")
+    );
+    let output = Command::new("powershell")
+        .args(["-Command", &script])
+        .output()
+        .map_err(|e| format!("Cannot look up windows: {}", e))?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|&handle| handle != 0)
+        .ok_or_else(|| format!("No window with '{}' in its title", title))
+}
+
+/// `uridecodebin` first, then an explicit chain: RTSP over TCP for networks
+/// that drop RTP over UDP, or `multipartdemux` for an MJPEG stream. Passed
+/// through, an RTSP camera's H.264 comes out parsed instead of raw video.
+fn network_chains(url: &str, passthrough: bool) -> Vec<String> {
+    let url = url.replace('"', "%22");
+    let is_rtsp = url.starts_with("rtsp");
+
+    if passthrough {
+        return ["", " protocols=tcp"]
+            .iter()
+            .map(|protocols| {
+                format!(
+                    "rtspsrc location=\"{}\" latency=200{} ! rtph264depay ! h264parse config-interval=-1",
+                    url, protocols
+                )
+            })
+            .collect();
+    }
+
+    let fallback = if is_rtsp {
+        format!(
+            "rtspsrc location=\"{}\" latency=200 protocols=tcp ! decodebin ! {}",
+            url, SCALE_TO_STREAM
+        )
+    } else {
+        format!(
+            "souphttpsrc location=\"{}\" is-live=true do-timestamp=true ! multipartdemux ! jpegdec ! {}",
+            url, SCALE_TO_STREAM
+        )
+    };
+
+    vec![
+        format!("uridecodebin uri=\"{}\" ! {}", url, SCALE_TO_STREAM),
+        fallback,
+    ]
+}
+
+/// Images of a slideshow directory in name order. Only those of the same
+/// type as the first are used, since one decoder reads them all.
+fn slideshow_images(directory: &Path) -> Result<(Vec<PathBuf>, SlideType), String> {
+    let entries = fs::read_dir(directory).map_err(|e| {
+        format!(
+            "Cannot read slideshow directory '{}': {}",
+            directory.display(),
+            e
+        )
+    })?;
+
+    let mut images: Vec<(PathBuf, SlideType)> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter_map(|path| slide_type(&path).map(|slide_type| (path, slide_type)))
+        .collect();
+    images.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let slide_type = match images.first() {
+        Some((_, slide_type)) => *slide_type,
+        None => {
+            return Err(format!(
+                "No JPEG or PNG images in slideshow directory '{}'",
+                directory.display()
+            ));
+        }
+    };
+
+    Ok((
+        images
+            .into_iter()
+            .filter(|(_, t)| *t == slide_type)
+            .map(|(path, _)| path)
+            .collect(),
+        slide_type,
+    ))
+}
+
+fn slide_type(path: &Path) -> Option<SlideType> {
+    match path.extension()?.to_str()?.to_lowercase().as_str() {
+        "jpg" | "jpeg" => Some(JPEG),
+        "png" => Some(PNG),
+        _ => None,
+    }
+}
+
+/// Copies the slideshow's images to numbered files in a per-slot temporary
+/// directory, since `multifilesrc` reads a numbered sequence. Returns the
+/// `multifilesrc` location pattern and the type of the images.
+fn stage_slides(directory: &Path, control_index: usize) -> Result<(String, SlideType), String> {
+    let (images, slide_type) = slideshow_images(directory)?;
+
+    let staging = std::env::temp_dir().join(format!("sender_slideshow_{}", control_index + 1));
+    let _ = fs::remove_dir_all(&staging);
+    fs::create_dir_all(&staging).map_err(|e| format!("Cannot prepare slideshow: {}", e))?;
+
+    for (number, image) in images.iter().enumerate() {
+        fs::copy(
+            image,
+            staging.join(format!("{:05}.{}", number, slide_type.extension)),
+        )
+        .map_err(|e| format!("Cannot copy '{}': {}", image.display(), e))?;
+    }
+
+    Ok((
+        gst_path(&staging.join(format!("%05d.{}", slide_type.extension))),
+        slide_type,
+    ))
+}
+
+/// A path as written in a pipeline description, where backslashes would be
+/// taken as escapes.
+fn gst_path(path: &Path) -> String {
+    path.display().to_string().replace('\\', "/")
+}
@@ -1,13 +1,640 @@
+pub use common::parse_ip_address;
+pub use common::srtp::{SrtpCipher, parse_srtp_key};
+pub use common::stream::{
+    DEFAULT_SRT_LATENCY_MS, DEFAULT_TCP_FALLBACK, DEFAULT_ULPFEC_PERCENTAGE,
+    DEFAULT_ULPFEC_PERCENTAGE_IMPORTANT, FecScheme, PayloadFormat, RED_PAYLOAD_TYPE,
+    RTX_PAYLOAD_TYPE, SrtConfig, SrtMode, TcpMode, Transport, ULPFEC_PAYLOAD_TYPE,
+};
+use std::net::IpAddr;
+
 #[derive(Clone)]
 pub struct CameraInfo {
     pub index: usize,
     pub name: String,
     pub device_path: String,
+    /// What selecting this entry streams: the capture device, or a screen.
+    pub source: SourceKind,
+}
+
+/// Time each slideshow image is shown when none is given.
+pub const DEFAULT_SLIDE_SECONDS: u32 = 5;
+
+/// What a camera slot streams.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SourceKind {
+    /// A capture device, by `ksvideosrc` device index.
+    Device(usize),
+    /// A video file, played from the start again whenever it ends.
+    File(String),
+    /// A `videotestsrc` pattern with the wall clock and stream time drawn on it.
+    TestPattern(TestPattern),
+    /// The JPEG or PNG images of a directory in name order, each shown for
+    /// `seconds_per_image`, repeating.
+    Slideshow {
+        directory: String,
+        seconds_per_image: u32,
+    },
+    /// A monitor, or a region of it.
+    Screen {
+        monitor: usize,
+        region: Option<ScreenRegion>,
+    },
+    /// The window whose title contains this text (the exact title on X11).
+    Window(String),
+    /// An RTSP or HTTP MJPEG camera. With `passthrough` an RTSP camera's
+    /// H.264 is sent on without decoding and re-encoding it.
+    Network { url: String, passthrough: bool },
+}
+
+impl SourceKind {
+    /// Parses a source written as `device:<index>`, `pattern:<name>`,
+    /// `file:<path>`, `slideshow:<directory>[:<seconds per image>]`,
+    /// `screen:<monitor>[@<x>,<y>,<width>x<height>]`, `window:<title>`, an
+    /// `rtsp://` or `http://` URL, or `copy:<rtsp url>` for pass-through.
+    pub fn parse(text: &str) -> Result<SourceKind, String> {
+        let (kind, value) = text
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| format!("'{}' is not of the form <kind>:<value>", text.trim()))?;
+
+        match kind {
+            "device" => value
+                .trim()
+                .parse()
+                .map(SourceKind::Device)
+                .map_err(|_| format!("'{}' is not a device index", value)),
+            "pattern" => TestPattern::from_name(value.trim())
+                .map(SourceKind::TestPattern)
+                .ok_or_else(|| format!("Unknown test pattern '{}'", value)),
+            "file" => Ok(SourceKind::File(value.to_string())),
+            "slideshow" => {
+                // A trailing `:<number>` is the time per image; anything else
+                // (e.g. a drive letter) is part of the directory.
+                let (directory, seconds_per_image) = match value
+                    .rsplit_once(':')
+                    .map(|(directory, seconds)| (directory, seconds.parse::<u32>()))
+                {
+                    Some((directory, Ok(seconds))) => (directory, seconds),
+                    _ => (value, DEFAULT_SLIDE_SECONDS),
+                };
+                if seconds_per_image == 0 {
+                    return Err("Slideshow images need at least one second each".to_string());
+                }
+                Ok(SourceKind::Slideshow {
+                    directory: directory.to_string(),
+                    seconds_per_image,
+                })
+            }
+            "screen" => {
+                let (monitor, region) = match value.split_once('@') {
+                    Some((monitor, region)) => (monitor, Some(ScreenRegion::parse(region)?)),
+                    None => (value, None),
+                };
+                let monitor = monitor
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("'{}' is not a monitor number", monitor))?;
+                Ok(SourceKind::Screen { monitor, region })
+            }
+            "window" if !value.trim().is_empty() => {
+                Ok(SourceKind::Window(value.trim().to_string()))
+            }
+            "window" => Err("Window capture needs part of the window title".to_string()),
+            "rtsp" | "rtsps" | "http" | "https" => Ok(SourceKind::Network {
+                url: text.trim().to_string(),
+                passthrough: false,
+            }),
+            "copy"
+                if value.trim().starts_with("rtsp://") || value.trim().starts_with("rtsps://") =>
+            {
+                Ok(SourceKind::Network {
+                    url: value.trim().to_string(),
+                    passthrough: true,
+                })
+            }
+            "copy" => Err("Only RTSP cameras can be passed through".to_string()),
+            other => Err(format!(
+                "Unknown source kind '{}'; use device, pattern, file, slideshow, screen, window or a URL",
+                other
+            )),
+        }
+    }
+
+    /// True if the source already delivers H.264 for the stream.
+    pub fn is_passthrough(&self) -> bool {
+        matches!(
+            self,
+            SourceKind::Network {
+                passthrough: true,
+                ..
+            }
+        )
+    }
+}
+
+impl std::fmt::Display for SourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceKind::Device(index) => write!(f, "camera {}", index),
+            SourceKind::File(path) => write!(f, "file {}", path),
+            SourceKind::TestPattern(pattern) => write!(f, "{} test pattern", pattern.label()),
+            SourceKind::Slideshow { directory, .. } => write!(f, "slideshow {}", directory),
+            SourceKind::Screen {
+                monitor,
+                region: None,
+            } => write!(f, "screen {}", monitor),
+            SourceKind::Screen {
+                monitor,
+                region: Some(region),
+            } => write!(f, "screen {} region {}", monitor, region),
+            SourceKind::Window(title) => write!(f, "window '{}'", title),
+            SourceKind::Network { url, passthrough } => write!(
+                f,
+                "{}{}",
+                redact_credentials(url),
+                if *passthrough { " (pass-through)" } else { "" }
+            ),
+        }
+    }
+}
+
+/// A URL without the `user:password@` part, for printing.
+pub fn redact_credentials(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let (authority, path) = match rest.find('/') {
+                Some(slash) => rest.split_at(slash),
+                None => (rest, ""),
+            };
+            match authority.rsplit_once('@') {
+                Some((_, host)) => format!("{}://***@{}{}", scheme, host, path),
+                None => url.to_string(),
+            }
+        }
+        None => url.to_string(),
+    }
+}
+
+/// Part of a monitor to capture, in pixels from its top-left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScreenRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScreenRegion {
+    /// Parses a region written as `<x>,<y>,<width>x<height>`, e.g.
+    /// `0,0,1280x720`.
+    pub fn parse(text: &str) -> Result<ScreenRegion, String> {
+        let invalid = || {
+            format!(
+                "'{}' is not a region of the form x,y,WIDTHxHEIGHT",
+                text.trim()
+            )
+        };
+
+        let mut parts = text.trim().splitn(3, ',');
+        let (x, y, size) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(x), Some(y), Some(size)) => (x, y, size),
+            _ => return Err(invalid()),
+        };
+        let (width, height) = size.split_once(['x', 'X']).ok_or_else(invalid)?;
+
+        let number = |value: &str| value.trim().parse::<u32>().map_err(|_| invalid());
+        let region = ScreenRegion {
+            x: number(x)?,
+            y: number(y)?,
+            width: number(width)?,
+            height: number(height)?,
+        };
+        if region.width == 0 || region.height == 0 {
+            return Err("A screen region needs a width and height".to_string());
+        }
+        // Capture sources take the region's far edges as coordinates too.
+        if region.x.checked_add(region.width).is_none()
+            || region.y.checked_add(region.height).is_none()
+        {
+            return Err(format!(
+                "Screen region '{}' reaches past the largest coordinate",
+                text.trim()
+            ));
+        }
+        Ok(region)
+    }
 }
 
+impl std::fmt::Display for ScreenRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{}x{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TestPattern {
+    Smpte,
+    Ball,
+    /// Random noise; hard to compress, so it shows the stream at its worst.
+    Snow,
+}
+
+impl TestPattern {
+    pub const ALL: [TestPattern; 3] = [TestPattern::Smpte, TestPattern::Ball, TestPattern::Snow];
+
+    /// Pattern name as understood by `videotestsrc`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TestPattern::Smpte => "smpte",
+            TestPattern::Ball => "ball",
+            TestPattern::Snow => "snow",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TestPattern::Smpte => "SMPTE bars",
+            TestPattern::Ball => "Moving ball",
+            TestPattern::Snow => "Snow",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<TestPattern> {
+        TestPattern::ALL.iter().copied().find(|p| p.name() == name)
+    }
+}
+
+#[derive(Clone)]
 pub struct StreamConfig {
-    pub camera_index: usize,
+    pub source: SourceKind,
     pub ip: String,
     pub port: String,
     pub fec_port: String,
+    /// Hop limit applied when `ip` is a multicast group.
+    pub multicast_ttl: u32,
+    /// Outgoing interface for multicast traffic; empty lets the OS choose.
+    pub multicast_iface: String,
+    /// Loopback RTP/FEC ports of the fan-out relay, filled in by `GStreamerManager`.
+    pub relay_ports: Option<(u16, u16)>,
+    /// SRTP protection for both the RTP and FEC flows; `None` sends in the clear.
+    pub srtp: Option<SrtpConfig>,
+    /// Audio muxed into the MPEG-TS next to the video; `None` sends video only.
+    pub audio: Option<AudioConfig>,
+    /// How the video is carried in RTP.
+    pub payload: PayloadFormat,
+    /// Forward error correction protecting the RTP stream.
+    pub fec: FecScheme,
+    /// Resend packets receivers report lost (NACK) on top of RaptorQ.
+    pub retransmission: bool,
+    /// How the stream gets to the receiver. Over TCP the RTP and FEC flows
+    /// use the same port numbers as over UDP. Over SRT the MPEG-TS is sent
+    /// to `ip`:`port` without RTP, FEC or extra destinations.
+    pub transport: Transport,
+}
+
+impl StreamConfig {
+    /// Checks that the source, audio, payload format and transport fit
+    /// together.
+    pub fn check(&self) -> Result<(), String> {
+        if self.audio.is_some() && self.payload != PayloadFormat::MpegTs {
+            return Err(format!(
+                "Audio is carried in the MPEG-TS; it cannot be sent with {} payloading",
+                self.payload.label()
+            ));
+        }
+        if self.source.is_passthrough() && !self.payload.is_h264() {
+            return Err(format!(
+                "A passed-through camera sends H.264 and cannot be sent as {}",
+                self.payload.label()
+            ));
+        }
+        if let Transport::Srt(_) = self.transport {
+            if self.payload != PayloadFormat::MpegTs {
+                return Err("SRT carries the MPEG-TS; choose MPEG-TS payloading".to_string());
+            }
+            if self.srtp.is_some() {
+                return Err("SRT encrypts with its passphrase; turn SRTP off".to_string());
+            }
+            if self.retransmission {
+                return Err("SRT retransmits lost packets itself".to_string());
+            }
+            self.port
+                .trim()
+                .parse::<u16>()
+                .map_err(|_| format!("'{}' is not a valid SRT port", self.port))?;
+        }
+        if let Transport::Tcp(mode) = self.transport {
+            if self.retransmission {
+                return Err("TCP delivers every packet; retransmission is not needed".to_string());
+            }
+            let is_multicast = parse_ip_address(&self.ip).is_ok_and(|ip| ip.is_multicast());
+            if mode == TcpMode::Connect && is_multicast {
+                return Err("TCP cannot connect to a multicast group".to_string());
+            }
+        }
+        if self.retransmission {
+            if self.fec != FecScheme::RaptorQ {
+                return Err("Retransmission is only combined with RaptorQ FEC".to_string());
+            }
+            if self.srtp.is_some() {
+                return Err(
+                    "Retransmission feedback is sent as plain RTCP and cannot be used with SRTP"
+                        .to_string(),
+                );
+            }
+            self.rtcp_ports()?;
+        }
+        Ok(())
+    }
+
+    /// Ports of the RTCP flows: sender reports go to the receiver on the RTP
+    /// port + 1, and receiver reports with NACKs come back on the FEC port + 1.
+    pub fn rtcp_ports(&self) -> Result<(u16, u16), String> {
+        let next_port = |port: &str| {
+            port.trim()
+                .parse::<u16>()
+                .ok()
+                .and_then(|port| port.checked_add(1))
+                .ok_or_else(|| format!("'{}' is not a port with a free port after it", port))
+        };
+        Ok((next_port(&self.port)?, next_port(&self.fec_port)?))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AudioSource {
+    /// The default recording device.
+    Microphone,
+    /// A quiet 440 Hz sine.
+    TestTone,
+}
+
+impl AudioSource {
+    pub const ALL: [AudioSource; 2] = [AudioSource::Microphone, AudioSource::TestTone];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioSource::Microphone => "mic",
+            AudioSource::TestTone => "tone",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AudioSource::Microphone => "Microphone",
+            AudioSource::TestTone => "Test tone",
+        }
+    }
+
+    /// Pipeline source producing raw audio.
+    pub fn element(&self) -> &'static str {
+        match self {
+            AudioSource::Microphone => "autoaudiosrc",
+            AudioSource::TestTone => "audiotestsrc is-live=true wave=sine freq=440 volume=0.2",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AudioCodec {
+    Opus,
+    Aac,
+}
+
+impl AudioCodec {
+    pub const ALL: [AudioCodec; 2] = [AudioCodec::Opus, AudioCodec::Aac];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioCodec::Opus => "opus",
+            AudioCodec::Aac => "aac",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AudioCodec::Opus => "Opus",
+            AudioCodec::Aac => "AAC",
+        }
+    }
+
+    /// Encoder stage, ending in caps `mpegtsmux` accepts.
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            AudioCodec::Opus => "opusenc bitrate=64000",
+            AudioCodec::Aac => "avenc_aac bitrate=128000 ! aacparse",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioConfig {
+    pub source: AudioSource,
+    pub codec: AudioCodec,
+}
+
+impl AudioConfig {
+    /// Parses audio written as `mic` or `tone`, optionally followed by
+    /// `:opus` or `:aac`. Opus is the default.
+    pub fn parse(text: &str) -> Result<AudioConfig, String> {
+        let (source, codec) = match text.trim().split_once(':') {
+            Some((source, codec)) => (source, codec),
+            None => (text.trim(), AudioCodec::Opus.name()),
+        };
+
+        Ok(AudioConfig {
+            source: AudioSource::ALL
+                .iter()
+                .copied()
+                .find(|s| s.name() == source)
+                .ok_or_else(|| format!("Unknown audio source '{}'; use mic or tone", source))?,
+            codec: AudioCodec::ALL
+                .iter()
+                .copied()
+                .find(|c| c.name() == codec)
+                .ok_or_else(|| format!("Unknown audio codec '{}'; use opus or aac", codec))?,
+        })
+    }
+}
+
+/// An additional receiver that gets a copy of a camera's RTP and FEC packets.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Destination {
+    pub ip: IpAddr,
+    pub port: u16,
+    pub fec_port: u16,
+}
+
+/// Parses a destination list written as `host:port/fec_port` entries separated
+/// by `;` or `,`, e.g. `192.168.0.20:5000/5002; [2001:db8::7]:5000/5002`.
+pub fn parse_destinations(text: &str) -> Result<Vec<Destination>, String> {
+    let mut destinations = Vec::new();
+
+    for entry in text.split([';', ',']) {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let (address, fec_port) = entry
+            .rsplit_once('/')
+            .ok_or_else(|| format!("'{}' is missing the FEC port (host:port/fec_port)", entry))?;
+        let (host, port) = address
+            .rsplit_once(':')
+            .ok_or_else(|| format!("'{}' is missing the RTP port (host:port/fec_port)", entry))?;
+
+        destinations.push(Destination {
+            ip: parse_ip_address(host)?,
+            port: port
+                .trim()
+                .parse()
+                .map_err(|_| format!("'{}' has an invalid RTP port", entry))?,
+            fec_port: fec_port
+                .trim()
+                .parse()
+                .map_err(|_| format!("'{}' has an invalid FEC port", entry))?,
+        });
+    }
+
+    Ok(destinations)
+}
+
+#[derive(Clone)]
+pub struct SrtpConfig {
+    pub cipher: SrtpCipher,
+    /// Master key and salt as uppercase hex.
+    pub key: String,
+    /// Key ID from the key store, sent as the MKI; `None` for a typed-in key.
+    pub mki: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream_config(port: &str, fec_port: &str) -> StreamConfig {
+        StreamConfig {
+            source: SourceKind::Device(0),
+            ip: "127.0.0.1".to_string(),
+            port: port.to_string(),
+            fec_port: fec_port.to_string(),
+            multicast_ttl: 1,
+            multicast_iface: String::new(),
+            relay_ports: None,
+            srtp: None,
+            audio: None,
+            payload: PayloadFormat::MpegTs,
+            fec: FecScheme::RaptorQ,
+            retransmission: false,
+            transport: Transport::Udp,
+        }
+    }
+
+    #[test]
+    fn parses_sources() {
+        assert_eq!(SourceKind::parse("device:2"), Ok(SourceKind::Device(2)));
+        assert_eq!(
+            SourceKind::parse("pattern:ball"),
+            Ok(SourceKind::TestPattern(TestPattern::Ball))
+        );
+        assert_eq!(
+            SourceKind::parse("file:C:\\videos\\a.mp4"),
+            Ok(SourceKind::File("C:\\videos\\a.mp4".to_string()))
+        );
+        assert_eq!(
+            SourceKind::parse("window: Editor "),
+            Ok(SourceKind::Window("Editor".to_string()))
+        );
+        assert_eq!(
+            SourceKind::parse("copy:rtsp://cam/stream"),
+            Ok(SourceKind::Network {
+                url: "rtsp://cam/stream".to_string(),
+                passthrough: true,
+            })
+        );
+        assert!(SourceKind::parse("copy:http://cam/video").is_err());
+        assert!(SourceKind::parse("device:first").is_err());
+        assert!(SourceKind::parse("webcam").is_err());
+    }
+
+    #[test]
+    fn parses_slideshows_with_drive_letters() {
+        let slideshow = |directory: &str, seconds_per_image| SourceKind::Slideshow {
+            directory: directory.to_string(),
+            seconds_per_image,
+        };
+        assert_eq!(
+            SourceKind::parse("slideshow:C:\\pictures:3"),
+            Ok(slideshow("C:\\pictures", 3))
+        );
+        assert_eq!(
+            SourceKind::parse("slideshow:C:\\dir:5"),
+            Ok(slideshow("C:\\dir", 5))
+        );
+        assert_eq!(
+            SourceKind::parse("slideshow:C:\\pictures"),
+            Ok(slideshow("C:\\pictures", DEFAULT_SLIDE_SECONDS))
+        );
+        assert_eq!(
+            SourceKind::parse("slideshow:/home/me/pictures"),
+            Ok(slideshow("/home/me/pictures", DEFAULT_SLIDE_SECONDS))
+        );
+        assert!(SourceKind::parse("slideshow:C:\\pictures:0").is_err());
+    }
+
+    #[test]
+    fn parses_screens() {
+        assert_eq!(
+            SourceKind::parse("screen:1"),
+            Ok(SourceKind::Screen {
+                monitor: 1,
+                region: None
+            })
+        );
+        assert_eq!(
+            SourceKind::parse("screen:0@10,20,640x480"),
+            Ok(SourceKind::Screen {
+                monitor: 0,
+                region: Some(ScreenRegion {
+                    x: 10,
+                    y: 20,
+                    width: 640,
+                    height: 480
+                }),
+            })
+        );
+        assert!(SourceKind::parse("screen:0@10,20").is_err());
+    }
+
+    #[test]
+    fn parses_screen_regions() {
+        assert_eq!(
+            ScreenRegion::parse(" 0, 0, 1280X720 "),
+            Ok(ScreenRegion {
+                x: 0,
+                y: 0,
+                width: 1280,
+                height: 720
+            })
+        );
+        assert!(ScreenRegion::parse("0,0,1280").is_err());
+        assert!(ScreenRegion::parse("0,0,0x720").is_err());
+        assert!(ScreenRegion::parse("-1,0,1280x720").is_err());
+    }
+
+    #[test]
+    fn rejects_screen_regions_past_the_largest_coordinate() {
+        let max = u32::MAX;
+        assert!(ScreenRegion::parse(&format!("{},0,1x1", max)).is_err());
+        assert!(ScreenRegion::parse(&format!("0,1,1x{}", max)).is_err());
+        assert!(ScreenRegion::parse(&format!("{},0,1x1", max - 1)).is_ok());
+    }
+
+    #[test]
+    fn finds_rtcp_ports_after_the_stream_ports() {
+        assert_eq!(stream_config("5000", "5002").rtcp_ports(), Ok((5001, 5003)));
+        assert!(stream_config("65535", "5002").rtcp_ports().is_err());
+        assert!(stream_config("5000", "65535").rtcp_ports().is_err());
+        assert!(stream_config("5000", "fec").rtcp_ports().is_err());
+    }
 }
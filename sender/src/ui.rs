@@ -1,16 +1,44 @@
 
-use crate::backend::CameraBackend;
-use crate::gstreamer::GStreamerManager;
-use crate::types::{CameraInfo, StreamConfig};
+use sender::backend::CameraBackend;
+use sender::gstreamer::GStreamerManager;
+use sender::keystore::{KeyStore, unix_now};
+use sender::sources;
+use sender::types::{
+    AudioCodec, AudioConfig, AudioSource, CameraInfo, DEFAULT_SLIDE_SECONDS,
+    DEFAULT_TCP_FALLBACK, FecScheme, PayloadFormat, ScreenRegion, SourceKind, SrtConfig, SrtMode,
+    SrtpCipher, SrtpConfig, StreamConfig, TcpMode, TestPattern, Transport, parse_destinations,
+    parse_ip_address, parse_srtp_key,
+};
 use native_windows_gui as nwg;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 
+const KEY_STORE_PATH: &str = "sender_keys.txt";
+
 pub struct CameraControls {
     pub camera_dropdown: nwg::ComboBox<String>,
     pub ip_input: nwg::TextInput,
     pub port_input: nwg::TextInput,
     pub fec_port_input: nwg::TextInput,
+    pub ttl_input: nwg::TextInput,
+    pub iface_input: nwg::TextInput,
+    pub destinations_input: nwg::TextInput,
     pub start_button: nwg::Button,
+    pub apply_button: nwg::Button,
+    pub srtp_cipher_dropdown: nwg::ComboBox<String>,
+    pub srtp_key_input: nwg::TextInput,
+    pub preview_button: nwg::Button,
+    pub audio_dropdown: nwg::ComboBox<String>,
+    pub payload_dropdown: nwg::ComboBox<String>,
+    pub fec_dropdown: nwg::ComboBox<String>,
+    /// ULPFEC protection as `percentage[,key frame percentage]`.
+    pub fec_settings_input: nwg::TextInput,
+    pub retransmission_checkbox: nwg::CheckBox,
+    pub transport_dropdown: nwg::ComboBox<String>,
+    /// SRT settings as `latency ms[:passphrase]`.
+    pub srt_settings_input: nwg::TextInput,
+    /// Screen region (`x,y,WIDTHxHEIGHT`), window title or camera URL.
+    pub region_input: nwg::TextInput,
     pub label: nwg::Label,
 }
 
@@ -21,7 +49,22 @@ impl Default for CameraControls {
             ip_input: Default::default(),
             port_input: Default::default(),
             fec_port_input: Default::default(),
+            ttl_input: Default::default(),
+            iface_input: Default::default(),
+            destinations_input: Default::default(),
             start_button: Default::default(),
+            apply_button: Default::default(),
+            srtp_cipher_dropdown: Default::default(),
+            srtp_key_input: Default::default(),
+            preview_button: Default::default(),
+            audio_dropdown: Default::default(),
+            payload_dropdown: Default::default(),
+            fec_dropdown: Default::default(),
+            fec_settings_input: Default::default(),
+            retransmission_checkbox: Default::default(),
+            transport_dropdown: Default::default(),
+            srt_settings_input: Default::default(),
+            region_input: Default::default(),
             label: Default::default(),
         }
     }
@@ -31,13 +74,23 @@ pub struct SenderApp {
     pub window: nwg::Window,
     pub camera_controls: Vec<CameraControls>,
     available_cameras: Vec<CameraInfo>,
+    available_screens: Vec<CameraInfo>,
+    /// Video file and slideshow directory picked for each row, if any.
+    picked_files: Vec<Option<String>>,
+    picked_slideshows: Vec<Option<String>>,
     pub refresh_button: nwg::Button,
+    pub export_keys_button: nwg::Button,
+    pub rotation_timer: nwg::AnimationTimer,
     camera_count_label: nwg::Label,
     ip_label: nwg::Label,
     port_label: nwg::Label,
     fec_label: nwg::Label,
+    ttl_label: nwg::Label,
+    iface_label: nwg::Label,
+    destinations_label: nwg::Label,
     camera_label: nwg::Label,
     gstreamer_manager: GStreamerManager,
+    key_store: Option<KeyStore>,
     pub streaming: Vec<Arc<Mutex<bool>>>,
 }
 
@@ -46,24 +99,41 @@ impl SenderApp {
         let mut gstreamer_manager = GStreamerManager::new();
         gstreamer_manager.initialize_streams(6);
 
+        let key_store = match KeyStore::load(Path::new(KEY_STORE_PATH)) {
+            Ok(key_store) => Some(key_store),
+            Err(e) => {
+                println!("Warning: SRTP key store unavailable: {}", e);
+                None
+            }
+        };
+
         Self {
             window: Default::default(),
             camera_controls: Vec::new(),
             available_cameras: Vec::new(),
+            available_screens: Vec::new(),
+            picked_files: vec![None; 6],
+            picked_slideshows: vec![None; 6],
             refresh_button: Default::default(),
+            export_keys_button: Default::default(),
+            rotation_timer: Default::default(),
             camera_count_label: Default::default(),
             ip_label: Default::default(),
             port_label: Default::default(),
             fec_label: Default::default(),
+            ttl_label: Default::default(),
+            iface_label: Default::default(),
+            destinations_label: Default::default(),
             camera_label: Default::default(),
             streaming: gstreamer_manager.streaming.clone(),
             gstreamer_manager,
+            key_store,
         }
     }
 
     pub fn build_ui(&mut self) -> Result<(), nwg::NwgError> {
         nwg::Window::builder()
-            .size((750, 400))
+            .size((1510, 470))
             .position((300, 300))
             .title("Multi-Camera Sender with Auto-Detection")
             .build(&mut self.window)?;
@@ -82,6 +152,19 @@ impl SenderApp {
             .parent(&self.window)
             .build(&mut self.camera_count_label)?;
 
+        nwg::Button::builder()
+            .text("Export Key Bundle")
+            .position((350, 10))
+            .size((140, 30))
+            .parent(&self.window)
+            .build(&mut self.export_keys_button)?;
+
+        nwg::AnimationTimer::builder()
+            .parent(&self.window)
+            .interval(std::time::Duration::from_secs(10))
+            .build(&mut self.rotation_timer)?;
+        self.rotation_timer.start();
+
         self.build_headers()?;
 
         if let Err(e) = self.detect_cameras() {
@@ -108,28 +191,51 @@ impl SenderApp {
         nwg::Label::builder()
             .text("IP Address")
             .position((140, 50))
-            .size((100, 20))
+            .size((160, 20))
             .parent(&self.window)
             .build(&mut self.ip_label)?;
 
         nwg::Label::builder()
             .text("RTP Port")
-            .position((250, 50))
+            .position((310, 50))
             .size((80, 20))
             .parent(&self.window)
             .build(&mut self.port_label)?;
 
         nwg::Label::builder()
             .text("FEC Port")
-            .position((340, 50))
+            .position((400, 50))
             .size((80, 20))
             .parent(&self.window)
             .build(&mut self.fec_label)?;
 
+        nwg::Label::builder()
+            .text("TTL")
+            .position((490, 50))
+            .size((50, 20))
+            .parent(&self.window)
+            .build(&mut self.ttl_label)?;
+
+        nwg::Label::builder()
+            .text("Mcast Iface")
+            .position((550, 50))
+            .size((90, 20))
+            .parent(&self.window)
+            .build(&mut self.iface_label)?;
+
+        nwg::Label::builder()
+            .text("Extra Destinations (host:port/fec; ...)")
+            .position((740, 50))
+            .size((270, 20))
+            .parent(&self.window)
+            .build(&mut self.destinations_label)?;
+
         Ok(())
     }
 
     fn detect_cameras(&mut self) -> Result<(), String> {
+        self.available_screens = CameraBackend::detect_screens();
+
         match CameraBackend::detect_cameras() {
             Ok(cameras) => {
                 self.available_cameras = cameras;
@@ -145,7 +251,7 @@ impl SenderApp {
 
     fn create_camera_controls(&mut self) -> Result<(), nwg::NwgError> {
         for i in 0..6 {
-            let y_pos = 80 + (i * 40);
+            let y_pos = 80 + (i * 60);
             let mut controls = CameraControls::default();
 
             nwg::ComboBox::builder()
@@ -157,39 +263,155 @@ impl SenderApp {
             nwg::TextInput::builder()
                 .text("192.168.0.101")
                 .position((140, y_pos))
-                .size((100, 25))
+                .size((160, 25))
                 .parent(&self.window)
                 .build(&mut controls.ip_input)?;
 
             nwg::TextInput::builder()
                 .text(&format!("{}", 5000 + i * 4))
-                .position((250, y_pos))
+                .position((310, y_pos))
                 .size((80, 25))
                 .parent(&self.window)
                 .build(&mut controls.port_input)?;
 
             nwg::TextInput::builder()
                 .text(&format!("{}", 5002 + i * 4))
-                .position((340, y_pos))
+                .position((400, y_pos))
                 .size((80, 25))
                 .parent(&self.window)
                 .build(&mut controls.fec_port_input)?;
 
+            nwg::TextInput::builder()
+                .text("16")
+                .position((490, y_pos))
+                .size((50, 25))
+                .parent(&self.window)
+                .build(&mut controls.ttl_input)?;
+
+            nwg::TextInput::builder()
+                .text("")
+                .position((550, y_pos))
+                .size((90, 25))
+                .parent(&self.window)
+                .build(&mut controls.iface_input)?;
+
             nwg::Button::builder()
                 .text("Start")
-                .position((430, y_pos))
+                .position((650, y_pos))
                 .size((80, 25))
                 .parent(&self.window)
                 .enabled(false)
                 .build(&mut controls.start_button)?;
 
+            nwg::TextInput::builder()
+                .text("")
+                .position((740, y_pos))
+                .size((200, 25))
+                .parent(&self.window)
+                .build(&mut controls.destinations_input)?;
+
+            nwg::Button::builder()
+                .text("Apply")
+                .position((950, y_pos))
+                .size((60, 25))
+                .parent(&self.window)
+                .build(&mut controls.apply_button)?;
+
             nwg::Label::builder()
                 .text("")
-                .position((520, y_pos))
+                .position((1020, y_pos))
                 .size((50, 25))
                 .parent(&self.window)
                 .build(&mut controls.label)?;
 
+            nwg::CheckBox::builder()
+                .text("Retransmit")
+                .position((1080, y_pos))
+                .size((100, 25))
+                .parent(&self.window)
+                .build(&mut controls.retransmission_checkbox)?;
+
+            nwg::ComboBox::builder()
+                .position((1190, y_pos))
+                .size((130, 25))
+                .collection(Self::transport_items())
+                .selected_index(Some(0))
+                .parent(&self.window)
+                .build(&mut controls.transport_dropdown)?;
+
+            nwg::TextInput::builder()
+                .text("")
+                .placeholder_text(Some("latency ms[:passphrase]"))
+                .position((1330, y_pos))
+                .size((170, 25))
+                .parent(&self.window)
+                .build(&mut controls.srt_settings_input)?;
+
+            let mut ciphers = vec!["No encryption".to_string()];
+            ciphers.extend(SrtpCipher::ALL.iter().map(|c| format!("SRTP {}", c.name())));
+
+            nwg::ComboBox::builder()
+                .position((140, y_pos + 27))
+                .size((160, 25))
+                .collection(ciphers)
+                .selected_index(Some(0))
+                .parent(&self.window)
+                .build(&mut controls.srtp_cipher_dropdown)?;
+
+            nwg::TextInput::builder()
+                .text("")
+                .position((310, y_pos + 27))
+                .size((420, 25))
+                .parent(&self.window)
+                .build(&mut controls.srtp_key_input)?;
+
+            nwg::TextInput::builder()
+                .text("")
+                .placeholder_text(Some("Region, window or URL"))
+                .position((10, y_pos + 27))
+                .size((120, 25))
+                .parent(&self.window)
+                .build(&mut controls.region_input)?;
+
+            nwg::Button::builder()
+                .text("Preview")
+                .position((740, y_pos + 27))
+                .size((80, 25))
+                .parent(&self.window)
+                .build(&mut controls.preview_button)?;
+
+            nwg::ComboBox::builder()
+                .position((830, y_pos + 27))
+                .size((180, 25))
+                .collection(Self::audio_items())
+                .selected_index(Some(0))
+                .parent(&self.window)
+                .build(&mut controls.audio_dropdown)?;
+
+            nwg::ComboBox::builder()
+                .position((1020, y_pos + 27))
+                .size((100, 25))
+                .collection(PayloadFormat::ALL.iter().map(|p| p.label().to_string()).collect())
+                .selected_index(Some(0))
+                .parent(&self.window)
+                .build(&mut controls.payload_dropdown)?;
+
+            nwg::ComboBox::builder()
+                .position((1130, y_pos + 27))
+                .size((110, 25))
+                .collection(vec!["RaptorQ".to_string(), "ULPFEC + RED".to_string()])
+                .selected_index(Some(0))
+                .parent(&self.window)
+                .build(&mut controls.fec_dropdown)?;
+
+            nwg::TextInput::builder()
+                .text("")
+                .placeholder_text(Some("%[,key %]"))
+                .position((1250, y_pos + 27))
+                .size((70, 25))
+                .parent(&self.window)
+                .build(&mut controls.fec_settings_input)?;
+
             self.camera_controls.push(controls);
         }
 
@@ -202,61 +424,485 @@ impl SenderApp {
             self.available_cameras.len()
         ));
 
-        for controls in self.camera_controls.iter_mut() {
-            let mut items = Vec::new();
+        for control_index in 0..self.camera_controls.len() {
+            let controls = &self.camera_controls[control_index];
+            controls.camera_dropdown.set_collection(self.source_items(control_index));
+            controls.camera_dropdown.set_selection(Some(0));
+            // Test patterns, files and slideshows work without a camera.
+            controls.start_button.set_enabled(true);
+        }
+    }
+
+    /// Dropdown entries of a row: the cameras and screens, then the test
+    /// patterns, a window, a network camera, a video file and an image
+    /// slideshow.
+    fn source_entries(&self, control_index: usize) -> Vec<(String, SourceEntry)> {
+        let mut entries = Vec::new();
 
-            if !self.available_cameras.is_empty() {
-                items.push("Select Camera".to_string());
-                for camera in &self.available_cameras {
-                    items.push(camera.name.clone());
+        if !self.available_cameras.is_empty() {
+            entries.push(("Select Camera".to_string(), SourceEntry::None));
+        } else {
+            entries.push(("No cameras found".to_string(), SourceEntry::None));
+        }
+
+        for info in self.available_cameras.iter().chain(&self.available_screens) {
+            entries.push((info.name.clone(), SourceEntry::Listed(info.source.clone())));
+        }
+        for pattern in TestPattern::ALL {
+            entries.push((
+                format!("Test pattern: {}", pattern.label()),
+                SourceEntry::Listed(SourceKind::TestPattern(pattern)),
+            ));
+        }
+        entries.push(("Window (title below)".to_string(), SourceEntry::Window));
+        entries.push(("Network camera (URL below)".to_string(), SourceEntry::Network));
+        entries.push((
+            match &self.picked_files[control_index] {
+                Some(path) => format!("Video file: {}", file_name(path)),
+                None => "Video file...".to_string(),
+            },
+            SourceEntry::File,
+        ));
+        entries.push((
+            match &self.picked_slideshows[control_index] {
+                Some(directory) => format!("Image slideshow: {}", file_name(directory)),
+                None => "Image slideshow...".to_string(),
+            },
+            SourceEntry::Slideshow,
+        ));
+
+        entries
+    }
+
+    fn source_items(&self, control_index: usize) -> Vec<String> {
+        self.source_entries(control_index)
+            .into_iter()
+            .map(|(label, _)| label)
+            .collect()
+    }
+
+    fn selected_entry(&self, control_index: usize) -> Option<SourceEntry> {
+        let selection = self.camera_controls[control_index]
+            .camera_dropdown
+            .selection()?;
+        self.source_entries(control_index)
+            .into_iter()
+            .nth(selection)
+            .map(|(_, entry)| entry)
+    }
+
+    /// The source a row would stream. A screen takes the region typed below
+    /// the dropdown, if any, a window its title and a network camera its URL.
+    fn get_selected_source(&self, control_index: usize) -> Result<Option<SourceKind>, String> {
+        let region_text = self.camera_controls[control_index].region_input.text();
+        let region_text = region_text.trim();
+
+        Ok(match self.selected_entry(control_index) {
+            Some(SourceEntry::Listed(SourceKind::Screen { monitor, .. })) => {
+                let region = if region_text.is_empty() {
+                    None
+                } else {
+                    Some(ScreenRegion::parse(region_text)?)
+                };
+                Some(SourceKind::Screen { monitor, region })
+            }
+            Some(SourceEntry::Listed(source)) => Some(source),
+            Some(SourceEntry::Window) => {
+                if region_text.is_empty() {
+                    return Err("Type part of the window title below the dropdown".to_string());
+                }
+                Some(SourceKind::Window(region_text.to_string()))
+            }
+            Some(SourceEntry::Network) => match SourceKind::parse(region_text) {
+                Ok(source @ SourceKind::Network { .. }) => Some(source),
+                _ => {
+                    return Err(
+                        "Type an rtsp:// or http:// URL below the dropdown, or copy:rtsp://... \
+to send the camera's H.264 on unchanged"
+                            .to_string(),
+                    );
                 }
-                controls.start_button.set_enabled(true);
-            } else {
-                items.push("No cameras found".to_string());
-                controls.start_button.set_enabled(false);
+            },
+            Some(SourceEntry::File) => {
+                self.picked_files[control_index].clone().map(SourceKind::File)
             }
+            Some(SourceEntry::Slideshow) => self.picked_slideshows[control_index]
+                .clone()
+                .map(|directory| SourceKind::Slideshow {
+                    directory,
+                    seconds_per_image: DEFAULT_SLIDE_SECONDS,
+                }),
+            Some(SourceEntry::None) | None => None,
+        })
+    }
 
-            controls.camera_dropdown.set_collection(items);
-            controls.camera_dropdown.set_selection(Some(0));
+    /// Asks for the file or directory when a row's video file or slideshow
+    /// entry is selected.
+    pub fn handle_source_selection(&mut self, control_index: usize) {
+        let selection = self.camera_controls[control_index].camera_dropdown.selection();
+
+        if let Some(SourceEntry::File) = self.selected_entry(control_index) {
+            if let Some(path) = self.pick_path(
+                "Choose a video file",
+                nwg::FileDialogAction::Open,
+                Some("Video(*.mp4;*.mkv;*.avi;*.mov;*.webm)|Any file(*.*)"),
+            ) {
+                self.picked_files[control_index] = Some(path);
+            }
+        } else if let Some(SourceEntry::Slideshow) = self.selected_entry(control_index) {
+            if let Some(directory) = self.pick_path(
+                "Choose a directory of JPEG or PNG images",
+                nwg::FileDialogAction::OpenDirectory,
+                None,
+            ) {
+                self.picked_slideshows[control_index] = Some(directory);
+            }
+        } else {
+            return;
+        }
+
+        let dropdown = &self.camera_controls[control_index].camera_dropdown;
+        dropdown.set_collection(self.source_items(control_index));
+        // Cancelling without an earlier choice leaves nothing selected.
+        dropdown.set_selection(selection);
+        if let Ok(None) = self.get_selected_source(control_index) {
+            dropdown.set_selection(Some(0));
         }
     }
 
-    fn get_selected_camera_index(&self, control_index: usize) -> Option<usize> {
-        if let Some(selection) = self.camera_controls[control_index]
-            .camera_dropdown
-            .selection()
-        {
-            if selection > 0 && selection <= self.available_cameras.len() {
-                return Some(self.available_cameras[selection - 1].index);
+    fn pick_path(
+        &self,
+        title: &str,
+        action: nwg::FileDialogAction,
+        filters: Option<&str>,
+    ) -> Option<String> {
+        let mut builder = nwg::FileDialog::builder().title(title).action(action);
+        if let Some(filters) = filters {
+            builder = builder.filters(filters);
+        }
+
+        let mut dialog = nwg::FileDialog::default();
+        if builder.build(&mut dialog).is_err() || !dialog.run(Some(&self.window)) {
+            return None;
+        }
+
+        dialog
+            .get_selected_item()
+            .ok()
+            .map(|path| path.to_string_lossy().into_owned())
+    }
+
+    /// "No audio", then every audio source with every codec.
+    fn audio_items() -> Vec<String> {
+        let mut items = vec!["No audio".to_string()];
+        for source in AudioSource::ALL {
+            for codec in AudioCodec::ALL {
+                items.push(format!("{} ({})", source.label(), codec.label()));
             }
         }
-        None
+        items
+    }
+
+    fn get_audio_config(&self, control_index: usize) -> Option<AudioConfig> {
+        let selection = self.camera_controls[control_index]
+            .audio_dropdown
+            .selection()?
+            .checked_sub(1)?;
+        Some(AudioConfig {
+            source: *AudioSource::ALL.get(selection / AudioCodec::ALL.len())?,
+            codec: AudioCodec::ALL[selection % AudioCodec::ALL.len()],
+        })
+    }
+
+    fn get_fec_scheme(&self, control_index: usize) -> Result<FecScheme, String> {
+        let controls = &self.camera_controls[control_index];
+        match controls.fec_dropdown.selection() {
+            Some(1) => FecScheme::ulpfec(&controls.fec_settings_input.text()),
+            _ => Ok(FecScheme::RaptorQ),
+        }
+    }
+
+    /// UDP, UDP with the TCP fallback, then the TCP and SRT modes.
+    fn transport_items() -> Vec<String> {
+        let mut items = vec!["UDP".to_string(), "UDP + TCP fallback".to_string()];
+        items.extend(TcpMode::ALL.iter().map(|mode| format!("TCP {}", mode.name())));
+        items.extend(SrtMode::ALL.iter().map(|mode| format!("SRT {}", mode.name())));
+        items
+    }
+
+    fn get_transport(&self, control_index: usize) -> Result<Transport, String> {
+        let controls = &self.camera_controls[control_index];
+        let selection = controls.transport_dropdown.selection().unwrap_or(0);
+        let srt_selection = selection.checked_sub(2 + TcpMode::ALL.len());
+
+        if let Some(mode) = srt_selection.and_then(|s| SrtMode::ALL.get(s).copied()) {
+            return SrtConfig::with_settings(mode, &controls.srt_settings_input.text())
+                .map(Transport::Srt);
+        }
+        Ok(match selection {
+            1 => Transport::UdpWithTcpFallback(DEFAULT_TCP_FALLBACK),
+            selection => selection
+                .checked_sub(2)
+                .and_then(|s| TcpMode::ALL.get(s).copied())
+                .map_or(Transport::Udp, Transport::Tcp),
+        })
+    }
+
+    /// SRTP settings for a camera row. A typed-in key is used as is; with the
+    /// key field left empty the key comes from the key store instead.
+    fn get_srtp_config(&mut self, control_index: usize) -> Result<Option<SrtpConfig>, String> {
+        let controls = &self.camera_controls[control_index];
+
+        let cipher = match controls.srtp_cipher_dropdown.selection() {
+            Some(selection) if selection > 0 && selection <= SrtpCipher::ALL.len() => {
+                SrtpCipher::ALL[selection - 1]
+            }
+            _ => return Ok(None),
+        };
+
+        let key_text = controls.srtp_key_input.text();
+        if !key_text.trim().is_empty() {
+            let key = parse_srtp_key(cipher, &key_text)?;
+            return Ok(Some(SrtpConfig {
+                cipher,
+                key,
+                mki: None,
+            }));
+        }
+
+        self.key_store_srtp_config(control_index, cipher).map(Some)
+    }
+
+    fn key_store_srtp_config(
+        &mut self,
+        control_index: usize,
+        cipher: SrtpCipher,
+    ) -> Result<SrtpConfig, String> {
+        let key_store = self
+            .key_store
+            .as_mut()
+            .ok_or("SRTP key store could not be loaded; see console output")?;
+        let camera = control_index + 1;
+        let now = unix_now();
+
+        if key_store.ensure_schedule(camera, cipher, now)? {
+            key_store.save()?;
+            println!(
+                "Camera slot {} - SRTP key schedule updated; export a new key bundle for receivers",
+                camera
+            );
+        }
+
+        let entry = key_store
+            .active_key(camera, cipher, now)
+            .ok_or_else(|| format!("No active SRTP key for camera slot {}", camera))?;
+
+        Ok(SrtpConfig {
+            cipher,
+            key: entry.key.clone(),
+            mki: Some(entry.key_id),
+        })
+    }
+
+    /// Moves streaming cameras that use the key store onto their newly active
+    /// key, switching it in the running pipeline. Receivers holding the bundle
+    /// already know the next key by its MKI, so they keep decrypting across
+    /// the switch without being restarted.
+    pub fn check_key_rotation(&mut self) {
+        for control_index in 0..self.camera_controls.len() {
+            if !self.gstreamer_manager.is_streaming(control_index) {
+                continue;
+            }
+
+            let config = match self.gstreamer_manager.current_config(control_index) {
+                Some(config) => config,
+                None => continue,
+            };
+            let current = match &config.srtp {
+                Some(srtp) if srtp.mki.is_some() => srtp.clone(),
+                _ => continue,
+            };
+
+            match self.key_store_srtp_config(control_index, current.cipher) {
+                Ok(next) if next.mki != current.mki => {
+                    println!(
+                        "Camera slot {} - rotating SRTP key {} -> {}",
+                        control_index + 1,
+                        current.mki.unwrap_or_default(),
+                        next.mki.unwrap_or_default()
+                    );
+                    if let Err(e) = self.gstreamer_manager.set_srtp_key(control_index, next) {
+                        println!(
+                            "Warning: SRTP key rotation failed for camera slot {}: {}",
+                            control_index + 1,
+                            e
+                        );
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => println!(
+                    "Warning: SRTP key rotation failed for camera slot {}: {}",
+                    control_index + 1,
+                    e
+                ),
+            }
+        }
+    }
+
+    pub fn export_key_bundle(&mut self) {
+        let key_store = match self.key_store.as_ref() {
+            Some(key_store) => key_store,
+            None => {
+                nwg::simple_message("Error", "SRTP key store could not be loaded; see console output");
+                return;
+            }
+        };
+
+        let mut dialog = nwg::FileDialog::default();
+        if nwg::FileDialog::builder()
+            .title("Export SRTP key bundle")
+            .action(nwg::FileDialogAction::Save)
+            .filters("Key bundle(*.txt)")
+            .build(&mut dialog)
+            .is_err()
+            || !dialog.run(Some(&self.window))
+        {
+            return;
+        }
+
+        let path = match dialog.get_selected_item() {
+            Ok(path) => path,
+            Err(_) => return,
+        };
+
+        match key_store.export_bundle(Path::new(&path)) {
+            Ok(count) => nwg::simple_message(
+                "Key Bundle",
+                &format!(
+                    "Exported {} key(s). Import this file on the receivers before the next rotation.",
+                    count
+                ),
+            ),
+            Err(e) => nwg::simple_message("Error", &e),
+        }
     }
 
     pub fn toggle_pipeline(&mut self, control_index: usize) {
         let is_streaming = self.gstreamer_manager.is_streaming(control_index);
 
         if is_streaming {
+            let source = self
+                .gstreamer_manager
+                .current_config(control_index)
+                .map(|config| config.source);
+
             self.gstreamer_manager.stop_pipeline(control_index);
             self.camera_controls[control_index]
                 .start_button
                 .set_text("Start");
+
+            if let Some(source) = source {
+                self.gstreamer_manager.feed_preview(control_index, &source);
+            }
         } else {
-            let camera_device_index = match self.get_selected_camera_index(control_index) {
-                Some(idx) => idx,
-                None => {
+            let source = match self.get_selected_source(control_index) {
+                Ok(Some(source)) => source,
+                Ok(None) => {
                     nwg::simple_message("Error", "Please select a camera first");
                     return;
                 }
+                Err(e) => {
+                    nwg::simple_message("Error", &e);
+                    return;
+                }
+            };
+
+            if let Err(e) = sources::check_source(&source) {
+                nwg::simple_message("Error", &e);
+                return;
+            }
+
+            let ip = match parse_ip_address(&self.camera_controls[control_index].ip_input.text()) {
+                Ok(addr) => addr.to_string(),
+                Err(e) => {
+                    nwg::simple_message("Error", &e);
+                    return;
+                }
+            };
+
+            let multicast_ttl = match self.camera_controls[control_index]
+                .ttl_input
+                .text()
+                .trim()
+                .parse::<u32>()
+            {
+                Ok(ttl) if (1..=255).contains(&ttl) => ttl,
+                _ => {
+                    nwg::simple_message("Error", "Multicast TTL must be between 1 and 255");
+                    return;
+                }
+            };
+
+            let srtp = match self.get_srtp_config(control_index) {
+                Ok(srtp) => srtp,
+                Err(e) => {
+                    nwg::simple_message("Error", &e);
+                    return;
+                }
+            };
+
+            let fec = match self.get_fec_scheme(control_index) {
+                Ok(fec) => fec,
+                Err(e) => {
+                    nwg::simple_message("Error", &e);
+                    return;
+                }
+            };
+
+            let transport = match self.get_transport(control_index) {
+                Ok(transport) => transport,
+                Err(e) => {
+                    nwg::simple_message("Error", &e);
+                    return;
+                }
             };
 
             let config = StreamConfig {
-                camera_index: camera_device_index,
-                ip: self.camera_controls[control_index].ip_input.text(),
+                source,
+                ip,
                 port: self.camera_controls[control_index].port_input.text(),
                 fec_port: self.camera_controls[control_index].fec_port_input.text(),
+                multicast_ttl,
+                multicast_iface: self.camera_controls[control_index]
+                    .iface_input
+                    .text()
+                    .trim()
+                    .to_string(),
+                relay_ports: None,
+                srtp,
+                audio: self.get_audio_config(control_index),
+                payload: self.camera_controls[control_index]
+                    .payload_dropdown
+                    .selection()
+                    .and_then(|selection| PayloadFormat::ALL.get(selection).copied())
+                    .unwrap_or(PayloadFormat::MpegTs),
+                fec,
+                retransmission: self.camera_controls[control_index]
+                    .retransmission_checkbox
+                    .check_state()
+                    == nwg::CheckBoxState::Checked,
+                transport,
             };
 
+            if let Err(e) = config.check() {
+                nwg::simple_message("Error", &e);
+                return;
+            }
+
+            if !self.apply_destinations(control_index) {
+                return;
+            }
+
             self.gstreamer_manager.start_pipeline(control_index, config);
             self.camera_controls[control_index]
                 .start_button
@@ -264,6 +910,75 @@ impl SenderApp {
         }
     }
 
+    /// Pushes the extra destinations typed for a camera to the fan-out relay.
+    /// A camera that started without extra destinations has no relay yet and
+    /// is restarted with one. Returns false (after telling the user) if the
+    /// list does not parse.
+    pub fn apply_destinations(&mut self, control_index: usize) -> bool {
+        let text = self.camera_controls[control_index].destinations_input.text();
+
+        match parse_destinations(&text) {
+            Ok(destinations) => {
+                let needs_relay = self
+                    .gstreamer_manager
+                    .set_destinations(control_index, destinations);
+                if needs_relay {
+                    self.gstreamer_manager.restart_pipeline(control_index);
+                }
+                true
+            }
+            Err(e) => {
+                nwg::simple_message("Error", &format!("Invalid destinations: {}", e));
+                false
+            }
+        }
+    }
+
+    /// Opens or closes the small preview window of a camera row. Streaming is
+    /// not interrupted either way.
+    pub fn toggle_preview(&mut self, control_index: usize) {
+        if self.gstreamer_manager.is_preview_open(control_index) {
+            self.gstreamer_manager.close_preview(control_index);
+            self.set_preview_controls(control_index, false);
+            return;
+        }
+
+        let result = self
+            .get_selected_source(control_index)
+            .and_then(|source| self.gstreamer_manager.open_preview(control_index, source));
+        match result
+        {
+            Ok(()) => self.set_preview_controls(control_index, true),
+            Err(e) => nwg::simple_message("Error", &e),
+        }
+    }
+
+    /// Resets the rows whose preview window the user closed.
+    pub fn check_previews(&mut self) {
+        for control_index in 0..self.camera_controls.len() {
+            if self.gstreamer_manager.preview_closed(control_index) {
+                self.set_preview_controls(control_index, false);
+            }
+        }
+    }
+
+    pub fn close_previews(&mut self) {
+        for control_index in 0..self.camera_controls.len() {
+            self.gstreamer_manager.close_preview(control_index);
+        }
+    }
+
+    fn set_preview_controls(&self, control_index: usize, open: bool) {
+        let controls = &self.camera_controls[control_index];
+        if open {
+            controls.preview_button.set_text("Close");
+            controls.label.set_text("Preview");
+        } else {
+            controls.preview_button.set_text("Preview");
+            controls.label.set_text("");
+        }
+    }
+
     pub fn refresh_cameras(&mut self) {
         if let Err(e) = self.detect_cameras() {
             nwg::simple_message("Error", &format!("Failed to detect cameras: {}", e));
@@ -273,6 +988,7 @@ impl SenderApp {
     }
 
     pub fn cleanup(&mut self) {
+        self.close_previews();
         for i in 0..self.streaming.len() {
             if self.gstreamer_manager.is_streaming(i) {
                 self.gstreamer_manager.stop_pipeline(i);
@@ -280,3 +996,26 @@ impl SenderApp {
         }
     }
 }
+
+/// What a camera dropdown entry selects.
+#[derive(Clone)]
+enum SourceEntry {
+    /// The "Select Camera" placeholder.
+    None,
+    Listed(SourceKind),
+    /// Window capture; the title is typed below the dropdown.
+    Window,
+    /// An RTSP or MJPEG camera; the URL is typed below the dropdown.
+    Network,
+    /// Picked with a file dialog when selected.
+    File,
+    Slideshow,
+}
+
+/// Last component of a path, for showing in a dropdown.
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}